@@ -0,0 +1,28 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use kecs::World;
+
+const NUM_ENTITIES: usize = 1_000_000;
+
+struct Position(f32, f32);
+
+fn component_insertion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("component_insertion_1m");
+    group.sample_size(10);
+    group.bench_function("add_component", |b| {
+        b.iter(|| {
+            // Each new entity grows the component column by one element
+            let mut world = World::new();
+            let mut last = world.new_entity();
+            for _ in 0..NUM_ENTITIES {
+                last = world.new_entity();
+                world.add_component(last, Position(0.0, 0.0));
+            }
+            black_box(world.get_component::<Position>(last).map(|p| p.0 + p.1));
+            world
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, component_insertion);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use kecs::{Query, World};
+
+const NUM_ENTITIES: usize = 100_000;
+
+struct Position(f32, f32);
+struct Velocity(f32, f32);
+struct Marker;
+
+fn make_world() -> World {
+    let mut world = World::new();
+    for i in 0..NUM_ENTITIES {
+        let entity = world.new_entity();
+        world.add_component(entity, Position(0.0, 0.0));
+        world.add_component(entity, Velocity(1.0, 2.0));
+        // Spread the entities across two archetypes
+        if i % 2 == 0 {
+            world.add_component(entity, Marker);
+        }
+    }
+    world
+}
+
+fn query_iteration(c: &mut Criterion) {
+    let mut world = make_world();
+    world.add_system(
+        "iter_mut",
+        |mut query: Query<(&mut Position, &Velocity)>| {
+            for (position, velocity) in query.iter_mut() {
+                position.0 += velocity.0;
+                position.1 += velocity.1;
+            }
+        },
+    );
+    world.add_system(
+        "for_each_mut",
+        |mut query: Query<(&mut Position, &Velocity)>| {
+            query.for_each_mut(|(position, velocity)| {
+                position.0 += velocity.0;
+                position.1 += velocity.1;
+            });
+        },
+    );
+
+    let mut group = c.benchmark_group("query_iteration_100k");
+    group.bench_function("iter_mut", |b| b.iter(|| world.update("iter_mut")));
+    group.bench_function("for_each_mut", |b| b.iter(|| world.update("for_each_mut")));
+    group.finish();
+}
+
+criterion_group!(benches, query_iteration);
+criterion_main!(benches);
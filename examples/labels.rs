@@ -26,8 +26,8 @@ fn setup_entities(mut commands: Commands) {
     builder.build();
 }
 
-fn update_entities_position(query: Query<(&mut Position, &Velocity)>) {
-    for (pos, vel) in query.iter() {
+fn update_entities_position(mut query: Query<(&mut Position, &Velocity)>) {
+    for (pos, vel) in query.iter_mut() {
         pos.0[0] += vel.0[0];
         pos.0[1] += vel.0[1];
     }
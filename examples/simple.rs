@@ -16,8 +16,8 @@ struct Transform {
 }
 
 // A query is an iterator over the entities with the specified components
-fn update_bullet_position(query: Query<(&Bullet, &mut Transform)>) {
-    for (bullet, transform) in query.iter() {
+fn update_bullet_position(mut query: Query<(&Bullet, &mut Transform)>) {
+    for (bullet, transform) in query.iter_mut() {
         transform.position[0] += bullet.direction[0];
         transform.position[1] += bullet.direction[1];
     }
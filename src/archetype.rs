@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeSet, HashMap, HashSet},
     hash::{Hash, Hasher},
 };
 
@@ -14,8 +14,8 @@ pub struct ArchetypeId(usize);
 pub struct Archetype {
     /// The components that compose this archetype
     pub components: HashSet<ComponentId>,
-    /// Which entities are associated to this archetype
-    pub entities: SparseSet<Entity, ()>,
+    /// Which entities are associated to this archetype, kept ordered so that queries iterate them in a stable order
+    pub entities: BTreeSet<Entity>,
 }
 
 impl Archetype {
@@ -35,24 +35,52 @@ impl Archetype {
 #[derive(Default)]
 pub struct ArchetypeManager {
     archetypes: HashMap<ArchetypeId, Archetype>,
+    // The ids of the archetypes in the order they were created, so that the queries
+    // only need to check the archetypes created since their last update
+    archetype_ids: Vec<ArchetypeId>,
 }
 
 impl ArchetypeManager {
     /// Gets the archetype of this component set, creating it if it doesn't exists
     pub fn archetype_of<T>(&mut self, ids: &SparseSet<ComponentId, T>) -> ArchetypeId {
         let mut hasher = std::hash::DefaultHasher::new();
-        for (component, _) in ids.iter() {
+        // The components are hashed in a sorted order, so that the same set of components
+        // always gets the same archetype regardless of the order in which they were added
+        for (component, _) in ids.iter_sorted() {
             component.hash(&mut hasher);
         }
         let id = hasher.finish();
         let id = ArchetypeId(id as usize);
-        self.archetypes.entry(id).or_insert_with(|| Archetype {
-            components: ids.iter().map(|(i, _)| i).collect(),
-            entities: Default::default(),
-        });
+        if let Entry::Vacant(entry) = self.archetypes.entry(id) {
+            entry.insert(Archetype {
+                components: ids.iter().map(|(i, _)| i).collect(),
+                entities: Default::default(),
+            });
+            self.archetype_ids.push(id);
+        }
         id
     }
 
+    /// The number of archetypes created so far
+    pub fn len(&self) -> usize {
+        self.archetype_ids.len()
+    }
+
+    /// Returns true if no archetype was created yet
+    pub fn is_empty(&self) -> bool {
+        self.archetype_ids.is_empty()
+    }
+
+    // Iterates the archetypes created after the first `start` archetypes, in creation order
+    pub(crate) fn archetypes_from(
+        &self,
+        start: usize,
+    ) -> impl Iterator<Item = (ArchetypeId, &Archetype)> {
+        self.archetype_ids[start..]
+            .iter()
+            .map(|id| (*id, &self.archetypes[id]))
+    }
+
     /// Gets the archetype with this id if it exists
     pub fn get_archetype(&self, id: ArchetypeId) -> Option<&Archetype> {
         self.archetypes.get(&id)
@@ -62,3 +90,30 @@ impl ArchetypeManager {
         self.archetypes.get_mut(&id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{type_registrar::UniqueTypeId, ComponentId, SparseSet};
+
+    use super::ArchetypeManager;
+
+    #[test]
+    fn archetype_does_not_depend_on_insertion_order() {
+        let component_a = ComponentId(UniqueTypeId(0, "A"));
+        let component_b = ComponentId(UniqueTypeId(1, "B"));
+
+        let mut a_then_b = SparseSet::<ComponentId, ()>::new();
+        a_then_b.insert(component_a, ());
+        a_then_b.insert(component_b, ());
+
+        let mut b_then_a = SparseSet::<ComponentId, ()>::new();
+        b_then_a.insert(component_b, ());
+        b_then_a.insert(component_a, ());
+
+        let mut manager = ArchetypeManager::default();
+        assert_eq!(
+            manager.archetype_of(&a_then_b),
+            manager.archetype_of(&b_then_a)
+        );
+    }
+}
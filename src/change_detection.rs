@@ -0,0 +1,425 @@
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    query::{sealed, AccessMode, QueryFilter, QueryParam, ReadOnlyQueryParam},
+    sparse_set::SparseSet,
+    ComponentId, Entity, UnsafeWorldPtr, WorldContainer,
+};
+
+/// The ticks of a system's run: they are used to find out which components changed since the last time the system ran.
+/// The ticks are taken from the [`WorldContainer`]'s change tick, see [`WorldContainer::change_tick`]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct SystemTicks {
+    /// The tick of the previous run of the system, `0` if the system never ran before
+    pub last_run: u64,
+    /// The tick of the current run of the system
+    pub this_run: u64,
+}
+
+impl SystemTicks {
+    /// Returns `true` if something changed at `changed_tick` after the previous run of the system
+    pub fn is_changed(&self, changed_tick: u64) -> bool {
+        changed_tick > self.last_run
+    }
+}
+
+/// The ticks of a component of an [`Entity`], taken from the [`WorldContainer`]'s change tick:
+/// they can be compared to a [`SystemTicks`] to implement custom change detection, see [`crate::Query::iter_with_ticks`]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct ComponentTicks {
+    /// The tick at which the component was added to the entity
+    pub added: u64,
+    /// The tick at which the component was last changed, or added
+    pub changed: u64,
+}
+
+/// A [`QueryParam`] that provides non-mutable access to a component, like `&T`, and can tell
+/// if the component changed since the last time the system ran
+/// e.g
+/// ```
+/// use kecs::{Query, Ref};
+/// struct Health(u32);
+/// fn print_changed_health(query: Query<Ref<Health>>) {
+///     for health in query.iter() {
+///         if health.is_changed() {
+///             println!("Health is now {}", health.0);
+///         }
+///     }
+/// }
+/// ```
+pub struct Ref<'a, T: 'static> {
+    value: &'a T,
+    changed_tick: u64,
+    ticks: SystemTicks,
+}
+
+/// A [`QueryParam`] that provides mutable access to a component, like `&mut T`, and can tell
+/// if the component changed since the last time the system ran.
+/// Unlike `&mut T`, the component is marked as changed only when it is mutably dereferenced
+pub struct Mut<'a, T: 'static> {
+    value: &'a mut T,
+    changed_tick: &'a AtomicU64,
+    ticks: SystemTicks,
+}
+
+/// A system parameter used to identify a system with the type `H`, so that other systems can find out
+/// which components the system wrote with [`ChangedBy`]. `H` can be any type, usually an empty struct
+pub struct SystemHandle<H: 'static> {
+    pub(crate) _ph: PhantomData<fn() -> H>,
+}
+
+/// A [`QueryFilter`] matching the entities whose component `T` was written by the last run of the system
+/// identified by the [`SystemHandle<H>`], and not changed since then
+/// e.g
+/// ```
+/// use kecs::{ChangedBy, Entity, Query, SystemHandle, World};
+/// struct Mover;
+/// struct Position(f32);
+///
+/// fn move_first(_: SystemHandle<Mover>, mut query: Query<&mut Position>) {
+///     if let Some(position) = query.iter_mut().next() {
+///         position.0 += 1.0;
+///     }
+/// }
+/// fn print_moved(query: Query<Entity, ChangedBy<Mover, Position>>) {
+///     for entity in query.iter() {
+///         println!("{entity:?} was moved");
+///     }
+/// }
+/// let mut world = World::new();
+/// world.add_system(0, move_first);
+/// world.add_system(0, print_moved);
+/// world.update(0);
+/// ```
+pub struct ChangedBy<H: 'static, T: 'static> {
+    _ph: PhantomData<fn() -> (H, T)>,
+}
+
+impl<'a, T: 'static> Ref<'a, T> {
+    /// Returns `true` if the component changed since the last time the system ran
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.changed_tick)
+    }
+
+    /// Returns the tick at which the component was last changed
+    pub fn changed_tick(&self) -> u64 {
+        self.changed_tick
+    }
+
+    /// Consumes the [`Ref`], returning the inner reference
+    pub fn into_inner(self) -> &'a T {
+        self.value
+    }
+}
+
+impl<'a, T: 'static> Mut<'a, T> {
+    /// Returns `true` if the component changed since the last time the system ran
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.changed_tick())
+    }
+
+    /// Returns the tick at which the component was last changed
+    pub fn changed_tick(&self) -> u64 {
+        self.changed_tick.load(Ordering::Relaxed)
+    }
+
+    /// Marks the component as changed without mutably dereferencing it
+    pub fn set_changed(&mut self) {
+        self.changed_tick
+            .store(self.ticks.this_run, Ordering::Relaxed);
+    }
+}
+
+impl<'a, T: 'static> Deref for Ref<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<'a, T: 'static> Deref for Mut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<'a, T: 'static> DerefMut for Mut<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.set_changed();
+        self.value
+    }
+}
+
+impl<'a, A: 'static> sealed::Sealed for Ref<'a, A> {}
+impl<'a, A: 'static> ReadOnlyQueryParam for Ref<'a, A> {}
+
+impl<'a, A: 'static> QueryParam for Ref<'a, A> {
+    fn compute_component_set(
+        store: &mut WorldContainer,
+        component_set: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+        <&A as QueryParam>::compute_component_set(store, component_set)
+    }
+
+    fn can_extract(store: &WorldContainer, entity: Entity) -> bool {
+        store.entity_has_component::<A>(entity)
+    }
+
+    type Item<'q> = Ref<'q, A>;
+
+    unsafe fn extract<'q>(
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        let changed_tick = store.get_changed_tick::<A>(entity).load(Ordering::Relaxed);
+        Ref {
+            value: std::mem::transmute::<&A, &A>(store.get_component::<A>(entity).get()),
+            changed_tick,
+            ticks,
+        }
+    }
+
+    type Fetch = ComponentId;
+
+    fn init_fetch(store: &WorldContainer) -> Self::Fetch {
+        store.get_component_id_assertive::<A>()
+    }
+
+    unsafe fn extract_fetched<'q>(
+        fetch: Self::Fetch,
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        let changed_tick = store
+            .get_changed_tick_by_id(entity, fetch)
+            .load(Ordering::Relaxed);
+        Ref {
+            value: std::mem::transmute::<&A, &A>(
+                store.get_component_by_id::<A>(entity, fetch).get(),
+            ),
+            changed_tick,
+            ticks,
+        }
+    }
+}
+
+impl<'a, A: 'static> QueryParam for Mut<'a, A> {
+    fn compute_component_set(
+        store: &mut WorldContainer,
+        component_set: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+        <&mut A as QueryParam>::compute_component_set(store, component_set)
+    }
+
+    fn can_extract(store: &WorldContainer, entity: Entity) -> bool {
+        store.entity_has_component::<A>(entity)
+    }
+
+    type Item<'q> = Mut<'q, A>;
+
+    unsafe fn extract<'q>(
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        Mut {
+            value: std::mem::transmute::<&mut A, &mut A>(
+                store.get_component_mut::<A>(entity).into_mut(),
+            ),
+            changed_tick: std::mem::transmute::<&AtomicU64, &AtomicU64>(
+                store.get_changed_tick::<A>(entity),
+            ),
+            ticks,
+        }
+    }
+
+    type Fetch = ComponentId;
+
+    fn init_fetch(store: &WorldContainer) -> Self::Fetch {
+        store.get_component_id_assertive::<A>()
+    }
+
+    unsafe fn extract_fetched<'q>(
+        fetch: Self::Fetch,
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        Mut {
+            value: std::mem::transmute::<&mut A, &mut A>(
+                store.get_component_mut_by_id::<A>(entity, fetch).into_mut(),
+            ),
+            changed_tick: std::mem::transmute::<&AtomicU64, &AtomicU64>(
+                store.get_changed_tick_by_id(entity, fetch),
+            ),
+            ticks,
+        }
+    }
+}
+
+impl<H: 'static, T: 'static> QueryFilter for ChangedBy<H, T> {
+    fn compute_component_set(
+        store: &mut WorldContainer,
+        component_set: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+        // The component may already be accessed by the query parameters
+        let id = store.get_or_create_component_id::<T>();
+        if !component_set.contains(&id) {
+            component_set.insert(id, AccessMode::Read);
+        }
+    }
+
+    unsafe fn matches(store: &UnsafeWorldPtr, entity: Entity, _ticks: SystemTicks) -> bool {
+        let Some(handle_tick) = store.get().get_system_handle_tick::<H>() else {
+            return false;
+        };
+        handle_tick != 0
+            && store.get_changed_tick::<T>(entity).load(Ordering::Relaxed) == handle_tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use crate::{ChangedBy, Entity, Mut, Query, Ref, SystemHandle, World};
+
+    struct Health(u32);
+
+    #[test]
+    fn ref_is_changed() {
+        let mut world = World::new();
+        let entity_a = world.new_entity();
+        world.add_component(entity_a, Health(10));
+        let entity_b = world.new_entity();
+        world.add_component(entity_b, Health(20));
+
+        let changed = Arc::<RwLock<Vec<Entity>>>::default();
+        let changed_2 = changed.clone();
+        world.add_system(0, move |query: Query<(Entity, Ref<Health>)>| {
+            let mut changed = changed_2.write().unwrap();
+            changed.clear();
+            changed.extend(
+                query
+                    .iter()
+                    .filter(|(_, health)| health.is_changed())
+                    .map(|(e, _)| e),
+            );
+        });
+
+        // Everything is changed during the first run
+        world.update(0);
+        assert_eq!(*changed.read().unwrap(), vec![entity_a, entity_b]);
+
+        world.update(0);
+        assert!(changed.read().unwrap().is_empty());
+
+        world.get_component_mut::<Health>(entity_b).unwrap().0 = 5;
+        world.update(0);
+        assert_eq!(*changed.read().unwrap(), vec![entity_b]);
+    }
+
+    #[test]
+    fn mut_sets_changed_on_deref_mut() {
+        let mut world = World::new();
+        let entity_a = world.new_entity();
+        world.add_component(entity_a, Health(10));
+        let entity_b = world.new_entity();
+        world.add_component(entity_b, Health(20));
+
+        // Only damage the entities with more than 15 health
+        world.add_system(0, |mut query: Query<Mut<Health>>| {
+            for mut health in query.iter_mut() {
+                if health.0 > 15 {
+                    health.0 -= 1;
+                }
+            }
+        });
+
+        let changed = Arc::<RwLock<Vec<Entity>>>::default();
+        let changed_2 = changed.clone();
+        world.add_system(0, move |query: Query<(Entity, Ref<Health>)>| {
+            let mut changed = changed_2.write().unwrap();
+            changed.clear();
+            changed.extend(
+                query
+                    .iter()
+                    .filter(|(_, health)| health.is_changed())
+                    .map(|(e, _)| e),
+            );
+        });
+
+        world.update(0);
+        world.update(0);
+        assert_eq!(*changed.read().unwrap(), vec![entity_b]);
+        assert_eq!(world.get_component::<Health>(entity_a).unwrap().0, 10);
+        assert_eq!(world.get_component::<Health>(entity_b).unwrap().0, 18);
+    }
+
+    #[test]
+    fn changed_by_system() {
+        struct Mover;
+        struct Position(u32);
+
+        let mut world = World::new();
+        let entities: Vec<_> = (0..6)
+            .map(|i| {
+                let entity = world.new_entity();
+                world.add_component(entity, Position(i));
+                entity
+            })
+            .collect();
+
+        // Moves the entities with an even position, while there's any
+        world.add_system(
+            0,
+            |_: SystemHandle<Mover>, mut query: Query<Mut<Position>>| {
+                for mut position in query.iter_mut() {
+                    if position.0 % 2 == 0 && position.0 < 10 {
+                        position.0 += 2;
+                    }
+                }
+            },
+        );
+        // Another system writing to Position
+        world.add_system(0, |mut query: Query<(Entity, Mut<Position>)>| {
+            for (_, mut position) in query.iter_mut().filter(|(_, p)| p.0 == 1) {
+                position.0 = 1;
+            }
+        });
+
+        let moved = Arc::<RwLock<Vec<Entity>>>::default();
+        let moved_2 = moved.clone();
+        world.add_system(
+            0,
+            move |query: Query<Entity, ChangedBy<Mover, Position>>| {
+                *moved_2.write().unwrap() = query.iter().collect();
+            },
+        );
+
+        world.update(0);
+        assert_eq!(
+            *moved.read().unwrap(),
+            vec![entities[0], entities[2], entities[4]]
+        );
+
+        // Only the entities still below 10 are moved
+        world.update(0);
+        world.update(0);
+        world.update(0);
+        assert_eq!(*moved.read().unwrap(), vec![entities[0], entities[2]]);
+
+        world.update(0);
+        world.update(0);
+        assert!(moved.read().unwrap().is_empty());
+    }
+}
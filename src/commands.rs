@@ -1,17 +1,23 @@
 use std::{
     any::{type_name, TypeId},
     collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 
-use crossbeam::channel::{unbounded, Receiver, Sender};
+use crossbeam::channel::{unbounded, Receiver, SendError, Sender};
 
 use crate::{
-    entity_manager::EntityAllocator, erased_data_vec::ErasedVec, Entity, Resource, WorldContainer,
+    entity_manager::EntityAllocator, erased_data_vec::ErasedVec, ComponentId, Entity, Resource,
+    WorldContainer,
 };
 
 /// [`Commands`] is a system parameter used to queue operations that change the state of the world, such as
 /// spawning a new [`crate::Entity`] or adding a component to an entity. During an update loop (read: frame),
-/// all the commands are queue and then executed at the beginning of the frame
+/// all the commands are queue and then executed at the beginning of the frame.
+/// The commands of the systems running in parallel are executed in the order the systems were added
 pub struct Commands<'world> {
     sender: CommandsSender,
     entity_allocator: &'world EntityAllocator,
@@ -35,6 +41,13 @@ impl<'world> Commands<'world> {
         }
     }
 
+    // The commands created by a system, see CommandsSender::new_source
+    pub(crate) fn with_source(world: &'world WorldContainer, source: u64) -> Self {
+        let mut commands = Self::new(world);
+        commands.sender.source = source;
+        commands
+    }
+
     /// Starts an [`EntityBuilder`] instance
     pub fn spawn_entity<'c>(&'c mut self) -> EntityBuilder<'c, 'world>
     where
@@ -49,7 +62,6 @@ impl<'world> Commands<'world> {
     /// Destroys the given entity if it exists
     pub fn destroy_entity(&mut self, entity: Entity) {
         self.sender
-            .inner
             .send(CommandType::DestroyEntity { entity })
             .expect("Failed to send DestroyEntity command");
     }
@@ -57,7 +69,6 @@ impl<'world> Commands<'world> {
     /// Adds a new component to an existing entity, replacing any old ones of the same type
     pub fn add_component<T: 'static>(&mut self, entity: Entity, component: T) {
         self.sender
-            .inner
             .send(CommandType::AddComponent {
                 entity,
                 component: TypedBlob::new(component),
@@ -68,7 +79,6 @@ impl<'world> Commands<'world> {
     /// Removes a component from an entity if it exists
     pub fn remove_component<T: 'static>(&mut self, entity: Entity) {
         self.sender
-            .inner
             .send(CommandType::RemoveComponent {
                 entity,
                 component_ty: TypeId::of::<T>(),
@@ -77,26 +87,67 @@ impl<'world> Commands<'world> {
             .expect("Failed to send RemoveComponent command");
     }
 
-    /// Adds a new resource, replacing the old value if it does not exists
+    /// Spawns or overwrites entities with specific ids, e.g to replicate the entities received from a server.
+    /// When the commands are executed, the entities that don't exist are spawned with their exact id and generation,
+    /// while the existing ones get the components of their [`Bundle`], replacing any old ones of the same type.
+    /// A live entity using the same id of a spawned entity, but with an older generation, is destroyed,
+    /// while the entities older than the live ones are skipped, see [`crate::KecsWorld::get_or_spawn`]
+    pub fn insert_or_spawn_batch<B: Bundle>(
+        &mut self,
+        iter: impl IntoIterator<Item = (Entity, B)>,
+    ) {
+        let entities = iter
+            .into_iter()
+            .map(|(entity, bundle)| {
+                let mut builder = EntityBuilder {
+                    commands: &mut *self,
+                    components: Default::default(),
+                };
+                bundle.add_to_builder(&mut builder);
+                (entity, builder.components)
+            })
+            .collect();
+        self.sender
+            .send(CommandType::InsertOrSpawnBatch { entities })
+            .expect("Failed to send InsertOrSpawnBatch command");
+    }
+
+    /// Queues a closure that changes the world in ways not covered by the other commands, e.g swapping two components.
+    /// The closure runs on the main thread when the commands are executed
+    pub fn add(&mut self, f: impl FnOnce(&mut WorldContainer) + Send + 'static) {
+        self.sender
+            .send(CommandType::Custom(Box::new(f)))
+            .expect("Failed to send Custom command");
+    }
+
+    /// Adds a new resource, replacing the old value if it does not exists.
+    /// The resource can be accessed by systems running in parallel: this is safe, since [`Resource`] requires `Send + Sync`
     pub fn add_resource<R: 'static + Resource + Send>(&mut self, resource: R) {
         self.sender
-            .inner
             .send(CommandType::AddResource {
                 resource: TypedBlob::new(resource),
                 send: true,
             })
             .expect("Failed to send AddResource command");
     }
-    /// Adds a new resource, replacing the old value if it does not exists
+    /// Adds a new resource that is only accessed on the main thread, replacing the old value if it does not exists
     pub fn add_non_send_resource<R: 'static + Resource>(&mut self, resource: R) {
         self.sender
-            .inner
             .send(CommandType::AddResource {
                 resource: TypedBlob::new(resource),
                 send: false,
             })
             .expect("Failed to send AddResource command");
     }
+
+    /// Removes a resource if it exists
+    pub fn remove_resource<R: 'static + Resource>(&mut self) {
+        self.sender
+            .send(CommandType::RemoveResource {
+                component_ty: TypeId::of::<R>(),
+            })
+            .expect("Failed to send RemoveResource command");
+    }
 }
 
 impl<'c, 'world> EntityBuilder<'c, 'world>
@@ -105,24 +156,20 @@ where
 {
     /// Adds a new component to the new entity
     pub fn with_component<T: 'static>(&mut self, component: T) -> &mut Self {
-        let entry = self
-            .components
-            .insert(TypeId::of::<T>(), TypedBlob::new(component));
-        if let Some(entry) = entry {
-            unsafe {
-                // We know that the component is of the correct type
-                entry.data.drop_at(0);
-            }
-        }
+        self.with_blob(TypedBlob::new(component));
         self
     }
 
+    fn with_blob(&mut self, component: TypedBlob) {
+        // The replaced component, if any, is dropped with its blob
+        self.components.insert(component.blob_ty_id, component);
+    }
+
     /// Sends the command and returns the new entity id
     pub fn build(self) -> Entity {
         let entity = self.commands.entity_allocator.allocate_id();
         self.commands
             .sender
-            .inner
             .send(CommandType::NewEntity {
                 entity,
                 components: self.components,
@@ -132,25 +179,124 @@ where
     }
 }
 
-pub(crate) struct TypedBlob {
+/// A [`Bundle`] is a group of components that can be added to an entity at once, e.g a tuple of components.
+/// Structs with one component per field can implement [`Bundle`] through the [`crate::bundle`] macro
+pub trait Bundle: 'static {
+    /// Gets the ids of the components of the bundle, registering them if needed.
+    /// The ids must be in the same order in which the components are passed by [`Bundle::take_components`]
+    fn component_ids(world: &mut WorldContainer) -> Vec<ComponentId>;
+
+    /// Moves each component of the bundle into `f`, e.g by calling `f(TypedBlob::new(self.field))` for each field
+    fn take_components(self, f: impl FnMut(TypedBlob));
+
+    /// Adds all the components of the bundle to the [`EntityBuilder`]
+    fn add_to_builder(self, builder: &mut EntityBuilder<'_, '_>)
+    where
+        Self: Sized,
+    {
+        self.take_components(|component| builder.with_blob(component));
+    }
+}
+
+/// Declares a struct whose fields are the components of a [`Bundle`], e.g
+/// ```
+/// use kecs::{bundle, World};
+/// struct Transform([f32; 3]);
+/// struct Player;
+///
+/// bundle! {
+///     struct PlayerBundle {
+///         transform: Transform,
+///         player: Player,
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// let entity = world.new_entity();
+/// world.add_bundle(entity, PlayerBundle { transform: Transform([0.0; 3]), player: Player });
+/// assert!(world.get_component::<Player>(entity).is_some());
+/// ```
+#[macro_export]
+macro_rules! bundle {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty,)*
+        }
+
+        impl $crate::Bundle for $name {
+            fn component_ids(world: &mut $crate::WorldContainer) -> Vec<$crate::ComponentId> {
+                vec![$(world.get_or_create_component_id::<$ty>()),*]
+            }
+
+            fn take_components(self, mut f: impl FnMut($crate::TypedBlob)) {
+                $(f($crate::TypedBlob::new(self.$field));)*
+            }
+        }
+    };
+}
+
+macro_rules! impl_bundle_for_tuple {
+    ($($t:ident)*) => {
+        impl<$($t: 'static,)*> Bundle for ($($t,)*) {
+            fn component_ids(world: &mut WorldContainer) -> Vec<ComponentId> {
+                vec![$(world.get_or_create_component_id::<$t>()),*]
+            }
+
+            #[allow(non_snake_case)]
+            fn take_components(self, mut f: impl FnMut(TypedBlob)) {
+                let ($($t,)*) = self;
+                $(f(TypedBlob::new($t));)*
+            }
+        }
+    };
+}
+
+impl_bundle_for_tuple!(A);
+impl_bundle_for_tuple!(A B);
+impl_bundle_for_tuple!(A B C);
+impl_bundle_for_tuple!(A B C D);
+impl_bundle_for_tuple!(A B C D E);
+impl_bundle_for_tuple!(A B C D E F);
+impl_bundle_for_tuple!(A B C D E F G);
+impl_bundle_for_tuple!(A B C D E F G H);
+
+/// A single type-erased component, moved out of a [`Bundle`]
+pub struct TypedBlob {
     pub(crate) blob_ty_id: TypeId,
     pub(crate) data: ErasedVec,
     pub(crate) type_name: Option<&'static str>,
 }
 
 impl TypedBlob {
-    fn new<T: 'static>(data: T) -> Self {
-        let vec = unsafe {
-            let mut vec = ErasedVec::new_typed::<T>(true, 1);
-            vec.push_back(data);
-            vec
-        };
+    /// Takes ownership of the component
+    pub fn new<T: 'static>(data: T) -> Self {
         Self {
             blob_ty_id: TypeId::of::<T>(),
-            data: vec,
+            data: unsafe { ErasedVec::from_iter_typed(std::iter::once(data)) },
             type_name: Some(type_name::<T>()),
         }
     }
+
+    // Takes the data out of the blob without dropping the component: the caller must move the component out
+    // of the returned ErasedVec (e.g by copying it into a storage) or drop it
+    pub(crate) fn into_data(self) -> ErasedVec {
+        let blob = std::mem::ManuallyDrop::new(self);
+        // SAFETY: The blob is never used again, so the data is moved out only once
+        unsafe { std::ptr::read(&blob.data) }
+    }
+}
+
+impl Drop for TypedBlob {
+    fn drop(&mut self) {
+        // The component was never moved into the world, e.g the command was never executed
+        self.data.clear();
+    }
 }
 
 pub(crate) enum CommandType {
@@ -174,15 +320,34 @@ pub(crate) enum CommandType {
         resource: TypedBlob,
         send: bool,
     },
+    RemoveResource {
+        component_ty: TypeId,
+    },
+    InsertOrSpawnBatch {
+        entities: Vec<(Entity, HashMap<TypeId, TypedBlob>)>,
+    },
+    Custom(Box<dyn FnOnce(&mut WorldContainer) + Send>),
+}
+
+// The commands are applied ordered by batch, and then by the source that sent them: the systems that may run
+// in parallel share the same batch, so the order of their commands doesn't depend on which one ran first
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct CommandKey {
+    batch: u64,
+    source: u64,
 }
 
 pub(crate) struct CommandsReceiver {
-    receiver: Receiver<CommandType>,
+    receiver: Receiver<(CommandKey, CommandType)>,
 }
 
 #[derive(Clone)]
 pub(crate) struct CommandsSender {
-    inner: Sender<CommandType>,
+    inner: Sender<(CommandKey, CommandType)>,
+    batch: Arc<AtomicU64>,
+    next_source: Arc<AtomicU64>,
+    // 0 when the commands are not sent by a system
+    source: u64,
 }
 
 impl<'world> Commands<'world> {
@@ -190,28 +355,172 @@ impl<'world> Commands<'world> {
         let (sender, receiver) = unbounded();
 
         (
-            CommandsSender { inner: sender },
+            CommandsSender {
+                inner: sender,
+                batch: Default::default(),
+                next_source: Arc::new(AtomicU64::new(1)),
+                source: 0,
+            },
             CommandsReceiver { receiver },
         )
     }
 }
 
+impl CommandsSender {
+    fn send(&self, command: CommandType) -> Result<(), SendError<(CommandKey, CommandType)>> {
+        let key = CommandKey {
+            batch: self.batch.load(Ordering::Relaxed),
+            source: self.source,
+        };
+        self.inner.send((key, command))
+    }
+
+    /// Creates the source of the commands sent by a system: the sources are ordered by creation,
+    /// i.e by the order in which the systems were added
+    pub(crate) fn new_source(&self) -> u64 {
+        self.next_source.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Called by the schedulers after running the systems that may run in parallel with each other,
+    /// so that the commands sent afterwards are applied after theirs
+    pub(crate) fn finish_batch(&self) {
+        self.batch.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 impl CommandsReceiver {
-    pub(crate) fn try_get(&mut self) -> Option<CommandType> {
-        self.receiver.try_recv().ok()
+    /// The number of queued commands, without taking them
+    pub(crate) fn len(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Takes all the queued commands, ordered by batch and source.
+    /// The commands sent by the same source stay in the order they were sent
+    pub(crate) fn drain(&mut self) -> Vec<CommandType> {
+        // Commands sent from parallel systems can reach the channel in any order
+        let mut commands: Vec<_> = self.receiver.try_iter().collect();
+        commands.sort_by_key(|(key, _)| *key);
+        commands.into_iter().map(|(_, command)| command).collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::{Arc, RwLock};
-
-    use crate::{commands::Commands, Entity, Query, Res, ResMut, Resource, World};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, RwLock,
+        },
+        time::Duration,
+    };
+
+    use crate::{
+        commands::Commands, Bundle, Entity, GraphScheduler, Query, Res, ResMut, Resource,
+        Scheduler, World, WorldContainer,
+    };
 
     fn make_world() -> World {
         World::new()
     }
 
+    #[derive(Debug, PartialEq)]
+    struct Transform(u32);
+    #[derive(Debug, PartialEq)]
+    struct Player(&'static str);
+
+    crate::bundle! {
+        struct PlayerBundle {
+            transform: Transform,
+            player: Player,
+        }
+    }
+
+    #[test]
+    fn custom_command() {
+        let mut world = make_world();
+        let first = world.new_entity();
+        world.add_component(first, Transform(1));
+        let second = world.new_entity();
+        world.add_component(second, Player("second"));
+
+        world.add_system(0, move |mut commands: Commands| {
+            commands.add(move |world| {
+                world.entity_mut(first).unwrap().remove::<Transform>();
+                world.entity_mut(second).unwrap().insert(Transform(1));
+            });
+        });
+        let seen = Arc::new(RwLock::new(vec![]));
+        let seen_clone = seen.clone();
+        world.add_system(1, move |query: Query<(&Transform, &Player)>| {
+            seen_clone.write().unwrap().extend(
+                query
+                    .iter()
+                    .map(|(transform, player)| (transform.0, player.0)),
+            );
+        });
+
+        world.update(0);
+        world.update(0);
+        assert!(world.get_component::<Transform>(first).is_none());
+        world.update(1);
+        assert_eq!(*seen.read().unwrap(), vec![(1, "second")]);
+    }
+
+    #[test]
+    fn struct_bundle() {
+        let mut container = WorldContainer::new(Commands::create().0);
+        let ids = PlayerBundle::component_ids(&mut container);
+        assert_eq!(
+            ids,
+            vec![
+                container.get_component_id_assertive::<Transform>(),
+                container.get_component_id_assertive::<Player>()
+            ]
+        );
+
+        let mut world = make_world();
+        let entity = world.new_entity();
+        world.add_bundle(
+            entity,
+            PlayerBundle {
+                transform: Transform(1),
+                player: Player("one"),
+            },
+        );
+        assert_eq!(
+            world.get_component::<Transform>(entity),
+            Some(&Transform(1))
+        );
+        assert_eq!(world.get_component::<Player>(entity), Some(&Player("one")));
+
+        world.add_bundle(entity, (Transform(2),));
+        assert_eq!(
+            world.get_component::<Transform>(entity),
+            Some(&Transform(2))
+        );
+
+        let spawned = Entity(10, 0);
+        world.add_system(0, move |mut commands: Commands| {
+            commands.insert_or_spawn_batch([(
+                spawned,
+                PlayerBundle {
+                    transform: Transform(3),
+                    player: Player("three"),
+                },
+            )]);
+        });
+        world.update(0);
+        world.update(0);
+        assert_eq!(
+            world.get_component::<Transform>(spawned),
+            Some(&Transform(3))
+        );
+        assert_eq!(
+            world.get_component::<Player>(spawned),
+            Some(&Player("three"))
+        );
+    }
+
     #[test]
     fn spawn_entity() {
         let mut world = make_world();
@@ -285,6 +594,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parallel_commands_are_ordered_by_system() {
+        #[derive(Debug, PartialEq)]
+        struct Value(u32);
+
+        let mut world = make_world();
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        world.set_scheduler(0, GraphScheduler::with_thread_pool(Arc::new(thread_pool)));
+        let entity = world.new_entity();
+
+        // The second system sends its commands first: the systems don't access any component, so they
+        // run in the same parallel group, and the commands of the first one must still be applied first
+        let (sent, wait_sent) = crossbeam::channel::bounded(1);
+        world.add_system(0, move |mut commands: Commands| {
+            // Don't hang if the systems are run on a single thread
+            let _ = wait_sent.recv_timeout(Duration::from_secs(5));
+            commands.add_component(entity, Value(1));
+        });
+        world.add_system(0, move |mut commands: Commands| {
+            commands.add_component(entity, Value(2));
+            sent.send(()).unwrap();
+        });
+        assert_eq!(world.scheduler(0).max_parallelism(), 2);
+
+        world.update(0);
+        world.flush();
+        assert_eq!(world.get_component::<Value>(entity), Some(&Value(2)));
+    }
+
     #[test]
     fn destroy_entity() {
         let mut world = make_world();
@@ -314,8 +655,8 @@ mod tests {
         );
         world.add_system(
             0,
-            move |bullets: Query<(Entity, &mut Bullet)>, mut commands: Commands| {
-                for (entity, bullet) in bullets.iter() {
+            move |mut bullets: Query<(Entity, &mut Bullet)>, mut commands: Commands| {
+                for (entity, bullet) in bullets.iter_mut() {
                     if bullet.ticks_alive == 0 {
                         let mut destroyed = destroy_counter_2.write().unwrap();
                         *destroyed += 1;
@@ -334,6 +675,58 @@ mod tests {
         assert_eq!(*destroy_counter.read().unwrap(), 10);
     }
 
+    #[test]
+    fn add_resources() {
+        let mut world = make_world();
+
+        struct Score(u32);
+        impl Resource for Score {}
+        struct Window(u32);
+        impl Resource for Window {}
+
+        world.add_system(0, |mut commands: Commands| {
+            commands.add_resource(Score(10));
+            commands.add_non_send_resource(Window(20));
+        });
+        world.update(0);
+        world.flush();
+
+        assert_eq!(world.get_resource::<Score>().unwrap().0, 10);
+        assert_eq!(world.get_resource::<Window>().unwrap().0, 20);
+    }
+
+    #[test]
+    fn remove_resource() {
+        let mut world = make_world();
+
+        struct Session(u32);
+        impl Resource for Session {}
+        struct Missing;
+        impl Resource for Missing {}
+
+        world.add_resource(Session(42));
+        world.add_system(0, |mut commands: Commands| {
+            commands.remove_resource::<Session>();
+            // Removing an absent resource does nothing
+            commands.remove_resource::<Missing>();
+        });
+
+        // The resource is removed only when the commands are executed
+        world.update(0);
+        assert_eq!(world.get_resource::<Session>().unwrap().0, 42);
+
+        world.update(0);
+        assert!(world.get_resource::<Session>().is_none());
+        assert!(world.get_resource::<Missing>().is_none());
+
+        world.commands().add_resource(Session(1));
+        world.flush();
+        assert_eq!(world.get_resource::<Session>().unwrap().0, 1);
+        world.update(0);
+        world.flush();
+        assert!(world.get_resource::<Session>().is_none());
+    }
+
     #[test]
     fn remove_component() {
         let mut world = make_world();
@@ -381,4 +774,205 @@ mod tests {
         world.update(0);
         assert_eq!(get_counter(), 0);
     }
+
+    #[test]
+    fn insert_or_spawn_batch() {
+        #[derive(Debug, PartialEq)]
+        struct Position(i32);
+        #[derive(Debug, PartialEq)]
+        struct Velocity(i32);
+
+        let mut world = make_world();
+        let existing = world.new_entity();
+        world.add_component(existing, Position(0));
+        let destroyed = world.new_entity();
+        world.destroy_entity(destroyed);
+
+        let far_away = Entity(5, 3);
+        let reused = Entity(destroyed.0, destroyed.1 + 4);
+        world.commands().insert_or_spawn_batch([
+            (existing, (Position(1), Velocity(1))),
+            (far_away, (Position(2), Velocity(2))),
+            (reused, (Position(3), Velocity(3))),
+        ]);
+        world.update(0);
+
+        assert_eq!(
+            world.get_component::<Position>(existing),
+            Some(&Position(1))
+        );
+        assert_eq!(
+            world.get_component::<Velocity>(existing),
+            Some(&Velocity(1))
+        );
+        assert!(world.contains(far_away));
+        assert_eq!(
+            world.get_component::<Position>(far_away),
+            Some(&Position(2))
+        );
+        assert!(world.contains(reused));
+        assert!(!world.contains(destroyed));
+        assert_eq!(world.get_component::<Velocity>(reused), Some(&Velocity(3)));
+
+        let count =
+            world.run_oneshot_with(|query: Query<(&Position, &Velocity)>| query.iter().count());
+        assert_eq!(count, 3);
+
+        // A stale id doesn't replace the newer live entity
+        let warnings = Arc::new(AtomicUsize::new(0));
+        let warnings_2 = warnings.clone();
+        world.set_warning_handler(move |_| {
+            warnings_2.fetch_add(1, Ordering::Relaxed);
+        });
+        world
+            .commands()
+            .insert_or_spawn_batch([(Entity(5, 1), (Position(4), Velocity(4)))]);
+        world.update(0);
+        assert!(!world.contains(Entity(5, 1)));
+        assert_eq!(warnings.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            world.get_component::<Position>(far_away),
+            Some(&Position(2))
+        );
+
+        // The reserved ids are never allocated again, while the skipped ones can still be used
+        let new_entities: Vec<_> = (0..4).map(|_| world.new_entity()).collect();
+        for entity in &new_entities {
+            assert!(![existing.0, far_away.0, reused.0].contains(&entity.0));
+        }
+        world.add_component(new_entities[3], Position(4));
+        assert_eq!(
+            world.get_component::<Position>(far_away),
+            Some(&Position(2))
+        );
+    }
+
+    #[test]
+    fn get_component_with_queued_destroy() {
+        struct A;
+
+        let mut world = make_world();
+        let entity = world.new_entity();
+        world.add_component(entity, A);
+        world.add_system(0, move |world: &mut WorldContainer| {
+            world.commands().destroy_entity(entity);
+            // The entity is destroyed only when the commands are executed
+            assert!(world.get_component::<A>(entity).is_some());
+            assert!(world.get_component_mut::<A>(entity).is_some());
+        });
+        world.update(0);
+        world.flush();
+
+        assert!(world.get_component::<A>(entity).is_none());
+        assert!(world.get_component_mut::<A>(entity).is_none());
+        world.destroy_entity(entity);
+        assert!(world.get_component::<A>(entity).is_none());
+    }
+
+    #[test]
+    fn remove_component_of_destroyed_entity() {
+        struct A;
+
+        let mut world = make_world();
+        let entity = world.new_entity();
+        world.add_component(entity, A);
+        let other = world.new_entity();
+        world.add_component(other, A);
+
+        // The entity is destroyed before the component is removed, e.g by two systems racing in one frame
+        world.add_system(0, move |mut commands: Commands| {
+            commands.destroy_entity(entity);
+            commands.remove_component::<A>(entity);
+        });
+        world.update(0);
+        world.flush();
+
+        assert!(!world.contains(entity));
+        assert!(world.get_component::<A>(other).is_some());
+    }
+
+    #[test]
+    fn add_component_to_destroyed_entity() {
+        struct Tracked(Arc<RwLock<usize>>);
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                *self.0.write().unwrap() += 1;
+            }
+        }
+
+        let drops = Arc::<RwLock<usize>>::default();
+        let mut world = make_world();
+        let entity = world.new_entity();
+        let mut commands = world.commands();
+        commands.destroy_entity(entity);
+        commands.add_component(entity, Tracked(drops.clone()));
+        world.flush();
+
+        // The component is dropped instead of being leaked
+        assert!(!world.contains(entity));
+        assert_eq!(*drops.read().unwrap(), 1);
+    }
+
+    #[test]
+    fn typed_blobs_are_dropped_once() {
+        struct Tracked(Arc<RwLock<usize>>);
+        impl Resource for Tracked {}
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                *self.0.write().unwrap() += 1;
+            }
+        }
+
+        let drops = Arc::<RwLock<usize>>::default();
+        let mut world = make_world();
+
+        // The overwritten component is dropped right away, the other one once it's in the world
+        let mut commands = world.commands();
+        let mut builder = commands.spawn_entity();
+        builder.with_component(Tracked(drops.clone()));
+        builder.with_component(Tracked(drops.clone()));
+        let entity = builder.build();
+        assert_eq!(*drops.read().unwrap(), 1);
+        world.flush();
+        assert_eq!(*drops.read().unwrap(), 1);
+        world.destroy_entity(entity);
+        assert_eq!(*drops.read().unwrap(), 2);
+
+        // A replaced resource is dropped once, and the new one is moved into the world
+        world.commands().add_resource(Tracked(drops.clone()));
+        world.commands().add_resource(Tracked(drops.clone()));
+        world.flush();
+        assert_eq!(*drops.read().unwrap(), 3);
+        assert!(world.remove_resource::<Tracked>().is_some());
+        assert_eq!(*drops.read().unwrap(), 4);
+
+        // The components of the commands that were never executed are dropped with the world
+        world.commands().add_resource(Tracked(drops.clone()));
+        let mut commands = world.commands();
+        commands
+            .spawn_entity()
+            .with_component(Tracked(drops.clone()));
+        drop(world);
+        assert_eq!(*drops.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn pending_command_count() {
+        struct Enemy;
+
+        let mut world = make_world();
+        world.debug_assert_no_pending_commands();
+
+        let mut commands = world.commands();
+        let mut builder = commands.spawn_entity();
+        builder.with_component(Enemy);
+        let entity = builder.build();
+        commands.destroy_entity(entity);
+        assert_eq!(world.pending_command_count(), 2);
+
+        world.flush();
+        assert_eq!(world.pending_command_count(), 0);
+        world.debug_assert_no_pending_commands();
+        assert!(!world.contains(entity));
+    }
 }
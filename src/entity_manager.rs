@@ -6,8 +6,22 @@ use crate::{ArchetypeId, ComponentId, SparseSet};
 /// on which [`crate::System`]s operate: they are implemented as an integer, which uniquely identifies the components
 /// associated to the Entity
 #[derive(Default, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity(pub(crate) u32, pub(crate) u32);
 
+impl Entity {
+    /// Packs the entity into a single `u64`, with the index in the low 32 bits and the generation in the high 32 bits:
+    /// this is useful e.g to key external maps by entity, or to send the entity over the network
+    pub fn to_bits(self) -> u64 {
+        (self.1 as u64) << 32 | self.0 as u64
+    }
+
+    /// Unpacks an entity packed with [`Entity::to_bits`]
+    pub fn from_bits(bits: u64) -> Self {
+        Self(bits as u32, (bits >> 32) as u32)
+    }
+}
+
 /// Holds all the informations about an entity, such as its ArchetypeId and the entity's components
 #[derive(Default, Clone, Debug)]
 pub struct EntityInfo {
@@ -23,11 +37,24 @@ pub struct EntityInfo {
 #[derive(Default)]
 pub(crate) struct EntityAllocator {
     next_entity_id: AtomicU32,
+    // The lowest index that can be allocated, see Self::with_id_range
+    first_entity_id: u32,
     entity_info: SparseSet<Entity, EntityInfo>,
     dropped_entities: RwLock<Vec<Entity>>,
 }
 
 impl EntityAllocator {
+    /// Creates an allocator that never allocates an index below `start`. The entities spawned with an exact id below
+    /// `start` (e.g replicated from a server) are not recycled when destroyed, while the recycled indices in the range
+    /// keep their index and bump their generation
+    pub fn with_id_range(start: u32) -> Self {
+        Self {
+            next_entity_id: AtomicU32::new(start),
+            first_entity_id: start,
+            ..Default::default()
+        }
+    }
+
     pub fn new_entity(&mut self) -> Entity {
         let id = self.allocate_id();
         self.new_with_id(id);
@@ -61,12 +88,37 @@ impl EntityAllocator {
         );
     }
 
+    /// Reserves the exact id (index and generation) of `entity`, so that it can be passed to [`Self::new_with_id`]:
+    /// the index will not be returned by [`Self::allocate_id`] until the entity is destroyed.
+    /// The caller must ensure that no live entity uses the same index
+    pub fn reserve_id(&mut self, entity: Entity) {
+        let next_entity_id = self.next_entity_id.get_mut();
+        let dropped_entities = self.dropped_entities.get_mut().expect("dropped_entities");
+        if entity.0 >= *next_entity_id {
+            // The indices skipped by the reserved entity can still be allocated
+            dropped_entities.extend((*next_entity_id..entity.0).map(|index| Entity(index, 0)));
+            *next_entity_id = entity.0 + 1;
+        } else {
+            dropped_entities.retain(|dropped| dropped.0 != entity.0);
+        }
+    }
+
+    /// Returns the live entity using the same index of `entity`, regardless of its generation
+    pub fn live_entity_at(&self, entity: Entity) -> Option<Entity> {
+        self.entity_info
+            .get(&entity)
+            .map(|info| Entity(entity.0, info.generation))
+    }
+
     pub fn destroy_entity(&mut self, entity: Entity) {
         self.entity_info.remove(entity);
-        self.dropped_entities
-            .write()
-            .expect("dropped entityes")
-            .push(entity);
+        // The indices below the range belong to someone else, so they're never allocated
+        if entity.0 >= self.first_entity_id {
+            self.dropped_entities
+                .write()
+                .expect("dropped entityes")
+                .push(entity);
+        }
     }
 
     pub fn entity_info(&self, id: Entity) -> Option<&EntityInfo> {
@@ -90,7 +142,42 @@ impl EntityAllocator {
 mod tests {
     use crate::{type_registrar::UniqueTypeId, ComponentId};
 
-    use super::EntityAllocator;
+    use super::{Entity, EntityAllocator};
+
+    #[test]
+    fn entity_bits_round_trip() {
+        let entities = [
+            Entity(0, 0),
+            Entity(42, 1),
+            Entity(u32::MAX, 7),
+            Entity(3, u32::MAX),
+            Entity(u32::MAX, u32::MAX),
+        ];
+        for entity in entities {
+            assert_eq!(Entity::from_bits(entity.to_bits()), entity);
+        }
+        assert_eq!(Entity(1, 2).to_bits(), 0x0000_0002_0000_0001);
+    }
+
+    #[test]
+    fn with_id_range() {
+        let mut allocator = EntityAllocator::with_id_range(100);
+        let first = allocator.new_entity();
+        let second = allocator.new_entity();
+        assert_eq!(first, Entity(100, 0));
+        assert_eq!(second, Entity(101, 0));
+
+        // An entity spawned below the range is not recycled
+        let server_entity = Entity(5, 0);
+        allocator.reserve_id(server_entity);
+        allocator.new_with_id(server_entity);
+        allocator.destroy_entity(server_entity);
+        assert_eq!(allocator.new_entity(), Entity(102, 0));
+
+        // The entities in the range keep their index, with a new generation
+        allocator.destroy_entity(first);
+        assert_eq!(allocator.new_entity(), Entity(100, 1));
+    }
 
     #[test]
     fn test_entity_allocator() {
@@ -1,5 +1,7 @@
 use std::{alloc::Layout, marker::PhantomData, num::NonZeroUsize, ptr::NonNull};
 
+/// A type-erased pointer to a value, e.g a component read with [`crate::WorldContainer::get_component_erased`]
+#[derive(Clone, Copy)]
 pub struct ErasedPtr<'a> {
     data: NonNull<u8>,
     ph_data: PhantomData<&'a u8>,
@@ -24,6 +26,13 @@ pub unsafe fn make_drop_fn<T>(ptr: ErasedPtr<'_>) {
     std::mem::drop(t);
 }
 
+// The clone fn passed to ErasedVec::clone_with
+#[allow(dead_code)]
+pub unsafe fn make_clone_fn<T: Clone>(src: ErasedPtr<'_>, dst: *mut u8) {
+    let t = src.data.as_ptr().cast::<T>().as_ref().unwrap();
+    dst.cast::<T>().write(t.clone());
+}
+
 fn dangling_ptr_with_alignment(align: NonZeroUsize) -> NonNull<u8> {
     let align = align.get();
     unsafe { NonNull::new_unchecked(align as *mut u8) }
@@ -36,6 +45,17 @@ impl ErasedVec {
         Self::new(layout, with_drop_fn.then_some(make_drop_fn::<T>), capacity)
     }
 
+    /// Creates an ErasedVec containing all the elements of the iterator, allocating the storage only once
+    /// # SAFETY
+    ///   The caller must ensure that the elements are dropped (e.g through [`ErasedVec::clear`]) or moved out of the ErasedVec
+    pub unsafe fn from_iter_typed<T>(iter: impl ExactSizeIterator<Item = T>) -> Self {
+        let mut vec = Self::new_typed::<T>(true, iter.len());
+        for value in iter {
+            vec.push_back(value);
+        }
+        vec
+    }
+
     pub unsafe fn new(
         layout: Layout,
         drop_fn: Option<unsafe fn(ErasedPtr<'_>)>,
@@ -59,12 +79,14 @@ impl ErasedVec {
 
     pub unsafe fn push_back<T>(&mut self, value: T) -> usize {
         if self.len == self.capacity {
-            self.grow_exact(1);
+            self.grow_amortized(1);
         }
 
         let index = self.len;
 
         if self.layout.size() == 0 {
+            // The value is now owned by the vec, it's dropped through drop_at
+            std::mem::forget(value);
             self.len += 1;
             return index;
         }
@@ -75,6 +97,7 @@ impl ErasedVec {
         index
     }
 
+    #[allow(dead_code)]
     pub unsafe fn remove<T>(&mut self, index: usize) -> T {
         assert!(index < self.len);
 
@@ -87,6 +110,7 @@ impl ErasedVec {
         val
     }
 
+    #[allow(dead_code)]
     pub fn clear(&mut self) {
         if let Some(drop_fn) = self.drop_fn {
             for i in 0..self.len {
@@ -114,6 +138,25 @@ impl ErasedVec {
         ptr.as_mut().unwrap()
     }
 
+    /// Gets all the elements of the ErasedVec as a typed slice of length [`ErasedVec::len`]
+    /// # SAFETY
+    ///   1. The caller must ensure that T is the type of the elements stored in the ErasedVec
+    ///   2. The caller must ensure that all the elements are initialized
+    #[allow(dead_code)]
+    pub unsafe fn as_slice<T>(&self) -> &[T] {
+        debug_assert_eq!(self.layout, Layout::new::<T>());
+        std::slice::from_raw_parts(self.data.as_ptr().cast::<T>(), self.len)
+    }
+
+    /// Like [`ErasedVec::as_slice`], but the elements can be modified through the slice
+    /// # SAFETY
+    ///   The same rules of [`ErasedVec::as_slice`] apply
+    #[allow(dead_code)]
+    pub unsafe fn as_mut_slice<T>(&mut self) -> &mut [T] {
+        debug_assert_eq!(self.layout, Layout::new::<T>());
+        std::slice::from_raw_parts_mut(self.data.as_ptr().cast::<T>(), self.len)
+    }
+
     pub fn get_ptr(&self, index: usize) -> ErasedPtr<'_> {
         assert!(index < self.len);
         unsafe {
@@ -125,6 +168,14 @@ impl ErasedVec {
         }
     }
 
+    /// Iterates a pointer to each element in `0..len`, in order, without knowing the type of the elements:
+    /// together with `layout` and `drop_fn` this can be used to process a whole column
+    #[allow(dead_code)]
+    pub fn iter_ptrs(&self) -> impl Iterator<Item = ErasedPtr<'_>> + '_ {
+        (0..self.len).map(|index| self.get_ptr(index))
+    }
+
+    #[allow(dead_code)]
     pub fn capacity(&self) -> usize {
         self.capacity
     }
@@ -171,12 +222,19 @@ impl ErasedVec {
         self.capacity = new_capacity;
     }
 
+    // Grows the capacity by at least min_grow_elements, doubling it when possible: growing an ErasedVec
+    // one element at a time reallocates it only a logarithmic number of times
+    pub fn grow_amortized(&mut self, min_grow_elements: usize) {
+        let grow_elements = self.capacity.max(min_grow_elements).max(1);
+        self.grow_exact(grow_elements);
+    }
+
     // Makes sure that there's space for new_len elements in the ErasedVec.
     // This function will never shrink the ErasedVec
     pub fn ensure_len(&mut self, new_len: usize) {
         if new_len > self.capacity {
             let difference = new_len - self.capacity;
-            self.grow_exact(difference);
+            self.grow_amortized(difference);
         }
         self.len = new_len;
     }
@@ -204,6 +262,23 @@ impl ErasedVec {
         }
     }
 
+    /// Creates a new ErasedVec with the same layout and capacity, cloning each element through `clone_fn`
+    /// # SAFETY
+    ///   The caller must ensure that `clone_fn` clones a value of the type stored in this ErasedVec,
+    ///   writing the new value into `dst`, and that all the elements of the ErasedVec are initialized
+    // Kept as the primitive to clone whole columns (e.g for a snapshot of the storage), while clone_entity
+    // clones single components through the cloners registered in the WorldContainer
+    #[allow(dead_code)]
+    pub unsafe fn clone_with(&self, clone_fn: unsafe fn(src: ErasedPtr<'_>, dst: *mut u8)) -> Self {
+        let mut cloned = Self::new(self.layout, self.drop_fn, self.capacity);
+        cloned.ensure_len(self.len);
+        for i in 0..self.len {
+            let dst = cloned.get_ptr(i).data.as_ptr();
+            clone_fn(self.get_ptr(i), dst);
+        }
+        cloned
+    }
+
     /// # SAFETY
     /// The caller must ensure that the type of self and the data of `source` are the same
     /// and that the source must correctly deal with dropping the copied item
@@ -239,11 +314,23 @@ unsafe impl Send for ErasedVec {}
 unsafe impl Sync for ErasedVec {}
 
 impl<'a> ErasedPtr<'a> {
-    pub unsafe fn cast<T: 'static>(self) -> UnsafePtr<'a, T> {
+    /// Gets the raw pointer to the value
+    pub fn as_ptr(&self) -> *const u8 {
+        self.data.as_ptr().cast_const()
+    }
+
+    /// Reinterprets the value as a `T`
+    /// # Safety
+    ///   The caller must ensure that the pointed value is a `T`
+    pub unsafe fn deref<T: 'static>(self) -> &'a T {
+        self.data.cast::<T>().as_ref()
+    }
+
+    pub(crate) unsafe fn cast<T: 'static>(self) -> UnsafePtr<'a, T> {
         UnsafePtr(self.data.cast::<T>().as_ptr().cast_const(), PhantomData)
     }
 
-    pub unsafe fn cast_mut<T: 'static>(self) -> UnsafeMutPtr<'a, T> {
+    pub(crate) unsafe fn cast_mut<T: 'static>(self) -> UnsafeMutPtr<'a, T> {
         UnsafeMutPtr(self.data.cast::<T>().as_ptr(), PhantomData)
     }
 }
@@ -266,6 +353,7 @@ impl<'a, T> UnsafeMutPtr<'a, T> {
         unsafe { self.0.as_mut().unwrap() }
     }
 
+    #[allow(dead_code)]
     pub unsafe fn into_ref(self) -> &'a T {
         self.0.as_ref().unwrap()
     }
@@ -294,7 +382,7 @@ mod tests {
         sync::{Arc, RwLock},
     };
 
-    use super::ErasedVec;
+    use super::{make_clone_fn, ErasedVec};
 
     struct TestStruct {
         foo: u32,
@@ -325,6 +413,43 @@ mod tests {
             assert_eq!(vec.capacity(), 1);
         }
     }
+    #[test]
+    fn grow_amortized() {
+        unsafe {
+            let mut vec = ErasedVec::new_typed::<u64>(false, 0);
+            let mut reallocations = 0;
+            for i in 0..1000u64 {
+                let capacity = vec.capacity();
+                vec.push_back(i);
+                if vec.capacity() != capacity {
+                    reallocations += 1;
+                }
+            }
+            assert_eq!(vec.capacity(), 1024);
+            assert_eq!(reallocations, 11);
+            assert_eq!(vec.as_slice::<u64>().iter().sum::<u64>(), 999 * 1000 / 2);
+
+            // ensure_len grows at least to the requested length
+            vec.ensure_len(5000);
+            assert_eq!(vec.len(), 5000);
+            assert_eq!(vec.capacity(), 5000);
+            vec.ensure_len(5001);
+            assert_eq!(vec.capacity(), 10000);
+
+            // Exact growth is still available for the precise cases
+            let mut vec = ErasedVec::new_typed::<u64>(false, 0);
+            vec.reserve_exact(3);
+            assert_eq!(vec.capacity(), 3);
+
+            let mut zero_sized = ErasedVec::new_typed::<ZeroSizedStruct>(false, 0);
+            for _ in 0..10 {
+                zero_sized.push_back(ZeroSizedStruct);
+            }
+            zero_sized.ensure_len(20);
+            assert_eq!(zero_sized.len(), 20);
+        }
+    }
+
     #[test]
     fn operations() {
         unsafe {
@@ -497,6 +622,28 @@ mod tests {
         }
     }
     #[test]
+    fn zero_sized_drop() {
+        struct ZeroSizedDrop;
+        thread_local! {
+            static DROPS: RefCell<u32> = const { RefCell::new(0) };
+        }
+        impl Drop for ZeroSizedDrop {
+            fn drop(&mut self) {
+                DROPS.with(|drops| *drops.borrow_mut() += 1);
+            }
+        }
+        unsafe {
+            let mut vec = ErasedVec::new_typed::<ZeroSizedDrop>(true, 0);
+            vec.push_back(ZeroSizedDrop);
+            vec.push_back(ZeroSizedDrop);
+            // Pushing moves the value into the vec without dropping it
+            assert_eq!(DROPS.with(|drops| *drops.borrow()), 0);
+            vec.drop_at(0);
+            vec.drop_at(1);
+        }
+        assert_eq!(DROPS.with(|drops| *drops.borrow()), 2);
+    }
+    #[test]
     fn aligned_data() {
         unsafe {
             let mut vec = ErasedVec::new_typed::<ZeroSizedStructWithWeirdAlignment>(true, 0);
@@ -517,6 +664,98 @@ mod tests {
         }
     }
 
+    #[test]
+    fn clone_with() {
+        unsafe {
+            let mut vec = ErasedVec::new_typed::<String>(true, 4);
+            vec.push_back("Hello".to_owned());
+            vec.push_back("World".to_owned());
+
+            let mut cloned = vec.clone_with(make_clone_fn::<String>);
+            assert_eq!(cloned.len(), 2);
+            assert_eq!(cloned.capacity(), vec.capacity());
+            assert_eq!(cloned.get::<String>(0), "Hello");
+            assert_eq!(cloned.get::<String>(1), "World");
+
+            cloned.get_mut::<String>(0).push_str(", Kecs");
+            assert_eq!(cloned.get::<String>(0), "Hello, Kecs");
+            assert_eq!(vec.get::<String>(0), "Hello");
+
+            vec.clear();
+            cloned.clear();
+        }
+    }
+
+    #[test]
+    fn from_iter_typed() {
+        unsafe {
+            let mut vec = ErasedVec::from_iter_typed((0..100u32).map(|i| i.to_string()));
+            assert_eq!(vec.len(), 100);
+            assert_eq!(vec.capacity(), 100);
+            for i in 0..100 {
+                assert_eq!(vec.get::<String>(i), &i.to_string());
+            }
+
+            vec.clear();
+        }
+    }
+
+    #[test]
+    fn as_slice() {
+        unsafe {
+            let mut vec = ErasedVec::new_typed::<f32>(false, 0);
+            for value in [1.0f32, 2.0, 3.0, 4.0] {
+                vec.push_back(value);
+            }
+            assert_eq!(vec.as_slice::<f32>().iter().sum::<f32>(), 10.0);
+
+            vec.as_mut_slice::<f32>()
+                .iter_mut()
+                .for_each(|value| *value *= 2.0);
+            assert_eq!(vec.as_slice::<f32>(), &[2.0, 4.0, 6.0, 8.0]);
+
+            let empty = ErasedVec::new_typed::<u64>(false, 4);
+            assert!(empty.as_slice::<u64>().is_empty());
+
+            let mut zero_sized = ErasedVec::new_typed::<ZeroSizedStruct>(false, 0);
+            zero_sized.push_back(ZeroSizedStruct);
+            zero_sized.push_back(ZeroSizedStruct);
+            assert_eq!(zero_sized.as_slice::<ZeroSizedStruct>().len(), 2);
+            assert_eq!(zero_sized.as_mut_slice::<ZeroSizedStruct>().len(), 2);
+
+            let mut strings = ErasedVec::new_typed::<String>(true, 0);
+            strings.push_back("Hello".to_owned());
+            strings.push_back("World".to_owned());
+            strings.as_mut_slice::<String>()[1].push('!');
+            assert_eq!(strings.as_slice::<String>(), &["Hello", "World!"]);
+            strings.clear();
+        }
+    }
+
+    #[test]
+    fn iter_ptrs() {
+        unsafe {
+            let mut vec = ErasedVec::new_typed::<u32>(false, 0);
+            for value in [1u32, 2, 3] {
+                vec.push_back(value);
+            }
+            let values: Vec<u32> = vec
+                .iter_ptrs()
+                .map(|ptr| *ptr.cast::<u32>().into_ref())
+                .collect();
+            assert_eq!(values, vec![1, 2, 3]);
+
+            // The pointers of zero sized elements are all valid
+            let mut zero_sized = ErasedVec::new_typed::<ZeroSizedStruct>(false, 0);
+            zero_sized.push_back(ZeroSizedStruct);
+            zero_sized.push_back(ZeroSizedStruct);
+            assert_eq!(zero_sized.iter_ptrs().count(), 2);
+
+            let empty = ErasedVec::new_typed::<u64>(false, 4);
+            assert_eq!(empty.iter_ptrs().count(), 0);
+        }
+    }
+
     #[test]
     fn copy_vec() {
         struct TestStruct {
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+
+use crate::{Entity, WorldContainer};
+
+/// The parent of an [`Entity`], kept in sync with the parent's [`Children`] by [`WorldContainer::set_parent`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Parent(pub(crate) Entity);
+
+impl Parent {
+    /// Gets the parent entity
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// The children of an [`Entity`], kept in sync with their [`Parent`]s by [`WorldContainer::set_parent`].
+/// A child is removed when it's destroyed, while the children of a destroyed entity lose their [`Parent`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Children(pub(crate) Vec<Entity>);
+
+impl Children {
+    /// Gets the children, in the order in which they were added
+    pub fn as_slice(&self) -> &[Entity] {
+        &self.0
+    }
+
+    /// Iterates the children, in the order in which they were added
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl WorldContainer {
+    /// Makes `parent` the [`Parent`] of `child`, adding `child` to the parent's [`Children`].
+    /// If `child` already had a parent, it's removed from the old parent's [`Children`]
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) {
+        assert!(child != parent, "An entity can't be the parent of itself");
+        assert!(
+            self.contains(child) && self.contains(parent),
+            "Tried to set the parent of a destroyed entity"
+        );
+        self.detach_from_parent(child);
+        self.add_component(child, Parent(parent));
+        if let Some(children) = self.get_component_mut::<Children>(parent) {
+            children.0.push(child);
+        } else {
+            self.add_component(parent, Children(vec![child]));
+        }
+    }
+
+    // Removes the entity from the Children of its Parent, returning the parent
+    pub(crate) fn detach_from_parent(&mut self, child: Entity) -> Option<Entity> {
+        let parent = self.get_component::<Parent>(child)?.0;
+        if let Some(children) = self.get_component_mut::<Children>(parent) {
+            children.0.retain(|entity| *entity != child);
+        }
+        Some(parent)
+    }
+
+    // Called before destroying the entity, so that no stale id is left in the hierarchy: the entity is removed from
+    // the Children of its Parent, and its children lose their Parent
+    pub(crate) fn detach_hierarchy(&mut self, entity: Entity) {
+        self.detach_from_parent(entity);
+        let children = self
+            .get_component_mut::<Children>(entity)
+            .map(|children| std::mem::take(&mut children.0))
+            .unwrap_or_default();
+        for child in children {
+            if self.get_component::<Parent>(child) == Some(&Parent(entity)) {
+                self.remove_component::<Parent>(child);
+            }
+        }
+    }
+
+    // Collects the entity and all its descendants, children before their parents.
+    // A malformed hierarchy may contain cycles, so each entity is visited only once
+    pub(crate) fn collect_descendants(&self, entity: Entity) -> Vec<Entity> {
+        let mut visited = HashSet::new();
+        let mut descendants = vec![];
+        let mut stack = vec![entity];
+        while let Some(entity) = stack.pop() {
+            if !self.contains(entity) || !visited.insert(entity) {
+                continue;
+            }
+            descendants.push(entity);
+            if let Some(children) = self.get_component::<Children>(entity) {
+                stack.extend(children.as_slice().iter().rev());
+            }
+        }
+        descendants.reverse();
+        descendants
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{Children, Parent, Query, World};
+
+    #[test]
+    fn set_parent() {
+        let mut world = World::new();
+        let parent = world.new_entity();
+        let other_parent = world.new_entity();
+        let child = world.new_entity();
+
+        world.set_parent(child, parent);
+        assert_eq!(world.get_component::<Parent>(child), Some(&Parent(parent)));
+        assert_eq!(
+            world.get_component::<Children>(parent).unwrap().as_slice(),
+            &[child]
+        );
+
+        // The child is moved from the children of the old parent
+        world.set_parent(child, other_parent);
+        assert_eq!(
+            world.get_component::<Parent>(child),
+            Some(&Parent(other_parent))
+        );
+        assert!(world
+            .get_component::<Children>(parent)
+            .unwrap()
+            .as_slice()
+            .is_empty());
+        assert_eq!(
+            world
+                .get_component::<Children>(other_parent)
+                .unwrap()
+                .as_slice(),
+            &[child]
+        );
+    }
+
+    #[test]
+    fn despawn_recursive() {
+        let mut world = World::new();
+        let root = world.new_entity();
+        let parent = world.new_entity();
+        let child = world.new_entity();
+        let grandchild = world.new_entity();
+        let sibling = world.new_entity();
+        world.set_parent(parent, root);
+        world.set_parent(sibling, root);
+        world.set_parent(child, parent);
+        world.set_parent(grandchild, child);
+
+        let parents = Arc::<Mutex<usize>>::default();
+        let parents_2 = parents.clone();
+        world.add_system(0, move |query: Query<&Parent>| {
+            *parents_2.lock().unwrap() = query.iter().count();
+        });
+
+        world.despawn_recursive(parent);
+        for entity in [parent, child, grandchild] {
+            assert!(!world.contains(entity));
+        }
+        assert!(world.contains(root));
+        assert!(world.contains(sibling));
+        assert_eq!(
+            world.get_component::<Children>(root).unwrap().as_slice(),
+            &[sibling]
+        );
+
+        world.update(0);
+        assert_eq!(*parents.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn despawn_recursive_with_cycle() {
+        let mut world = World::new();
+        let first = world.new_entity();
+        let second = world.new_entity();
+        world.set_parent(second, first);
+        // Build a cycle by hand, set_parent doesn't check the descendants of the child
+        world.add_component(second, Children(vec![first]));
+
+        world.despawn_recursive(first);
+        assert!(!world.contains(first));
+        assert!(!world.contains(second));
+    }
+
+    #[test]
+    fn destroyed_child_is_detached() {
+        let mut world = World::new();
+        let parent = world.new_entity();
+        let first = world.new_entity();
+        let second = world.new_entity();
+        world.set_parent(first, parent);
+        world.set_parent(second, parent);
+
+        world.destroy_entity(first);
+        assert_eq!(
+            world.get_component::<Children>(parent).unwrap().as_slice(),
+            &[second]
+        );
+
+        // The children of a destroyed parent lose their Parent
+        world.destroy_entity(parent);
+        assert!(world.contains(second));
+        assert!(world.get_component::<Parent>(second).is_none());
+    }
+}
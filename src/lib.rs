@@ -24,19 +24,20 @@
 //! world.add_component(entity, Name("Frank".to_string()));
 //!
 //! // You can iterate on multipler components by using a tuple, e.g Query<(&mut MutatedComponent, &NonMutatedComponent)>
-//! fn iter_only_foos(query: Query<&mut Foo>) {
-//!     for item in query.iter() {
+//! // Queries writing to a component are iterated with iter_mut
+//! fn iter_only_foos(mut query: Query<&mut Foo>) {
+//!     for item in query.iter_mut() {
 //!         item.0 = 123;
 //!     }
 //! }
 //!
-//! fn iter_only_bazs(query: Query<&mut Baz>) {
-//!     for item in query.iter() {
+//! fn iter_only_bazs(mut query: Query<&mut Baz>) {
+//!     for item in query.iter_mut() {
 //!         item.0 = 456;
 //!     }
 //! }
 //!
-//! fn print_names(query: Query<&mut Name>) {
+//! fn print_names(query: Query<&Name>) {
 //!     for item in query.iter() {
 //!         println!("item name {}", item.0);
 //!     }    
@@ -51,11 +52,15 @@
 //! world.update(run_systems);
 //! ```
 mod archetype;
+mod change_detection;
 mod entity_manager;
 mod erased_data_vec;
+mod hierarchy;
 mod query;
 mod resources;
 mod schedule;
+#[cfg(feature = "serde")]
+mod snapshot;
 mod storage;
 mod system;
 mod type_registrar;
@@ -66,13 +71,23 @@ mod commands;
 mod sparse_set;
 
 pub use archetype::*;
-pub use commands::{Commands, EntityBuilder};
+pub use change_detection::{ChangedBy, ComponentTicks, Mut, Ref, SystemHandle, SystemTicks};
+pub use commands::{Bundle, Commands, EntityBuilder, TypedBlob};
 pub use entity_manager::{Entity, EntityInfo};
+pub use erased_data_vec::ErasedPtr;
+pub use hierarchy::{Children, Parent};
 pub use query::*;
-pub use resources::{Res, ResMut, Resource};
-pub use schedule::{GraphScheduler, LinearScheduler, Scheduler};
+pub use resources::{Res, ResMut, Resource, ResourceGroup, Resources};
+pub use schedule::{
+    GraphScheduler, GraphSystemId, LinearScheduler, ScheduleCycleError, ScheduleExport, Scheduler,
+};
+#[cfg(feature = "serde")]
+pub use snapshot::{EntitySnapshot, SnapshotError, WorldSnapshot};
 pub use sparse_set::SparseSet;
-pub use system::{System, SystemContainer, SystemParam};
+pub use system::{
+    In, IntoSystemWithOutput, InvalidSystemError, System, SystemContainer, SystemParam,
+    SystemState, SystemTuple,
+};
 pub use world::*;
 pub use world_container::*;
 
@@ -80,7 +95,9 @@ pub use world_container::*;
 pub type World = KecsWorld<GraphScheduler>;
 
 impl<'cworld> SystemParam for Commands<'cworld> {
-    type State = ();
+    // The source of the commands sent by the system
+    type State = u64;
+    type Item<'world, 'state> = Commands<'world>;
 
     const IS_MUT_WORLD: bool = false;
 
@@ -90,35 +107,38 @@ impl<'cworld> SystemParam for Commands<'cworld> {
     ) {
     }
 
-    fn create<'world, 'state>(_data: &'state Self::State, store: &'world mut WorldContainer) -> Self
-    where
-        'world: 'state,
-    {
-        unsafe { std::mem::transmute(store.commands()) }
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        Commands::with_source(store, *data)
     }
 
-    fn create_initial_state(_store: &mut WorldContainer) -> Self::State {}
-
-    fn on_entity_changed(
-        _state: &mut Self::State,
-        _store: &WorldContainer,
-        _entity: Entity,
-        _info: &EntityInfo,
-    ) {
+    fn create_initial_state(store: &mut WorldContainer) -> Self::State {
+        store.commands.new_source()
     }
 
     fn is_exclusive(_world: &mut WorldContainer) -> bool {
         false
     }
-
-    fn on_entity_destroyed(_state: &mut Self::State, _store: &WorldContainer, _entity: Entity) {}
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::{Arc, RwLock};
-
-    use crate::{query::Query, IntoLabel, World};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    };
+
+    use crate::{
+        change_detection::SystemTicks,
+        query::{AccessMode, Query},
+        system::IntoSystem,
+        Commands, ComponentId, Entity, EntityRef, GraphScheduler, In, IntoLabel,
+        InvalidSystemError, KecsWorld, LinearScheduler, ResMut, Resource, SparseSet, System,
+        SystemParam, SystemState, World, WorldContainer,
+    };
 
     #[test]
     fn iter_n_times() {
@@ -126,8 +146,8 @@ mod tests {
             counter: Arc<RwLock<usize>>,
         }
 
-        fn counting_system(query: Query<&mut TestComponent>) {
-            for counter in query.iter() {
+        fn counting_system(mut query: Query<&mut TestComponent>) {
+            for counter in query.iter_mut() {
                 let mut lock = counter.counter.write().unwrap();
                 *lock = lock.overflowing_add(1).0;
             }
@@ -161,8 +181,8 @@ mod tests {
             counter: Arc<RwLock<usize>>,
         }
 
-        fn counting_system(query: Query<&mut TestComponent>) {
-            for counter in query.iter() {
+        fn counting_system(mut query: Query<&mut TestComponent>) {
+            for counter in query.iter_mut() {
                 let mut lock = counter.counter.write().unwrap();
                 *lock = lock.overflowing_add(1).0;
             }
@@ -197,8 +217,8 @@ mod tests {
             counter: Arc<RwLock<usize>>,
         }
 
-        fn counting_system(query: Query<&mut TestComponent>) {
-            for counter in query.iter() {
+        fn counting_system(mut query: Query<&mut TestComponent>) {
+            for counter in query.iter_mut() {
                 let mut lock = counter.counter.write().unwrap();
                 *lock = lock.overflowing_add(1).0;
             }
@@ -236,6 +256,365 @@ mod tests {
         assert_eq!(counter.read().unwrap().to_owned(), ENTITIES);
     }
 
+    #[test]
+    fn system_count() {
+        struct TestComponentA;
+
+        fn system_a(_: Query<&TestComponentA>) {}
+        fn system_b(_: Query<&mut TestComponentA>) {}
+
+        let mut world = World::new();
+        assert_eq!(world.total_system_count(), 0);
+
+        world.add_system("lab_1", system_a);
+        world.add_system("lab_1", system_b);
+        world.add_system("lab_1", system_a);
+        world.add_system("lab_2", system_b);
+
+        assert_eq!(world.system_count("lab_1"), 3);
+        assert_eq!(world.system_count("lab_2"), 1);
+        assert_eq!(world.system_count("lab_3"), 0);
+        assert_eq!(world.total_system_count(), 4);
+
+        let mut world = KecsWorld::<LinearScheduler>::new();
+        world.add_system("lab_1", system_a);
+        world.add_system("lab_2", system_b);
+        world.add_system("lab_2", system_a);
+
+        assert_eq!(world.system_count("lab_1"), 1);
+        assert_eq!(world.system_count("lab_2"), 2);
+        assert_eq!(world.total_system_count(), 3);
+    }
+
+    #[test]
+    fn remove_component_from_all() {
+        struct Selected;
+        struct Position;
+
+        let mut world = World::new();
+        let entities = (0..1000)
+            .map(|i| {
+                let entity = world.new_entity();
+                world.add_component(entity, Position);
+                if i % 2 == 0 {
+                    world.add_component(entity, Selected);
+                }
+                entity
+            })
+            .collect::<Vec<_>>();
+
+        let selected = Arc::new(Mutex::new(0));
+        let selected_2 = selected.clone();
+        world.add_system(0, move |query: Query<&Selected>| {
+            *selected_2.lock().unwrap() = query.iter().count();
+        });
+        world.update(0);
+        assert_eq!(*selected.lock().unwrap(), 500);
+
+        assert_eq!(world.remove_component_from_all::<Selected>(), 500);
+        assert_eq!(world.remove_component_from_all::<Selected>(), 0);
+        world.update(0);
+        assert_eq!(*selected.lock().unwrap(), 0);
+        for entity in entities {
+            assert!(world.get_component::<Selected>(entity).is_none());
+            assert!(world.get_component::<Position>(entity).is_some());
+        }
+    }
+
+    #[test]
+    fn init_system_twice() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        struct CountedParam;
+        impl SystemParam for CountedParam {
+            type State = Counted;
+            type Item<'world, 'state> = Self;
+            const IS_MUT_WORLD: bool = false;
+
+            fn add_dependencies(
+                _store: &mut WorldContainer,
+                _components: &mut SparseSet<ComponentId, AccessMode>,
+            ) {
+            }
+
+            fn create<'world, 'state>(
+                _data: &'state Self::State,
+                _store: &'world mut WorldContainer,
+                _ticks: SystemTicks,
+            ) -> Self::Item<'world, 'state> {
+                CountedParam
+            }
+
+            fn create_initial_state(_store: &mut WorldContainer) -> Self::State {
+                Counted
+            }
+
+            fn is_exclusive(_world: &mut WorldContainer) -> bool {
+                false
+            }
+        }
+
+        let mut world = WorldContainer::new(Commands::create().0);
+        let mut system = IntoSystem::into_system(|_: CountedParam| {});
+        system.init(&mut world);
+        system.init(&mut world);
+        // The first state is dropped when the system is initialized again
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+        system.run(&mut world);
+
+        drop(system);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn system_state_in_exclusive_system() {
+        struct Counter(usize);
+        impl Resource for Counter {}
+        struct Marker;
+
+        let mut world = World::new();
+        world.add_resource(Counter(0));
+        world.add_system(0, |world: &mut WorldContainer| {
+            let entity = world.new_entity();
+            world.add_component(entity, Marker);
+
+            let mut state = SystemState::<(ResMut<Counter>, Query<&Marker>)>::new(world);
+            let (mut counter, query) = state.get(world);
+            counter.0 = query.iter().count();
+        });
+        world.update(0);
+        world.update(0);
+        assert_eq!(world.get_resource::<Counter>().unwrap().0, 2);
+    }
+
+    #[test]
+    fn system_state_reused_across_runs() {
+        struct Foo(usize);
+
+        let mut world = World::new();
+        let entity = world.new_entity();
+        world.add_component(entity, Foo(1));
+
+        let state = Arc::<Mutex<Option<SystemState<Query<&Foo>>>>>::default();
+        let sums = Arc::<Mutex<Vec<usize>>>::default();
+        let sums_2 = sums.clone();
+        world.add_system(0, move |world: &mut WorldContainer| {
+            let mut state = state.lock().unwrap();
+            let state = state.get_or_insert_with(|| SystemState::new(world));
+            let sum = state.get(world).iter().map(|foo| foo.0).sum();
+            sums_2.lock().unwrap().push(sum);
+        });
+        world.update(0);
+
+        // The cached state sees the entities spawned after it was created
+        let entity = world.new_entity();
+        world.add_component(entity, Foo(2));
+        world.update(0);
+        assert_eq!(*sums.lock().unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "A SystemState can't create &mut WorldContainer")]
+    fn system_state_with_mut_world() {
+        let mut world = World::new();
+        world.add_system(0, |world: &mut WorldContainer| {
+            SystemState::<&mut WorldContainer>::new(world);
+        });
+        world.update(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "reads all the components, but also writes")]
+    fn system_state_with_entity_ref_and_write() {
+        struct Foo;
+        let mut world = World::new();
+        world.add_system(0, |world: &mut WorldContainer| {
+            SystemState::<(Query<EntityRef>, Query<&mut Foo>)>::new(world);
+        });
+        world.update(0);
+    }
+
+    #[test]
+    fn new_with_entity_range() {
+        struct Replicated;
+
+        let mut world = World::new_with_entity_range(1000);
+        let local = world.new_entity();
+        assert_eq!(local.to_bits(), 1000);
+
+        // The entities replicated from the server use the indices below the range
+        let server_entity = Entity::from_bits(3);
+        assert!(world.get_or_spawn(server_entity));
+        world.add_component(server_entity, Replicated);
+        world.destroy_entity(server_entity);
+        assert_eq!(world.new_entity().to_bits(), 1001);
+    }
+
+    #[test]
+    fn set_scheduler_with_thread_pool() {
+        struct Counter(u32);
+
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let mut world = World::new();
+        assert!(world
+            .set_scheduler(0, GraphScheduler::with_thread_pool(Arc::new(thread_pool)))
+            .is_none());
+
+        let entity = world.new_entity();
+        world.add_component(entity, Counter(0));
+
+        let num_threads = Arc::new(Mutex::new(vec![]));
+        for _ in 0..2 {
+            let num_threads = num_threads.clone();
+            world.add_system(0, move |_: Query<&Counter>| {
+                num_threads
+                    .lock()
+                    .unwrap()
+                    .push(rayon::current_num_threads());
+            });
+        }
+        world.add_system(0, |mut query: Query<&mut Counter>| {
+            query.single_mut().0 += 1;
+        });
+        world.update(0);
+
+        assert_eq!(world.system_count(0), 3);
+        assert_eq!(*num_threads.lock().unwrap(), vec![2, 2]);
+        assert_eq!(world.get_component::<Counter>(entity).unwrap().0, 1);
+    }
+
+    #[test]
+    fn add_systems() {
+        let order = Arc::new(Mutex::new(vec![]));
+        let (order_1, order_2, order_3) = (order.clone(), order.clone(), order.clone());
+
+        let mut world = KecsWorld::<LinearScheduler>::new();
+        let ids = world.add_systems(
+            0,
+            (
+                move || order_1.lock().unwrap().push(1),
+                move || order_2.lock().unwrap().push(2),
+                move || order_3.lock().unwrap().push(3),
+            ),
+        );
+        assert_eq!(ids, vec![0, 1, 2]);
+        assert_eq!(world.system_count(0), 3);
+
+        world.update(0);
+        assert_eq!(*order.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn flush_between_systems() {
+        struct Enemy;
+
+        let counts = Arc::new(Mutex::new(vec![]));
+        let counts_2 = counts.clone();
+
+        let mut world = KecsWorld::<LinearScheduler>::new();
+        world.add_system(0, |mut commands: Commands| {
+            let mut builder = commands.spawn_entity();
+            builder.with_component(Enemy);
+            builder.build();
+        });
+        world.add_system(0, move |query: Query<&Enemy>| {
+            counts_2.lock().unwrap().push(query.iter().count());
+        });
+
+        // By default the spawned entity is visible only in the next update
+        world.update(0);
+        assert_eq!(*counts.lock().unwrap(), vec![0]);
+
+        world.scheduler_mut(0).set_flush_between_systems(true);
+        world.update(0);
+        assert_eq!(*counts.lock().unwrap(), vec![0, 2]);
+        assert_eq!(world.system_count(0), 2);
+    }
+
+    #[test]
+    fn run_oneshot_in() {
+        struct Foo(u32);
+
+        let mut world = World::new();
+        for i in 0..3 {
+            let entity = world.new_entity();
+            world.add_component(entity, Foo(i));
+        }
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen_2 = seen.clone();
+        world.run_oneshot_in(42u32, move |n: In<u32>, query: Query<&Foo>| {
+            seen_2
+                .lock()
+                .unwrap()
+                .push(n.into_inner() + query.iter().map(|foo| foo.0).sum::<u32>());
+        });
+        assert_eq!(*seen.lock().unwrap(), vec![45]);
+
+        // The input of a system without an In parameter is dropped
+        let input = Arc::new(());
+        world.run_oneshot_in(input.clone(), || {});
+        assert_eq!(Arc::strong_count(&input), 1);
+
+        // The input is dropped along with a panicking system
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.run_oneshot_in(input.clone(), |_: Query<&Foo>, _: In<Arc<()>>| panic!());
+        }));
+        assert!(result.is_err());
+        assert_eq!(Arc::strong_count(&input), 1);
+    }
+
+    #[test]
+    fn scheduled_system_with_input() {
+        let mut world = World::new();
+        let error = world.try_add_system(0, |_: In<u32>| {}).unwrap_err();
+        assert!(matches!(error, InvalidSystemError::InputNotOneshot { .. }));
+        assert_eq!(world.system_count(0), 0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "A system with an In<T> parameter can only be run with run_oneshot_in"
+    )]
+    fn oneshot_with_output_with_input() {
+        let mut world = World::new();
+        world.run_oneshot_with(|In(n): In<u32>| n);
+    }
+
+    #[test]
+    fn run_oneshot_with() {
+        struct Enemy(u32);
+        struct Player;
+
+        let mut world = World::new();
+        for i in 0..3 {
+            let entity = world.new_entity();
+            world.add_component(entity, Enemy(i));
+        }
+        let player = world.new_entity();
+        world.add_component(player, Player);
+
+        let count = world.run_oneshot_with(|query: Query<&Enemy>| query.iter().count());
+        assert_eq!(count, 3);
+
+        let (total, player_found) = world.run_oneshot_with(
+            |enemies: Query<&Enemy>, players: Query<(Entity, &Player)>| {
+                (enemies.iter().map(|e| e.0).sum::<u32>(), players.single().0)
+            },
+        );
+        assert_eq!(total, 3);
+        assert_eq!(player_found, player);
+    }
+
     #[test]
     fn labels_update_all_systems() {
         let lab_1 = "lab_1".into_label();
@@ -270,4 +649,199 @@ mod tests {
         world.update(lab_2);
         assert_eq!(*counter.read().unwrap(), 2);
     }
+
+    #[test]
+    fn run_system() {
+        struct Health(u32);
+
+        let mut world = World::new();
+        let entity = world.new_entity();
+        world.add_component(entity, Health(10));
+
+        let damage = world.add_system(0, |mut query: Query<&mut Health>| {
+            for health in query.iter_mut() {
+                health.0 -= 1;
+            }
+        });
+        world.add_system(0, |mut query: Query<&mut Health>| {
+            for health in query.iter_mut() {
+                health.0 *= 2;
+            }
+        });
+
+        world.update(0);
+        assert_eq!(world.get_component::<Health>(entity).unwrap().0, 18);
+
+        // Only the damage system runs
+        assert!(world.run_system(0, damage));
+        assert_eq!(world.get_component::<Health>(entity).unwrap().0, 17);
+
+        assert!(!world.run_system(1, damage));
+        world.remove_system(0, damage);
+        assert!(!world.run_system(0, damage));
+    }
+
+    #[test]
+    fn try_add_system() {
+        struct TestComponent;
+
+        let mut world = World::new();
+        let error = world
+            .try_add_system(0, |_: &mut WorldContainer, _: Query<&TestComponent>| {})
+            .unwrap_err();
+        assert!(matches!(error, InvalidSystemError::MutWorldNotAlone { .. }));
+        assert_eq!(world.system_count(0), 0);
+
+        // Multiple exclusive systems are fine
+        assert!(world.try_add_system(0, |_: &mut WorldContainer| {}).is_ok());
+        assert!(world.try_add_system(0, |_: &mut WorldContainer| {}).is_ok());
+        assert_eq!(world.system_count(0), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "that parameter must be the only parameter")]
+    fn add_invalid_system() {
+        let mut world = World::new();
+        world.add_system(0, |_: &mut WorldContainer, _: Commands| {});
+    }
+
+    #[test]
+    fn component_hooks() {
+        struct Transform;
+        #[derive(Default)]
+        struct SpatialIndex(Vec<Entity>);
+        impl Resource for SpatialIndex {}
+
+        let mut world = World::new();
+        world.add_resource(SpatialIndex::default());
+        world.register_on_add::<Transform>(|world, entity| {
+            // The hooks run after the component is added
+            assert!(world.get_component::<Transform>(entity).is_some());
+            world
+                .get_resource_mut::<SpatialIndex>()
+                .unwrap()
+                .0
+                .push(entity);
+        });
+        world.register_on_remove::<Transform>(|world, entity| {
+            assert!(world.get_component::<Transform>(entity).is_none());
+            world
+                .get_resource_mut::<SpatialIndex>()
+                .unwrap()
+                .0
+                .retain(|e| *e != entity);
+        });
+        let index = |world: &World| world.get_resource::<SpatialIndex>().unwrap().0.clone();
+
+        let entity_a = world.new_entity();
+        world.add_component(entity_a, Transform);
+        // Replacing the component does not add it again
+        world.add_component(entity_a, Transform);
+        assert_eq!(index(&world), vec![entity_a]);
+
+        let mut commands = world.commands();
+        let mut builder = commands.spawn_entity();
+        builder.with_component(Transform);
+        let entity_b = builder.build();
+        world.flush();
+        assert_eq!(index(&world), vec![entity_a, entity_b]);
+
+        world.remove_component::<Transform>(entity_a);
+        assert_eq!(index(&world), vec![entity_b]);
+
+        world.entity_scope(entity_a, |scope| {
+            scope.add(Transform);
+        });
+        assert_eq!(index(&world), vec![entity_b, entity_a]);
+
+        world.commands().remove_component::<Transform>(entity_a);
+        world.destroy_entity(entity_b);
+        world.flush();
+        assert!(index(&world).is_empty());
+    }
+
+    #[test]
+    fn flush_commands() {
+        struct TestComponent;
+
+        let counter = Arc::<RwLock<usize>>::default();
+        let counter_2 = counter.clone();
+        let mut world = World::new();
+        world.add_system(0, move |query: Query<&TestComponent>| {
+            *counter_2.write().unwrap() = query.iter().count();
+        });
+
+        let mut commands = world.commands();
+        let mut builder = commands.spawn_entity();
+        builder.with_component(TestComponent);
+        let entity = builder.build();
+        world.flush();
+        assert!(world.contains(entity));
+        assert_eq!(*counter.read().unwrap(), 0);
+
+        // The scheduled systems see the spawned entity
+        world.update(0);
+        assert_eq!(*counter.read().unwrap(), 1);
+    }
+
+    #[test]
+    fn drain_entities() {
+        struct DropCounter(Arc<RwLock<usize>>);
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                *self.0.write().unwrap() += 1;
+            }
+        }
+
+        let drops = Arc::<RwLock<usize>>::default();
+        let mut world = World::new();
+        let mut entities = vec![];
+        for _ in 0..10 {
+            let entity = world.new_entity();
+            world.add_component(entity, DropCounter(drops.clone()));
+            entities.push(entity);
+        }
+        world.add_system(0, |query: Query<&DropCounter>| {
+            assert_eq!(query.iter().count(), 0);
+        });
+
+        let mut drained: Vec<_> = world.drain_entities().collect();
+        drained.sort();
+        assert_eq!(drained, entities);
+        assert_eq!(*drops.read().unwrap(), 10);
+        assert!(entities.iter().all(|entity| !world.contains(*entity)));
+
+        // The systems no longer see the destroyed entities
+        world.update(0);
+    }
+
+    #[test]
+    fn linear_scheduler_orderings() {
+        let runs = Arc::<Mutex<Vec<&'static str>>>::default();
+        let mut world = KecsWorld::<LinearScheduler>::new();
+        let runs_a = runs.clone();
+        let system_a = world.add_system(0, move || runs_a.lock().unwrap().push("a"));
+        let runs_b = runs.clone();
+        let system_b = world.add_system(0, move || runs_b.lock().unwrap().push("b"));
+        let runs_c = runs.clone();
+        world.add_system(0, move || runs_c.lock().unwrap().push("c"));
+
+        // The systems without orderings run in the order they were added
+        assert_eq!(
+            world.scheduler_mut(0).add_ordering(system_b, system_a),
+            Ok(true)
+        );
+        world.update(0);
+        assert_eq!(*runs.lock().unwrap(), vec!["b", "a", "c"]);
+
+        assert_eq!(world.scheduler_mut(0).add_ordering(system_b, 10), Ok(false));
+        // The cycle is rejected when the ordering is added, so the systems can still run
+        let error = world
+            .scheduler_mut(0)
+            .add_ordering(system_a, system_b)
+            .unwrap_err();
+        assert_eq!(error.systems.len(), 2);
+        world.update(0);
+        assert_eq!(*runs.lock().unwrap(), vec!["b", "a", "c", "b", "a", "c"]);
+    }
 }
@@ -1,8 +1,18 @@
-use std::{collections::HashSet, marker::PhantomData};
+use std::{
+    collections::{btree_set, BTreeSet},
+    iter::Flatten,
+    marker::PhantomData,
+    sync::atomic::Ordering,
+};
+
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
 use crate::{
-    archetype::ArchetypeId, sparse_set::SparseSet, world_container::WorldContainer, ComponentId,
-    Entity, UnsafeWorldPtr,
+    archetype::{ArchetypeId, ArchetypeManager},
+    change_detection::{ComponentTicks, SystemTicks},
+    sparse_set::SparseSet,
+    world_container::WorldContainer,
+    Bundle, Commands, ComponentId, Entity, UnsafeWorldPtr,
 };
 
 /// This Enum is used to represent how a resource (Component, Resource) is accessed
@@ -22,12 +32,142 @@ pub trait QueryParam {
         component_set: &mut SparseSet<ComponentId, AccessMode>,
     );
 
+    /// This method must be used to signal the components that an entity must have to be iterated by the query:
+    /// by default, all the components signaled in [`QueryParam::compute_component_set`] are required
+    fn compute_required_set(
+        store: &mut WorldContainer,
+        component_set: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+        Self::compute_component_set(store, component_set)
+    }
+
     /// This method can be used to further filter the entities that should be accessed by a query
     fn can_extract(store: &WorldContainer, entity: Entity) -> bool;
 
+    /// The value extracted for each entity, borrowing the components for the lifetime `'q` of the borrow
+    /// of the [`Query`] (e.g `&'q mut T` for `&mut T`)
+    type Item<'q>;
+
+    /// # Safety
+    /// The parameter must only be extracted for the entity specified, without breaking Rust's alising rules.
+    /// `ticks` are the ticks of the system that is extracting the parameter
+    unsafe fn extract<'q>(
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q>;
+
+    /// The data resolved once before iterating the archetypes of a query (e.g the ids of the components),
+    /// see [`Query::for_each_mut`]
+    type Fetch: Copy + Send + Sync;
+
+    /// This method is used to resolve the [`QueryParam::Fetch`] used by [`QueryParam::extract_fetched`]
+    fn init_fetch(store: &WorldContainer) -> Self::Fetch;
+
+    /// Like [`QueryParam::extract`], but using the data resolved by [`QueryParam::init_fetch`]
+    /// # Safety
+    /// The same rules of [`QueryParam::extract`] apply. The entity's components are not checked,
+    /// so the entity must belong to an archetype matching the query
+    unsafe fn extract_fetched<'q>(
+        fetch: Self::Fetch,
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q>;
+
+    /// This method must return true if the parameter accesses components that are not known in advance
+    /// (and so can't be signaled in [`QueryParam::compute_component_set`]), see [`EntityRef`]:
+    /// the systems with such a parameter are never run in parallel with other systems
+    fn is_exclusive() -> bool {
+        false
+    }
+}
+
+/// The trait shared by all the types that can be used to skip some of the entities iterated by a [`Query`],
+/// depending on the state of their components, see [`crate::ChangedBy`]
+pub trait QueryFilter {
+    /// `false` only if the filter never skips an entity
+    const CAN_SKIP: bool = true;
+
+    /// This method must be used to signal all the components read by the filter
+    fn compute_component_set(
+        store: &mut WorldContainer,
+        component_set: &mut SparseSet<ComponentId, AccessMode>,
+    );
+
+    /// Returns true if the entity must be iterated by the query
     /// # Safety
-    /// The parameter must only be extracted for the entity specified, without breaking Rust's alising rules
-    unsafe fn extract(store: &UnsafeWorldPtr, entity: Entity) -> Self;
+    /// The filter must only read the components signaled in [`QueryFilter::compute_component_set`]
+    unsafe fn matches(store: &UnsafeWorldPtr, entity: Entity, ticks: SystemTicks) -> bool;
+}
+
+impl QueryFilter for () {
+    const CAN_SKIP: bool = false;
+
+    fn compute_component_set(
+        _store: &mut WorldContainer,
+        _component_set: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+    }
+
+    unsafe fn matches(_store: &UnsafeWorldPtr, _entity: Entity, _ticks: SystemTicks) -> bool {
+        true
+    }
+}
+
+/// A [`QueryParam`] giving read-only access to all the components of an entity, without naming their types.
+/// This is useful e.g for inspectors or serializers: since any component can be read, the systems using
+/// this parameter are never run in parallel with other systems
+/// e.g
+/// ```
+/// use kecs::{EntityRef, Query};
+/// struct Health(u32);
+/// fn inspect(query: Query<EntityRef>) {
+///     for entity in query.iter() {
+///         println!("{:?} has {} components", entity.entity(), entity.component_ids().len());
+///         if let Some(health) = entity.get::<Health>() {
+///             println!("Health: {}", health.0);
+///         }
+///     }
+/// }
+/// ```
+pub struct EntityRef<'world> {
+    entity: Entity,
+    world: &'world WorldContainer,
+}
+
+impl<'world> EntityRef<'world> {
+    /// Gets the referenced [`Entity`]
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Gets a reference to the component `T` of the entity, if it has one
+    pub fn get<T: 'static>(&self) -> Option<&'world T> {
+        self.world.get_component::<T>(self.entity)
+    }
+
+    /// Returns true if the entity has the component `T`
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.world.entity_has_component::<T>(self.entity)
+    }
+
+    /// Gets the [`ComponentId`]s of all the components of the entity
+    pub fn component_ids(&self) -> Vec<ComponentId> {
+        self.world
+            .get_entity_info(self.entity)
+            .map(|info| info.components.iter().map(|(id, _)| id).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Implemented by the [`QueryParam`]s that never write to the components they access (e.g `&T`, [`Entity`]):
+/// only the queries of read-only parameters can be iterated with [`Query::iter`], which takes `&self`.
+/// This trait is sealed and cannot be implemented outside of kecs
+pub trait ReadOnlyQueryParam: QueryParam + sealed::Sealed {}
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
 }
 
 /// A Query is used by a system to iterate all the components matching the query's parameters
@@ -47,8 +187,8 @@ pub trait QueryParam {
 ///     world.add_component(entity, TestComponentB(3.14));
 /// }
 /// // This query will iterate all components that have a `TestComponentA`, aka both components in the world
-/// fn query_a(query: Query<&mut TestComponentA>) {
-///    for item in query.iter() {
+/// fn query_a(mut query: Query<&mut TestComponentA>) {
+///    for item in query.iter_mut() {
 ///        item.0 = 10;
 ///    }
 /// }
@@ -64,51 +204,251 @@ pub trait QueryParam {
 /// world.add_system(0, query_b);
 /// world.update(0);
 /// ```
-pub struct Query<'world, 'state, A: QueryParam> {
-    _ph: PhantomData<A>,
+/// The optional [`QueryFilter`] `F` can be used to skip some of the entities, see [`crate::ChangedBy`]
+pub struct Query<'world, 'state, A: QueryParam, F: QueryFilter = ()> {
+    _ph: PhantomData<(A, F)>,
     state: &'state QueryState,
     world_ptr: UnsafeWorldPtr<'world>,
+    ticks: SystemTicks,
 }
 
-/// The state of a [`Query`], used to e.g cache the entities that should be iterated by the [`Query`]
+/// The state of a [`Query`], used to e.g cache the archetypes whose entities should be iterated by the [`Query`]
 #[derive(Default)]
 pub struct QueryState {
-    pub(crate) entities: HashSet<Entity>,
+    // The archetypes matching the query, in the order they were created: an entity is iterated by the query
+    // only if its archetype is in this list. Archetypes are never removed, even when they no longer have any entity
+    pub(crate) archetypes: Vec<ArchetypeId>,
+    // The number of archetypes of the ArchetypeManager that were already checked
+    pub(crate) checked_archetypes: usize,
     pub(crate) query_archetype: ArchetypeId,
+    // The number of consecutive runs in which the query matched no entities
+    pub(crate) empty_runs: usize,
+}
+
+impl QueryState {
+    // Checks the archetypes created since the last update, storing the ones matching the query
+    pub(crate) fn update_archetypes(&mut self, archetype_manager: &ArchetypeManager) {
+        let query_archetype = archetype_manager
+            .get_archetype(self.query_archetype)
+            .expect("Failed to get system archetype");
+        for (id, archetype) in archetype_manager.archetypes_from(self.checked_archetypes) {
+            if archetype.includes_fully(query_archetype) {
+                self.archetypes.push(id);
+            }
+        }
+        self.checked_archetypes = archetype_manager.len();
+    }
+
+    // The entities of each archetype matching the query
+    pub(crate) fn archetype_entities<'a>(
+        &'a self,
+        archetype_manager: &'a ArchetypeManager,
+    ) -> impl Iterator<Item = &'a BTreeSet<Entity>> + 'a {
+        self.archetypes.iter().map(|id| {
+            &archetype_manager
+                .get_archetype(*id)
+                .expect("Failed to get query archetype")
+                .entities
+        })
+    }
 }
 
 /// An Iterator over the [`Query`] parameters.
-pub struct QueryIterator<'world, 'state, A: QueryParam> {
-    _ph: PhantomData<A>,
+/// The entities are iterated archetype by archetype, in ascending order within each archetype: as long as the entities
+/// don't change archetype, adaptors such as `take(n)` or `rev()` are stable across updates
+pub struct QueryIterator<'world, 'state, A: QueryParam, F: QueryFilter = ()> {
+    _ph: PhantomData<(F, &'state QueryState)>,
     world_ptr: UnsafeWorldPtr<'world>,
-    entity_iterator: std::collections::hash_set::Iter<'state, Entity>,
+    entity_iterator: Flatten<std::vec::IntoIter<btree_set::Iter<'world, Entity>>>,
+    // The number of entities left, including the ones that the filter may skip
+    remaining: usize,
+    fetch: A::Fetch,
+    ticks: SystemTicks,
 }
 
-impl<'world, 'state, A: QueryParam> Query<'world, 'state, A> {
+impl<'world, 'state, A: QueryParam, F: QueryFilter> Query<'world, 'state, A, F> {
     pub(crate) fn create_query(
         state: &'state QueryState,
         world_ptr: UnsafeWorldPtr<'world>,
+        ticks: SystemTicks,
     ) -> Self {
         Self {
             _ph: PhantomData,
             state,
             world_ptr,
+            ticks,
         }
     }
 
-    /// Creates a [`QueryIterator`] for the entitiets matching the query parameters
-    pub fn iter(&self) -> QueryIterator<'world, 'state, A> {
+    /// Creates a [`QueryIterator`] for the entities matching the query parameters, which may write
+    /// to the components: the iterator and its items borrow the query mutably, so that only one iterator can be alive at a time
+    pub fn iter_mut(&mut self) -> QueryIterator<'_, '_, A, F> {
+        self.iter_unchecked()
+    }
+
+    /// Like [`Query::iter_mut`], but each item is paired with its [`Entity`], see [`Query::iter_entities`]
+    pub fn iter_entities_mut(&mut self) -> impl Iterator<Item = (Entity, A::Item<'_>)> + '_ {
+        let mut iter = self.iter_unchecked();
+        std::iter::from_fn(move || iter.next_with_entity())
+    }
+
+    /// Calls `fun` on each of the entities matching the query parameters, spreading the entities across threads.
+    /// Each entity is visited exactly once, so the items never alias each other: the query is borrowed mutably
+    /// for the whole call, so that no other iterator can be alive at the same time
+    pub fn par_iter_mut<'q>(&'q mut self, fun: impl Fn(A::Item<'q>) + Send + Sync)
+    where
+        A::Item<'q>: Send,
+    {
+        self.par_iter_unchecked(fun)
+    }
+
+    /// Calls `fun` on each of the entities matching the query parameters, which may write to the components.
+    /// The entities are visited in the same order of [`Query::iter_mut`], but without the overhead of an iterator
+    pub fn for_each_mut<'q>(&'q mut self, fun: impl FnMut(A::Item<'q>)) {
+        self.for_each_unchecked(fun)
+    }
+
+    /// Returns the number of entities matched by the query, which is not the number of components accessed
+    /// by each item: without a [`QueryFilter`] the entities are counted without iterating them
+    pub fn len(&self) -> usize {
+        if F::CAN_SKIP {
+            self.matching_entities().count()
+        } else {
+            // SAFETY: The archetypes are not changed while a system is running
+            let world = unsafe { self.world_ptr.get() };
+            self.state
+                .archetype_entities(world.get_archetype_manager())
+                .map(BTreeSet::len)
+                .sum()
+        }
+    }
+
+    /// Returns true if the query matches no entities, see [`Query::len`]
+    pub fn is_empty(&self) -> bool {
+        self.matching_entities().next().is_none()
+    }
+
+    /// Returns the single element iterated by this query, panics if there is more than one
+    /// or if there are no items
+    pub fn single_mut(&mut self) -> A::Item<'_> {
+        Self::single_from(self.iter_unchecked())
+    }
+
+    /// Like [`Query::single_or_spawn`], but for queries that may write to the components
+    pub fn single_or_spawn_mut<B: Bundle>(
+        &mut self,
+        commands: &mut Commands,
+        spawn: impl FnOnce() -> B,
+    ) -> Option<A::Item<'_>> {
+        Self::single_or_spawn_from(self.iter_unchecked(), commands, spawn)
+    }
+
+    // The entities iterated by the query, without extracting their components
+    fn matching_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        // SAFETY: The archetypes are not changed while a system is running
+        let world = unsafe { self.world_ptr.get() };
+        self.state
+            .archetype_entities(world.get_archetype_manager())
+            .flatten()
+            .copied()
+            // SAFETY: The filter components were added to the system's dependencies
+            .filter(|entity| unsafe { F::matches(&self.world_ptr, *entity, self.ticks) })
+    }
+
+    // The caller must ensure that the items of the iterator don't alias the items of another iterator
+    fn iter_unchecked(&self) -> QueryIterator<'_, '_, A, F> {
+        // SAFETY: The archetypes are not changed while a system is running
+        let world = unsafe { self.world_ptr.get() };
+        let entity_iterators = self
+            .state
+            .archetype_entities(world.get_archetype_manager())
+            .map(|entities| entities.iter())
+            .collect::<Vec<_>>();
         QueryIterator {
             _ph: PhantomData,
             world_ptr: self.world_ptr.clone(),
-            entity_iterator: self.state.entities.iter(),
+            remaining: entity_iterators.iter().map(|iter| iter.len()).sum(),
+            entity_iterator: entity_iterators.into_iter().flatten(),
+            fetch: A::init_fetch(world),
+            ticks: self.ticks,
         }
     }
 
-    /// Returns the single element iterated by this query, panics if there is more than one
-    /// or if there are no items
-    pub fn single(&self) -> A {
-        let mut iter = self.iter();
+    // The caller must ensure that the items don't alias the items of another iterator
+    fn for_each_unchecked<'q>(&'q self, mut fun: impl FnMut(A::Item<'q>)) {
+        let (world_ptr, ticks) = (&self.world_ptr, self.ticks);
+        // SAFETY: The archetypes are not changed while a system is running
+        let world = unsafe { world_ptr.get() };
+        let fetch = A::init_fetch(world);
+        for entities in self.state.archetype_entities(world.get_archetype_manager()) {
+            for entity in entities {
+                // SAFETY: The entity belongs to an archetype matching the query, and the system scheduler
+                // ensures that no other system writes to the components
+                if let Some(item) =
+                    unsafe { extract_matching::<A, F>(fetch, world_ptr, *entity, ticks) }
+                {
+                    fun(item)
+                }
+            }
+        }
+    }
+
+    // The caller must ensure that the items don't alias the items of another iterator
+    fn par_iter_unchecked<'q>(&'q self, fun: impl Fn(A::Item<'q>) + Send + Sync)
+    where
+        A::Item<'q>: Send,
+    {
+        let (world_ptr, ticks) = (&self.world_ptr, self.ticks);
+        // SAFETY: The archetypes are not changed while a system is running
+        let world = unsafe { world_ptr.get() };
+        let fetch = A::init_fetch(world);
+        let run = |entity: &Entity| {
+            // SAFETY: Each entity is visited once, and it belongs to an archetype matching the query.
+            // The system scheduler ensures that no other system writes to the components
+            if let Some(item) =
+                unsafe { extract_matching::<A, F>(fetch, world_ptr, *entity, ticks) }
+            {
+                fun(item)
+            }
+        };
+        let archetype_entities = self
+            .state
+            .archetype_entities(world.get_archetype_manager())
+            .collect::<Vec<_>>();
+        if cfg!(miri) {
+            // Don't use rayon with miri, see GraphScheduler::execute
+            archetype_entities.into_iter().flatten().for_each(run)
+        } else {
+            archetype_entities
+                .into_par_iter()
+                .flat_map(|entities| entities.par_iter())
+                .for_each(run)
+        }
+    }
+
+    fn single_or_spawn_from<'q, B: Bundle>(
+        mut iter: QueryIterator<'q, '_, A, F>,
+        commands: &mut Commands,
+        spawn: impl FnOnce() -> B,
+    ) -> Option<A::Item<'q>> {
+        match iter.next() {
+            Some(first) => {
+                assert!(
+                    iter.next().is_none(),
+                    "The query iterates more than one item"
+                );
+                Some(first)
+            }
+            None => {
+                let mut builder = commands.spawn_entity();
+                spawn().add_to_builder(&mut builder);
+                builder.build();
+                None
+            }
+        }
+    }
+
+    fn single_from<'q>(mut iter: QueryIterator<'q, '_, A, F>) -> A::Item<'q> {
         let first = iter.next().expect("No entity matches the query");
         assert!(
             iter.next().is_none(),
@@ -118,16 +458,211 @@ impl<'world, 'state, A: QueryParam> Query<'world, 'state, A> {
     }
 }
 
-impl<'world, 'state, A: QueryParam> Iterator for QueryIterator<'world, 'state, A> {
-    type Item = A;
+impl<'world, 'state, A: ReadOnlyQueryParam, F: QueryFilter> Query<'world, 'state, A, F> {
+    /// Creates a [`QueryIterator`] for the entities matching the query parameters.
+    /// Since the parameters are read-only, any number of iterators can be alive at the same time, see [`Query::iter_mut`]
+    pub fn iter(&self) -> QueryIterator<'_, '_, A, F> {
+        self.iter_unchecked()
+    }
+
+    /// Like [`Query::par_iter_mut`], but since the parameters are read-only the query is not borrowed mutably
+    pub fn par_iter<'q>(&'q self, fun: impl Fn(A::Item<'q>) + Send + Sync)
+    where
+        A::Item<'q>: Send,
+    {
+        self.par_iter_unchecked(fun)
+    }
+
+    /// Like [`Query::for_each_mut`], but since the parameters are read-only the query is not borrowed mutably
+    pub fn for_each<'q>(&'q self, fun: impl FnMut(A::Item<'q>)) {
+        self.for_each_unchecked(fun)
+    }
+
+    /// Like [`Query::iter`], but each item is paired with its [`Entity`], without adding [`Entity`] to the parameters
+    pub fn iter_entities(&self) -> impl Iterator<Item = (Entity, A::Item<'_>)> + '_ {
+        let mut iter = self.iter();
+        std::iter::from_fn(move || iter.next_with_entity())
+    }
+
+    /// Creates a [`QueryIterator`] that yields at most `n` items: since the iteration order is stable,
+    /// the same entities are yielded each time the query is iterated
+    pub fn iter_limited(&self, n: usize) -> std::iter::Take<QueryIterator<'_, '_, A, F>> {
+        self.iter().take(n)
+    }
+
+    /// Returns the single element iterated by this query, panics if there is more than one
+    /// or if there are no items
+    pub fn single(&self) -> A::Item<'_> {
+        Self::single_from(self.iter())
+    }
+
+    /// Returns the single element iterated by this query, or queues the spawning of a new entity if there are no items:
+    /// `spawn` creates the components of the new entity, which will be iterated from the next update.
+    /// Panics if the query iterates more than one item
+    pub fn single_or_spawn<B: Bundle>(
+        &self,
+        commands: &mut Commands,
+        spawn: impl FnOnce() -> B,
+    ) -> Option<A::Item<'_>> {
+        Self::single_or_spawn_from(self.iter(), commands, spawn)
+    }
+}
+
+impl<'world, 'state, 'a, A: 'static, F: QueryFilter> Query<'world, 'state, &'a A, F> {
+    /// Like [`Query::iter`], but each component is yielded along with its [`ComponentTicks`],
+    /// so that the changes can be compared manually e.g against [`SystemTicks`]
+    pub fn iter_with_ticks(&self) -> impl Iterator<Item = (&A, ComponentTicks)> + '_ {
+        let (world_ptr, ticks) = (&self.world_ptr, self.ticks);
+        // SAFETY: The archetypes are not changed while a system is running
+        let world = unsafe { world_ptr.get() };
+        let fetch = <&A>::init_fetch(world);
+        self.state
+            .archetype_entities(world.get_archetype_manager())
+            .flatten()
+            .filter_map(move |entity| {
+                // SAFETY: The entity belongs to an archetype matching the query, and the system scheduler
+                // ensures that no other system writes to the component
+                unsafe {
+                    let component = extract_matching::<&A, F>(fetch, world_ptr, *entity, ticks)?;
+                    let ticks = ComponentTicks {
+                        added: world_ptr
+                            .get_added_tick_by_id(*entity, fetch)
+                            .load(Ordering::Relaxed),
+                        changed: world_ptr
+                            .get_changed_tick_by_id(*entity, fetch)
+                            .load(Ordering::Relaxed),
+                    };
+                    Some((component, ticks))
+                }
+            })
+    }
+}
+
+impl<'world, 'state, A: QueryParam, F: QueryFilter> Iterator
+    for QueryIterator<'world, 'state, A, F>
+{
+    type Item = A::Item<'world>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.entity_iterator
-            .next()
-            // SAFETY: The system scheduler must ensure that this unsafe call is safe
-            .map(|e| unsafe { A::extract(&self.world_ptr, *e) })
+        self.next_with_entity().map(|(_, item)| item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if F::CAN_SKIP {
+            (0, Some(self.remaining))
+        } else {
+            (self.remaining, Some(self.remaining))
+        }
+    }
+}
+
+impl<'world, 'state, A: QueryParam, F: QueryFilter> DoubleEndedIterator
+    for QueryIterator<'world, 'state, A, F>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while let Some(entity) = self.entity_iterator.next_back() {
+            self.remaining -= 1;
+            if let Some(item) = self.extract_if_matches(*entity) {
+                return Some(item);
+            }
+        }
+        None
     }
 }
+
+impl<'world, 'state, A: QueryParam, F: QueryFilter> QueryIterator<'world, 'state, A, F> {
+    fn next_with_entity(&mut self) -> Option<(Entity, A::Item<'world>)> {
+        while let Some(entity) = self.entity_iterator.next() {
+            self.remaining -= 1;
+            if let Some(item) = self.extract_if_matches(*entity) {
+                return Some((*entity, item));
+            }
+        }
+        None
+    }
+
+    fn extract_if_matches(&self, entity: Entity) -> Option<A::Item<'world>> {
+        // SAFETY: The entity belongs to an archetype matching the query, and the system scheduler
+        // must ensure that no other system writes to the components
+        unsafe { extract_matching::<A, F>(self.fetch, &self.world_ptr, entity, self.ticks) }
+    }
+}
+
+// Extracts the query parameters of the entity, if the filter matches it: the same safety rules of
+// QueryParam::extract_fetched apply. In debug builds the components of the entity are checked again,
+// so that an entity whose archetype was not updated after a component was removed is skipped instead of panicking
+unsafe fn extract_matching<'q, A: QueryParam, F: QueryFilter>(
+    fetch: A::Fetch,
+    world_ptr: &UnsafeWorldPtr,
+    entity: Entity,
+    ticks: SystemTicks,
+) -> Option<A::Item<'q>> {
+    if cfg!(debug_assertions) && !A::can_extract(world_ptr.get(), entity) {
+        return None;
+    }
+    // SAFETY: The filter components were added to the system's dependencies
+    if F::matches(world_ptr, entity, ticks) {
+        Some(A::extract_fetched(fetch, world_ptr, entity, ticks))
+    } else {
+        None
+    }
+}
+
+// Only the unfiltered queries know in advance how many entities are iterated
+impl<'world, 'state, A: QueryParam> ExactSizeIterator for QueryIterator<'world, 'state, A> {}
+
+impl sealed::Sealed for Entity {}
+impl ReadOnlyQueryParam for Entity {}
+impl<'world> sealed::Sealed for EntityRef<'world> {}
+impl<'world> ReadOnlyQueryParam for EntityRef<'world> {}
+impl<A: 'static> sealed::Sealed for &A {}
+impl<A: 'static> ReadOnlyQueryParam for &A {}
+impl<A: ReadOnlyQueryParam> sealed::Sealed for Option<A> {}
+impl<A: ReadOnlyQueryParam> ReadOnlyQueryParam for Option<A> {}
+
+impl<'world> QueryParam for EntityRef<'world> {
+    fn compute_component_set(
+        _store: &mut WorldContainer,
+        _component_set: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+    }
+
+    fn can_extract(_store: &WorldContainer, _entity: Entity) -> bool {
+        true
+    }
+
+    type Item<'q> = EntityRef<'q>;
+
+    unsafe fn extract<'q>(
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        EntityRef {
+            entity,
+            // SAFETY: The system is exclusive, so no other system can write to the components of the entity
+            world: std::mem::transmute::<&WorldContainer, &WorldContainer>(store.get()),
+        }
+    }
+
+    type Fetch = ();
+
+    fn init_fetch(_store: &WorldContainer) -> Self::Fetch {}
+
+    unsafe fn extract_fetched<'q>(
+        _fetch: Self::Fetch,
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        Self::extract(store, entity, ticks)
+    }
+
+    fn is_exclusive() -> bool {
+        true
+    }
+}
+
 impl QueryParam for Entity {
     fn compute_component_set(
         _store: &mut WorldContainer,
@@ -137,7 +672,26 @@ impl QueryParam for Entity {
     fn can_extract(_store: &WorldContainer, _entity: Entity) -> bool {
         true
     }
-    unsafe fn extract(_store: &UnsafeWorldPtr, entity: Entity) -> Self {
+    type Item<'q> = Entity;
+
+    unsafe fn extract<'q>(
+        _store: &UnsafeWorldPtr,
+        entity: Entity,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        entity
+    }
+
+    type Fetch = ();
+
+    fn init_fetch(_store: &WorldContainer) -> Self::Fetch {}
+
+    unsafe fn extract_fetched<'q>(
+        _fetch: Self::Fetch,
+        _store: &UnsafeWorldPtr,
+        entity: Entity,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'q> {
         entity
     }
 }
@@ -146,10 +700,31 @@ impl<A> QueryParam for &A
 where
     A: 'static,
 {
-    unsafe fn extract(store: &UnsafeWorldPtr, entity: Entity) -> Self {
+    type Item<'q> = &'q A;
+
+    unsafe fn extract<'q>(
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'q> {
         std::mem::transmute(store.get_component::<A>(entity).get())
     }
 
+    type Fetch = ComponentId;
+
+    fn init_fetch(store: &WorldContainer) -> Self::Fetch {
+        store.get_component_id_assertive::<A>()
+    }
+
+    unsafe fn extract_fetched<'q>(
+        fetch: Self::Fetch,
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        std::mem::transmute(store.get_component_by_id::<A>(entity, fetch).get())
+    }
+
     fn can_extract(store: &WorldContainer, entity: Entity) -> bool {
         store.entity_has_component::<A>(entity)
     }
@@ -159,9 +734,7 @@ where
         component_set: &mut SparseSet<ComponentId, AccessMode>,
     ) {
         let id = store.get_or_create_component_id::<A>();
-        if !component_set.insert(id, AccessMode::Read) {
-            panic!("Query accesses twice the same component type! This is not allowed");
-        }
+        add_component_access(component_set, id, AccessMode::Read);
     }
 }
 
@@ -169,10 +742,38 @@ impl<A> QueryParam for &mut A
 where
     A: 'static,
 {
-    unsafe fn extract(store: &UnsafeWorldPtr, entity: Entity) -> Self {
+    // Since the component could be written through the reference, it is always marked as changed
+    type Item<'q> = &'q mut A;
+
+    unsafe fn extract<'q>(
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        store
+            .get_changed_tick::<A>(entity)
+            .store(ticks.this_run, Ordering::Relaxed);
         std::mem::transmute(store.get_component_mut::<A>(entity))
     }
 
+    type Fetch = ComponentId;
+
+    fn init_fetch(store: &WorldContainer) -> Self::Fetch {
+        store.get_component_id_assertive::<A>()
+    }
+
+    unsafe fn extract_fetched<'q>(
+        fetch: Self::Fetch,
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        store
+            .get_changed_tick_by_id(entity, fetch)
+            .store(ticks.this_run, Ordering::Relaxed);
+        std::mem::transmute(store.get_component_mut_by_id::<A>(entity, fetch))
+    }
+
     fn can_extract(store: &WorldContainer, entity: Entity) -> bool {
         store.entity_has_component::<A>(entity)
     }
@@ -181,20 +782,115 @@ where
         component_set: &mut SparseSet<ComponentId, AccessMode>,
     ) {
         let id = store.get_or_create_component_id::<A>();
-        if !component_set.insert(id, AccessMode::Write) {
-            panic!("Query accesses twice the same component type! This is not allowed");
+        add_component_access(component_set, id, AccessMode::Write);
+    }
+}
+/// An optional parameter is extracted only for the entities having its components, otherwise it's `None`:
+/// unlike the other parameters, it doesn't restrict the entities iterated by the query
+/// e.g
+/// ```
+/// use kecs::Query;
+/// struct Health(u32);
+/// struct Shield(u32);
+/// fn damage(mut query: Query<(&mut Health, Option<&Shield>)>) {
+///     for (health, shield) in query.iter_mut() {
+///         let absorbed = shield.map_or(0, |shield| shield.0);
+///         health.0 = health.0.saturating_sub(10_u32.saturating_sub(absorbed));
+///     }
+/// }
+/// ```
+impl<A: QueryParam> QueryParam for Option<A> {
+    fn compute_component_set(
+        store: &mut WorldContainer,
+        component_set: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+        A::compute_component_set(store, component_set)
+    }
+
+    fn compute_required_set(
+        _store: &mut WorldContainer,
+        _component_set: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+    }
+
+    fn can_extract(_store: &WorldContainer, _entity: Entity) -> bool {
+        true
+    }
+
+    type Item<'q> = Option<A::Item<'q>>;
+
+    unsafe fn extract<'q>(
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        A::can_extract(store.get(), entity).then(|| A::extract(store, entity, ticks))
+    }
+
+    type Fetch = A::Fetch;
+
+    fn init_fetch(store: &WorldContainer) -> Self::Fetch {
+        A::init_fetch(store)
+    }
+
+    unsafe fn extract_fetched<'q>(
+        fetch: Self::Fetch,
+        store: &UnsafeWorldPtr,
+        entity: Entity,
+        ticks: SystemTicks,
+    ) -> Self::Item<'q> {
+        // The archetypes of the query don't tell if the entity has the optional components
+        A::can_extract(store.get(), entity).then(|| A::extract_fetched(fetch, store, entity, ticks))
+    }
+
+    fn is_exclusive() -> bool {
+        A::is_exclusive()
+    }
+}
+
+fn add_component_access(
+    component_set: &mut SparseSet<ComponentId, AccessMode>,
+    id: ComponentId,
+    access: AccessMode,
+) {
+    match component_set.get(&id) {
+        Some(previous) if *previous != access => {
+            panic!("Query both reads and writes {}", id.name());
+        }
+        Some(_) => {
+            panic!("Query accesses {} more than once", id.name());
+        }
+        None => {
+            component_set.insert(id, access);
         }
     }
 }
+
 macro_rules! impl_query_for_tuple {
     ($($t:ident)*) => {
+        impl<$($t: ReadOnlyQueryParam,)*> sealed::Sealed for ($($t,)*) {}
+        impl<$($t: ReadOnlyQueryParam,)*> ReadOnlyQueryParam for ($($t,)*) {}
+
         impl<$($t,)*> QueryParam for ($($t,)*)
         where
             $($t: QueryParam,)*
         {
+            type Item<'q> = ($($t::Item<'q>,)*);
+
+            unsafe fn extract<'q>(store: &UnsafeWorldPtr, entity: Entity, ticks: SystemTicks) -> Self::Item<'q> {
+                ($($t::extract(store, entity, ticks),)*)
+            }
+
+            type Fetch = ($($t::Fetch,)*);
+
+            fn init_fetch(store: &WorldContainer) -> Self::Fetch {
+                ($($t::init_fetch(store),)*)
+            }
 
-            unsafe fn extract(store: &UnsafeWorldPtr, entity: Entity) -> Self {
-                ($($t::extract(store, entity),)*)
+            #[allow(non_snake_case)]
+            unsafe fn extract_fetched<'q>(fetch: Self::Fetch, store: &UnsafeWorldPtr, entity: Entity, ticks: SystemTicks) -> Self::Item<'q> {
+                let ($($t,)*) = fetch;
+                ($($t::extract_fetched($t, store, entity, ticks),)*)
             }
 
             fn can_extract(store: &WorldContainer, entity: Entity) -> bool {
@@ -202,6 +898,20 @@ macro_rules! impl_query_for_tuple {
             }
             fn compute_component_set(store: &mut WorldContainer, component_set: &mut SparseSet<ComponentId, AccessMode>) {
                 $($t::compute_component_set(store, component_set);)*
+                // A parameter accessing unknown components (e.g EntityRef) could read the components written by the others
+                if Self::is_exclusive() {
+                    if let Some((id, _)) = component_set.iter().find(|(_, access)| **access == AccessMode::Write) {
+                        panic!("Query both reads and writes {}", id.name());
+                    }
+                }
+            }
+
+            fn compute_required_set(store: &mut WorldContainer, component_set: &mut SparseSet<ComponentId, AccessMode>) {
+                $($t::compute_required_set(store, component_set);)*
+            }
+
+            fn is_exclusive() -> bool {
+                $($t::is_exclusive() ||)* false
             }
         }
     };
@@ -225,3 +935,541 @@ impl_query_for_tuple!(A B C D E F G H I J K L M N O);
 impl_query_for_tuple!(A B C D E F G H I J K L M N O P);
 impl_query_for_tuple!(A B C D E F G H I J K L M N O P Q);
 impl_query_for_tuple!(A B C D E F G H I J K L M N O P Q R);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex, RwLock};
+
+    use crate::{
+        Commands, ComponentTicks, Entity, EntityRef, Mut, Query, Ref, World, WorldContainer,
+    };
+
+    struct Request;
+
+    #[test]
+    #[should_panic(expected = "Query both reads and writes")]
+    fn read_then_write_same_component() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<(&Request, &mut Request)>| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Query both reads and writes")]
+    fn write_then_read_same_component() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<(&mut Request, &Request)>| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Query accesses kecs::query::tests::Request more than once")]
+    fn write_twice_same_component() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<(&mut Request, &mut Request)>| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Query both reads and writes kecs::query::tests::Request")]
+    fn mut_then_ref_same_component() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<(Mut<Request>, Ref<Request>)>| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Query both reads and writes")]
+    fn optional_write_then_read_same_component() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<(Option<&mut Request>, &Request)>| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Query both reads and writes")]
+    fn nested_read_then_write_same_component() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<((Entity, &Request), &mut Request)>| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Query both reads and writes kecs::query::tests::Request")]
+    fn entity_ref_then_write() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<(EntityRef, &mut Request)>| {});
+    }
+
+    #[test]
+    fn entity_ref_then_read() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<(EntityRef, &Request)>| {});
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "reads all the components, but also writes kecs::query::tests::Request"
+    )]
+    fn entity_ref_and_write_in_different_queries() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<EntityRef>, _: Query<&mut Request>| {});
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "reads all the components, but also writes kecs::query::tests::Request"
+    )]
+    fn world_reader_and_write() {
+        let mut world = World::new();
+        world.add_system(0, |_: &WorldContainer, _: Query<&mut Request>| {});
+    }
+
+    #[test]
+    fn entity_ref_and_read_in_different_queries() {
+        let mut world = World::new();
+        world.add_system(0, |_: Query<EntityRef>, _: Query<&Request>| {});
+    }
+
+    #[test]
+    fn entity_in_any_position() {
+        struct A(Entity);
+        struct B(Entity);
+
+        let mut world = World::new();
+        let mut with_both = vec![];
+        for i in 0..6 {
+            let entity = world.new_entity();
+            world.add_component(entity, A(entity));
+            if i % 2 == 0 {
+                world.add_component(entity, B(entity));
+                with_both.push(entity);
+            }
+        }
+        let only_b = world.new_entity();
+        world.add_component(only_b, B(only_b));
+
+        let found = world.run_oneshot_with(|query: Query<(&A, Entity, &B)>| {
+            query
+                .iter()
+                .map(|(a, entity, b)| {
+                    assert_eq!(a.0, entity);
+                    assert_eq!(b.0, entity);
+                    entity
+                })
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(found, with_both);
+
+        let found = world.run_oneshot_with(|query: Query<(&B, Entity)>| {
+            query.iter().map(|(_, entity)| entity).collect::<Vec<_>>()
+        });
+        assert_eq!(found.len(), with_both.len() + 1);
+        assert!(found.contains(&only_b));
+    }
+
+    #[test]
+    fn single_or_spawn() {
+        struct Player(u32);
+
+        let mut world = World::new();
+        let found = Arc::<RwLock<Vec<Option<u32>>>>::default();
+        let found_2 = found.clone();
+        world.add_system(0, move |query: Query<&Player>, mut commands: Commands| {
+            let player = query.single_or_spawn(&mut commands, || (Player(42),));
+            found_2.write().unwrap().push(player.map(|p| p.0));
+        });
+
+        world.update(0);
+        world.update(0);
+        world.update(0);
+
+        assert_eq!(*found.read().unwrap(), vec![None, Some(42), Some(42)]);
+    }
+
+    #[test]
+    fn iter_limited_is_stable() {
+        let mut world = World::new();
+        let mut entities = (0..10)
+            .map(|_| {
+                let entity = world.new_entity();
+                world.add_component(entity, Request);
+                entity
+            })
+            .collect::<Vec<_>>();
+        entities.sort();
+
+        let processed = Arc::<RwLock<Vec<Entity>>>::default();
+        let processed_2 = processed.clone();
+        world.add_system(0, move |query: Query<(Entity, &Request)>| {
+            let mut processed = processed_2.write().unwrap();
+            processed.extend(query.iter_limited(3).map(|(e, _)| e));
+            processed.extend(query.iter().rev().take(2).map(|(e, _)| e));
+        });
+
+        world.update(0);
+        world.update(0);
+
+        let processed = processed.read().unwrap();
+        let expected = [
+            entities[0],
+            entities[1],
+            entities[2],
+            entities[9],
+            entities[8],
+        ];
+        assert_eq!(&processed[0..5], &expected);
+        assert_eq!(&processed[5..10], &expected);
+    }
+
+    #[test]
+    fn entity_ref() {
+        #[derive(Debug, PartialEq)]
+        struct Health(u32);
+        struct Name;
+        struct Unrelated;
+
+        let mut world = World::new();
+        let entity_a = world.new_entity();
+        world.add_component(entity_a, Health(10));
+        world.add_component(entity_a, Name);
+        let entity_b = world.new_entity();
+        world.add_component(entity_b, Unrelated);
+
+        let inspected = world.run_oneshot_with(|query: Query<EntityRef>| {
+            query
+                .iter()
+                .map(|entity| {
+                    (
+                        entity.entity(),
+                        entity.component_ids().len(),
+                        entity.get::<Health>().map(|h| h.0),
+                        entity.contains::<Name>(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            inspected,
+            vec![(entity_a, 2, Some(10), true), (entity_b, 1, None, false)]
+        );
+
+        // A system reading all the components can't run in parallel with a system writing to any component
+        world.add_system(0, |mut query: Query<&mut Unrelated>| {
+            query.iter_mut().for_each(|_| {});
+        });
+        world.add_system(0, |query: Query<EntityRef>| {
+            query.iter().for_each(|_| {});
+        });
+        assert_eq!(world.scheduler(0).schedule_groups().len(), 2);
+    }
+
+    #[test]
+    fn system_added_before_component_registration() {
+        struct Position(u32);
+        struct Velocity(u32);
+
+        let mut world = World::new();
+        let seen = Arc::<Mutex<Vec<(u32, u32)>>>::default();
+        let seen_2 = seen.clone();
+        // Neither component type is registered when the system is initialized
+        world.add_system(0, move |query: Query<(&Position, &Velocity)>| {
+            let mut seen = seen_2.lock().unwrap();
+            seen.clear();
+            seen.extend(query.iter().map(|(p, v)| (p.0, v.0)));
+        });
+        world.update(0);
+        assert!(seen.lock().unwrap().is_empty());
+
+        // The first component of each type is added to an entity that existed before the system
+        let first = world.new_entity();
+        world.add_component(first, Position(1));
+        world.update(0);
+        assert!(seen.lock().unwrap().is_empty());
+        world.add_component(first, Velocity(1));
+        world.update(0);
+        assert_eq!(*seen.lock().unwrap(), vec![(1, 1)]);
+
+        // Then a matching entity is spawned
+        let second = world.new_entity();
+        world.add_component(second, Velocity(2));
+        world.add_component(second, Position(2));
+        world.update(0);
+        let mut seen_sorted = seen.lock().unwrap().clone();
+        seen_sorted.sort();
+        assert_eq!(seen_sorted, vec![(1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn system_added_before_component_spawned_by_commands() {
+        struct Spawned;
+
+        let mut world = World::new();
+        let count = Arc::<Mutex<usize>>::default();
+        let count_2 = count.clone();
+        world.add_system(1, move |query: Query<&Spawned>| {
+            *count_2.lock().unwrap() = query.iter().count();
+        });
+        // The component is first registered through its TypeId when the command is executed
+        world.add_system(0, |mut commands: Commands| {
+            let mut builder = commands.spawn_entity();
+            builder.with_component(Spawned);
+            builder.build();
+        });
+        world.update(0);
+        world.update(1);
+        assert_eq!(*count.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn iter_entities() {
+        struct Position(u32);
+        struct Velocity;
+
+        let mut world = World::new();
+        let mut expected = vec![];
+        for i in 0..6 {
+            let entity = world.new_entity();
+            world.add_component(entity, Position(i));
+            if i % 2 == 0 {
+                world.add_component(entity, Velocity);
+                expected.push((entity, i + 1));
+            }
+        }
+
+        let seen = Arc::<Mutex<Vec<(Entity, u32)>>>::default();
+        let seen_2 = seen.clone();
+        world.add_system(0, |mut query: Query<(&mut Position, &Velocity)>| {
+            for (_, (position, _)) in query.iter_entities_mut() {
+                position.0 += 1;
+            }
+        });
+        world.add_system(1, move |query: Query<(&Position, &Velocity)>| {
+            *seen_2.lock().unwrap() = query
+                .iter_entities()
+                .map(|(entity, (position, _))| (entity, position.0))
+                .collect();
+        });
+        world.update(0);
+        world.update(1);
+
+        let mut seen = seen.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn len_and_is_empty() {
+        struct Position;
+        struct Velocity;
+
+        let mut world = World::new();
+        for i in 0..10 {
+            let entity = world.new_entity();
+            world.add_component(entity, Position);
+            if i % 2 == 0 {
+                world.add_component(entity, Velocity);
+            }
+        }
+
+        let counts = Arc::<Mutex<Vec<(usize, bool)>>>::default();
+        let counts_2 = counts.clone();
+        let counts_3 = counts.clone();
+        world.add_system(0, move |query: Query<(&Position, &Velocity)>| {
+            counts_2
+                .lock()
+                .unwrap()
+                .push((query.len(), query.is_empty()));
+        });
+        world.add_system(0, move |query: Query<(Entity, &mut Position)>| {
+            counts_3
+                .lock()
+                .unwrap()
+                .push((query.len(), query.is_empty()));
+        });
+        world.update(0);
+        world.remove_component_from_all::<Velocity>();
+        world.update(0);
+
+        let mut counts = counts.lock().unwrap().clone();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![(0, true), (5, false), (10, false), (10, false)]
+        );
+    }
+
+    #[test]
+    fn optional_param_does_not_restrict_membership() {
+        #[derive(Debug, PartialEq)]
+        struct Health(u32);
+        #[derive(Debug, PartialEq)]
+        struct Shield(u32);
+
+        let mut world = World::new();
+        let unshielded = world.new_entity();
+        world.add_component(unshielded, Health(10));
+        let shielded = world.new_entity();
+        world.add_component(shielded, Health(20));
+        world.add_component(shielded, Shield(5));
+        let only_shield = world.new_entity();
+        world.add_component(only_shield, Shield(1));
+
+        let found = world.run_oneshot_with(|query: Query<(Entity, &Health, Option<&Shield>)>| {
+            query
+                .iter()
+                .map(|(entity, health, shield)| (entity, health.0, shield.map(|s| s.0)))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&(unshielded, 10, None)));
+        assert!(found.contains(&(shielded, 20, Some(5))));
+
+        // The optional component can also be written
+        world.add_system(0, |mut query: Query<(&Health, Option<&mut Shield>)>| {
+            for (health, shield) in query.iter_mut() {
+                if let Some(shield) = shield {
+                    shield.0 += health.0;
+                }
+            }
+        });
+        world.update(0);
+        assert_eq!(world.get_component::<Shield>(shielded), Some(&Shield(25)));
+        assert_eq!(world.get_component::<Shield>(only_shield), Some(&Shield(1)));
+    }
+
+    #[test]
+    fn iter_with_ticks() {
+        struct Health(u32);
+
+        let mut world = World::new();
+        let entity = world.new_entity();
+        world.add_component(entity, Health(10));
+
+        let seen = Arc::<Mutex<Vec<ComponentTicks>>>::default();
+        let seen_2 = seen.clone();
+        world.add_system(0, move |query: Query<&Health>| {
+            let mut seen = seen_2.lock().unwrap();
+            seen.extend(query.iter_with_ticks().map(|(_, ticks)| ticks));
+        });
+        world.update(0);
+        world.get_component_mut::<Health>(entity).unwrap().0 = 5;
+        world.update(0);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0].added, seen[0].changed);
+        assert_eq!(seen[1].added, seen[0].added);
+        assert!(seen[1].changed > seen[0].changed);
+    }
+
+    #[test]
+    fn par_iter_mut() {
+        struct Counter(u32);
+
+        let mut world = World::new();
+        let entities = (0..100)
+            .map(|_| {
+                let entity = world.new_entity();
+                world.add_component(entity, Counter(0));
+                entity
+            })
+            .collect::<Vec<_>>();
+
+        let visited = Arc::<Mutex<Vec<Entity>>>::default();
+        let visited_2 = visited.clone();
+        world.add_system(0, move |mut query: Query<(Entity, &mut Counter)>| {
+            query.par_iter_mut(|(entity, counter)| {
+                counter.0 += 1;
+                visited_2.lock().unwrap().push(entity);
+            });
+        });
+        world.update(0);
+        world.update(0);
+
+        let mut visited = visited.lock().unwrap().clone();
+        visited.sort();
+        let expected = entities.iter().flat_map(|e| [*e, *e]).collect::<Vec<_>>();
+        assert_eq!(visited, expected);
+        for entity in entities {
+            assert_eq!(world.get_component::<Counter>(entity).unwrap().0, 2);
+        }
+    }
+
+    #[test]
+    fn for_each_mut_visits_all_archetypes() {
+        struct Counter(u32);
+        struct Tag;
+        struct Other;
+
+        let mut world = World::new();
+        let mut entities = (0..30)
+            .map(|i| {
+                let entity = world.new_entity();
+                world.add_component(entity, Counter(0));
+                if i % 2 == 0 {
+                    world.add_component(entity, Tag);
+                }
+                if i % 3 == 0 {
+                    world.add_component(entity, Other);
+                }
+                entity
+            })
+            .collect::<Vec<_>>();
+        // Entities moving to another archetype must be visited only once
+        world.remove_component::<Tag>(entities[0]);
+        world.remove_component::<Counter>(entities[1]);
+        let without_counter = entities.remove(1);
+
+        world.add_system(0, |mut query: Query<&mut Counter>| {
+            query.for_each_mut(|counter| counter.0 += 1);
+        });
+        let visited = Arc::<Mutex<Vec<Entity>>>::default();
+        let visited_2 = visited.clone();
+        world.add_system(0, move |query: Query<(Entity, &Counter)>| {
+            let mut visited = visited_2.lock().unwrap();
+            query.for_each(|(entity, _)| visited.push(entity));
+            let mut iterated = query.iter().map(|(e, _)| e).collect::<Vec<_>>();
+            visited.sort();
+            iterated.sort();
+            assert_eq!(*visited, iterated);
+        });
+        world.update(0);
+
+        entities.sort();
+        assert_eq!(*visited.lock().unwrap(), entities);
+        for entity in entities {
+            assert_eq!(world.get_component::<Counter>(entity).unwrap().0, 1);
+        }
+        assert!(world.get_component::<Counter>(without_counter).is_none());
+    }
+
+    #[test]
+    fn query_matches_entities_through_archetypes() {
+        struct A;
+        struct B;
+
+        let mut world = World::new();
+        let iterated = Arc::<Mutex<Vec<Entity>>>::default();
+        let iterated_2 = iterated.clone();
+        world.add_system(0, move |query: Query<(Entity, &A)>| {
+            let iter = query.iter();
+            let len = iter.len();
+            let entities = iter.map(|(e, _)| e).collect::<Vec<_>>();
+            assert_eq!(len, entities.len());
+            *iterated_2.lock().unwrap() = entities;
+        });
+
+        // The archetypes are created after the system was added
+        let only_a = world.new_entity();
+        world.add_component(only_a, A);
+        let a_and_b = world.new_entity();
+        world.add_component(a_and_b, A);
+        world.add_component(a_and_b, B);
+        let only_b = world.new_entity();
+        world.add_component(only_b, B);
+        world.update(0);
+        assert_eq!(*iterated.lock().unwrap(), vec![only_a, a_and_b]);
+
+        // Moving to an archetype that is not matched, and destroying an entity
+        world.remove_component::<A>(a_and_b);
+        world.add_component(only_b, A);
+        world.destroy_entity(only_a);
+        world.update(0);
+        assert_eq!(*iterated.lock().unwrap(), vec![only_b]);
+    }
+}
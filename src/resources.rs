@@ -1,12 +1,15 @@
 use std::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
     thread::ThreadId,
 };
 
 use crate::{
+    change_detection::SystemTicks,
     commands::TypedBlob,
     erased_data_vec::{ErasedVec, UnsafeMutPtr, UnsafePtr},
+    query::AccessMode,
     sparse_set::SparseSet,
     ComponentId, WorldContainer,
 };
@@ -20,6 +23,9 @@ pub trait Resource: Send + Sync + 'static {}
 pub struct ResourceData<const SEND: bool> {
     data_storage: ErasedVec,
     type_name: String,
+    changed_tick: AtomicU64,
+    #[cfg(debug_assertions)]
+    borrow: ResourceBorrow,
 
     // None for SEND resources
     original_creator: Option<ThreadId>,
@@ -34,10 +40,13 @@ where
     pub(crate) _ph: PhantomData<&'res T>,
     pub(crate) _ph_world: PhantomData<&'world WorldContainer>,
     pub(crate) ptr: UnsafePtr<'res, T>,
+    #[cfg(debug_assertions)]
+    pub(crate) _borrow: BorrowGuard<'res>,
 }
 
 /// Provides mutable access to a resource stored in the [`crate::WorldContainer`]
-/// To access a non-send resource mutably, use `&mut WorldContainer`
+/// To access a non-send resource mutably, use `&mut WorldContainer`.
+/// The resource is marked as changed only when it is mutably dereferenced
 pub struct ResMut<'world, 'res, T: 'static>
 where
     'world: 'res,
@@ -45,15 +54,187 @@ where
     pub(crate) _ph: PhantomData<&'res T>,
     pub(crate) _ph_world: PhantomData<&'world WorldContainer>,
     pub(crate) ptr: UnsafeMutPtr<'res, T>,
+    pub(crate) changed_tick: &'res AtomicU64,
+    pub(crate) ticks: SystemTicks,
+    #[cfg(debug_assertions)]
+    pub(crate) _borrow: BorrowGuard<'res>,
+}
+
+/// Provides non-mutable access to a group of resources stored in the [`crate::WorldContainer`] with a single
+/// system parameter, e.g `Resources<(Time, Input)>`: the resources are accessed as a tuple of references.
+/// Like [`Res`], only Send resources can be accessed, and `Option<Resources<..>>` can be used
+/// when some resource of the group might not exist
+/// e.g
+/// ```
+/// use kecs::{Resource, Resources};
+/// struct Time(f32);
+/// impl Resource for Time {}
+/// struct Gravity(f32);
+/// impl Resource for Gravity {}
+/// fn print_fall_speed(resources: Resources<(Time, Gravity)>) {
+///     let (time, gravity) = *resources;
+///     println!("Fall speed: {}", time.0 * gravity.0);
+/// }
+/// ```
+pub struct Resources<'res, T: ResourceGroup> {
+    refs: T::Refs<'res>,
+    #[cfg(debug_assertions)]
+    _borrows: Vec<BorrowGuard<'res>>,
+}
+
+/// Implemented by the tuples of up to 4 [`Resource`]s, which can be accessed together using [`Resources`]
+pub trait ResourceGroup: 'static {
+    /// The tuple of references to the resources
+    type Refs<'res>: Copy;
+
+    /// Returns true if every resource in the group exists
+    fn contains(store: &WorldContainer) -> bool;
+
+    /// Adds a read dependency for each resource in the group
+    fn add_dependencies(
+        store: &mut WorldContainer,
+        components: &mut SparseSet<ComponentId, AccessMode>,
+    );
+
+    /// # Safety
+    ///   The caller must ensure that no resource in the group is mutably accessed while the references are alive
+    unsafe fn extract<'res>(store: &WorldContainer) -> Self::Refs<'res>;
+}
+
+impl<'res, T: ResourceGroup> Resources<'res, T> {
+    /// Consumes the [`Resources`], returning the tuple of references
+    pub fn into_inner(self) -> T::Refs<'res> {
+        self.refs
+    }
+
+    /// # Safety
+    ///   The caller must ensure that no resource in the group is mutably accessed while self is alive
+    #[cfg_attr(not(debug_assertions), allow(unused_variables))]
+    pub(crate) unsafe fn extract(store: &'res WorldContainer, ids: &[ComponentId]) -> Self {
+        Self {
+            refs: T::extract(store),
+            #[cfg(debug_assertions)]
+            _borrows: ids
+                .iter()
+                .filter_map(|id| store.send_resources.borrow(*id))
+                .collect(),
+        }
+    }
+}
+
+impl<'res, T: ResourceGroup> Deref for Resources<'res, T> {
+    type Target = T::Refs<'res>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.refs
+    }
+}
+
+macro_rules! impl_resource_group {
+    ($($t:ident)*) => {
+        impl<$($t,)*> ResourceGroup for ($($t,)*)
+        where
+            $($t: Resource,)*
+        {
+            type Refs<'res> = ($(&'res $t,)*);
+
+            fn contains(store: &WorldContainer) -> bool {
+                $(store.contains_send_resource::<$t>())&&*
+            }
+
+            fn add_dependencies(
+                store: &mut WorldContainer,
+                components: &mut SparseSet<ComponentId, AccessMode>,
+            ) {
+                $(
+                    let id = store.get_or_create_component_id::<$t>();
+                    components.insert(id, AccessMode::Read);
+                )*
+            }
+
+            unsafe fn extract<'res>(store: &WorldContainer) -> Self::Refs<'res> {
+                ($({
+                    let ptr = store
+                        .get_component_id::<$t>()
+                        .and_then(|id| store.send_resources.get_unsafe_ref::<$t>(id))
+                        .unwrap_or_else(|| panic!("Resource {} not found", std::any::type_name::<$t>()));
+                    std::mem::transmute::<&$t, &'res $t>(ptr.get())
+                },)*)
+            }
+        }
+    };
+}
+
+impl_resource_group!(A);
+impl_resource_group!(A B);
+impl_resource_group!(A B C);
+impl_resource_group!(A B C D);
+
+// The number of live Res of a resource, or -1 while a ResMut is alive. The borrows are tracked only in debug builds,
+// to catch overlapping accesses that the schedulers can't see (e.g two labels updated concurrently from different threads)
+#[cfg(debug_assertions)]
+#[derive(Default)]
+pub(crate) struct ResourceBorrow(std::sync::atomic::AtomicIsize);
+
+// Releases the borrow of a resource when dropped
+#[cfg(debug_assertions)]
+pub(crate) struct BorrowGuard<'res> {
+    borrow: &'res ResourceBorrow,
+    mutable: bool,
+}
+
+#[cfg(debug_assertions)]
+impl ResourceBorrow {
+    pub(crate) fn borrow(&self, type_name: &str) -> BorrowGuard<'_> {
+        let borrowed = self
+            .0
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |state| {
+                (state >= 0).then_some(state + 1)
+            });
+        assert!(
+            borrowed.is_ok(),
+            "Tried to read resource '{type_name}' while it's being written"
+        );
+        BorrowGuard {
+            borrow: self,
+            mutable: false,
+        }
+    }
+
+    pub(crate) fn borrow_mut(&self, type_name: &str) -> BorrowGuard<'_> {
+        let borrowed = self
+            .0
+            .compare_exchange(0, -1, Ordering::Acquire, Ordering::Relaxed);
+        assert!(
+            borrowed.is_ok(),
+            "Tried to write resource '{type_name}' while it's being accessed"
+        );
+        BorrowGuard {
+            borrow: self,
+            mutable: true,
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for BorrowGuard<'_> {
+    fn drop(&mut self) {
+        if self.mutable {
+            self.borrow.0.store(0, Ordering::Release);
+        } else {
+            self.borrow.0.fetch_sub(1, Ordering::Release);
+        }
+    }
 }
 
 impl<const SEND: bool> ResourceData<SEND> {
-    fn new<R: 'static>(resource: R) -> Self {
-        let mut vec = unsafe { ErasedVec::new_typed::<R>(true, 1) };
-        unsafe { vec.push_back(resource) };
+    fn new<R: 'static>(resource: R, tick: u64) -> Self {
         Self {
-            data_storage: vec,
+            data_storage: unsafe { ErasedVec::from_iter_typed(std::iter::once(resource)) },
             type_name: std::any::type_name::<R>().to_string(),
+            changed_tick: AtomicU64::new(tick),
+            #[cfg(debug_assertions)]
+            borrow: Default::default(),
             original_creator: if SEND {
                 None
             } else {
@@ -61,11 +242,14 @@ impl<const SEND: bool> ResourceData<SEND> {
             },
         }
     }
-    fn new_from_existing(storage: ErasedVec, type_name: &'static str) -> Self {
+    fn new_from_existing(storage: ErasedVec, type_name: &'static str, tick: u64) -> Self {
         assert!(storage.len() == 1);
         Self {
             data_storage: storage,
             type_name: type_name.to_string(),
+            changed_tick: AtomicU64::new(tick),
+            #[cfg(debug_assertions)]
+            borrow: Default::default(),
             original_creator: if SEND {
                 None
             } else {
@@ -85,46 +269,71 @@ impl<const SEND: bool> ResourceData<SEND> {
 }
 
 #[derive(Default)]
-pub(crate) struct Resources<const SEND: bool> {
+pub(crate) struct ResourceStorage<const SEND: bool> {
     pub(crate) resources: SparseSet<ComponentId, ResourceData<SEND>>,
 }
 
-impl<const SEND: bool> Resources<SEND> {
+impl<const SEND: bool> ResourceStorage<SEND> {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn add<R: 'static>(&mut self, id: ComponentId, resource: R) {
+    pub fn add<R: 'static>(&mut self, id: ComponentId, resource: R, tick: u64) {
         if let Some(old_resource) = self.resources.get_mut(id) {
             old_resource.validate_access();
             // SAFETY: The resource is present in the SparseSet
             // We also know that the type is correct because of the id
             unsafe { old_resource.data_storage.drop_at(0) };
             unsafe { old_resource.data_storage.insert_at(0, resource) };
+            *old_resource.changed_tick.get_mut() = tick;
         } else {
-            let container = ResourceData::<SEND>::new(resource);
+            let container = ResourceData::<SEND>::new(resource, tick);
             self.resources.insert(id, container);
         }
     }
 
     /// # Safety
     /// The caller must ensure that id's type id corresponds to the type id of the resource
-    pub unsafe fn add_dynamic(&mut self, id: ComponentId, resource: TypedBlob) {
+    pub unsafe fn add_dynamic(&mut self, id: ComponentId, resource: TypedBlob, tick: u64) {
+        let type_name = resource.type_name.expect("No type name");
         if let Some(old_resource) = self.resources.get_mut(id) {
             old_resource.validate_access();
+            let data = resource.into_data();
             // SAFETY: The resource is present in the SparseSet
             // We also know that the type is correct because of the id
             unsafe { old_resource.data_storage.drop_at(0) };
-            old_resource.data_storage.copy_from(0, &resource.data, 0);
+            old_resource.data_storage.copy_from(0, &data, 0);
+            *old_resource.changed_tick.get_mut() = tick;
         } else {
-            let container = ResourceData::<SEND>::new_from_existing(
-                resource.data,
-                resource.type_name.expect("No type name"),
-            );
+            let container =
+                ResourceData::<SEND>::new_from_existing(resource.into_data(), type_name, tick);
             self.resources.insert(id, container);
         }
     }
 
+    /// Removes the resource, returning it if it existed
+    /// # Safety
+    /// The caller must ensure that R is the type of the resource identified by id
+    pub unsafe fn take<R: 'static>(&mut self, id: ComponentId) -> Option<R> {
+        let resource = self.resources.get_mut(id)?;
+        resource.validate_access();
+        // SAFETY: The resource is present, and it's removed from the SparseSet right after being moved out
+        let value = unsafe { resource.data_storage.remove::<R>(0) };
+        self.resources.remove(id);
+        Some(value)
+    }
+
+    /// Removes and drops the resource, returning true if it existed
+    pub fn remove(&mut self, id: ComponentId) -> bool {
+        let Some(resource) = self.resources.get(&id) else {
+            return false;
+        };
+        resource.validate_access();
+        // SAFETY: The resource is present, and it's removed from the SparseSet right after being dropped
+        unsafe { resource.data_storage.drop_at(0) };
+        self.resources.remove(id)
+    }
+
     // # Safety
     // The caller will ensure that, when accessing the pointer, no other mutable access is being performed
     pub unsafe fn get_ptr<R: 'static>(&self, id: ComponentId) -> Option<UnsafePtr<'_, R>> {
@@ -143,6 +352,27 @@ impl<const SEND: bool> Resources<SEND> {
         })
     }
 
+    pub(crate) fn mark_changed(&self, id: ComponentId, tick: u64) {
+        if let Some(changed_tick) = self.get_changed_tick(id) {
+            changed_tick.store(tick, Ordering::Relaxed);
+        }
+    }
+
+    /// Gets the tick at which the resource was last changed
+    pub(crate) fn get_changed_tick(&self, id: ComponentId) -> Option<&AtomicU64> {
+        self.resources
+            .get(&id)
+            .map(|resource| &resource.changed_tick)
+    }
+
+    // Tracks the non-mutable accesses done through Res and Resources
+    #[cfg(debug_assertions)]
+    pub(crate) fn borrow(&self, id: ComponentId) -> Option<BorrowGuard<'_>> {
+        self.resources
+            .get(&id)
+            .map(|resource| resource.borrow.borrow(&resource.type_name))
+    }
+
     // # Safety
     // The caller will ensure that, when accessing the pointer, no other mutable access is being performed
     pub(crate) unsafe fn get_unsafe_ref<R: 'static>(
@@ -153,19 +383,52 @@ impl<const SEND: bool> Resources<SEND> {
     }
 
     // # Safety
-    // The caller will ensure that, when accessing the pointer, no other access is being performed
-    pub(crate) unsafe fn get_unsafe_mut_ref<R: 'static>(
+    // The caller will ensure that, while the ResMut is alive, no other access is being performed
+    pub(crate) unsafe fn get_res_mut<R: 'static>(
         &self,
         id: ComponentId,
-    ) -> Option<UnsafeMutPtr<'_, R>> {
-        self.get_mut_ptr(id)
+        ticks: SystemTicks,
+    ) -> Option<ResMut<'_, '_, R>> {
+        let resource = self.resources.get(&id)?;
+        Some(ResMut {
+            _ph: PhantomData,
+            _ph_world: PhantomData,
+            ptr: self.get_mut_ptr(id)?,
+            changed_tick: &resource.changed_tick,
+            ticks,
+            #[cfg(debug_assertions)]
+            _borrow: resource.borrow.borrow_mut(&resource.type_name),
+        })
     }
 }
 
-impl<const SEND: bool> Drop for Resources<SEND> {
+impl<const SEND: bool> ResourceData<SEND> {
+    fn can_drop_on_current_thread(&self) -> bool {
+        SEND || self
+            .original_creator
+            .is_none_or(|id| id == std::thread::current().id())
+    }
+}
+
+impl<const SEND: bool> ResourceStorage<SEND> {
+    /// Iterates the names of the resources that will be leaked if the storage is dropped on the current thread
+    pub(crate) fn iter_leaked_on_drop(&self) -> impl Iterator<Item = &str> {
+        self.resources
+            .iter()
+            .filter(|(_, res)| !res.can_drop_on_current_thread())
+            .map(|(_, res)| res.type_name.as_str())
+    }
+}
+
+impl<const SEND: bool> Drop for ResourceStorage<SEND> {
     fn drop(&mut self) {
-        for res in self.resources.iter() {
-            unsafe { res.1.data_storage.drop_at(0) };
+        for (_, res) in self.resources.iter() {
+            // A non-send resource might not be safe to drop on a thread that does not own it:
+            // since panicking during drop would abort the program, the resource is leaked instead (and the WorldContainer
+            // reports it through its warning handler)
+            if res.can_drop_on_current_thread() {
+                unsafe { res.data_storage.drop_at(0) };
+            }
         }
     }
 }
@@ -187,8 +450,27 @@ impl<'world, 'res, T: Resource> Deref for ResMut<'world, 'res, T> {
     }
 }
 
+impl<'world, 'res, T: Resource> ResMut<'world, 'res, T> {
+    /// Returns `true` if the resource changed since the last time the system ran
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.changed_tick())
+    }
+
+    /// Returns the tick at which the resource was last changed
+    pub fn changed_tick(&self) -> u64 {
+        self.changed_tick.load(Ordering::Relaxed)
+    }
+
+    /// Marks the resource as changed without mutably dereferencing it
+    pub fn set_changed(&mut self) {
+        self.changed_tick
+            .store(self.ticks.this_run, Ordering::Relaxed);
+    }
+}
+
 impl<'world, 'res, T: Resource> DerefMut for ResMut<'world, 'res, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.set_changed();
         // SAFETY: The caller must ensure that no mutable references are existing for the referred resource.
         unsafe { self.ptr.get_mut() }
     }
@@ -199,3 +481,295 @@ unsafe impl<'world, 'res, T: Resource> Send for Res<'world, 'res, T> {}
 unsafe impl<'world, 'res, T: Resource> Send for ResMut<'world, 'res, T> {}
 unsafe impl<'world, 'res, T: Resource> Sync for Res<'world, 'res, T> {}
 unsafe impl<'world, 'res, T: Resource> Sync for ResMut<'world, 'res, T> {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use crate::{InvalidSystemError, Res, ResMut, Resource, Resources, World};
+
+    struct NonSendResource {
+        drops: Arc<AtomicUsize>,
+    }
+    impl Resource for NonSendResource {}
+
+    impl Drop for NonSendResource {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn non_send_resource_dropped_on_owner_thread() {
+        let drops = Arc::<AtomicUsize>::default();
+        let mut world = World::new();
+        world.add_non_send_resource(NonSendResource {
+            drops: drops.clone(),
+        });
+        drop(world);
+
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn non_send_resource_leaked_on_foreign_thread() {
+        let drops = Arc::<AtomicUsize>::default();
+        let mut world = World::new();
+        world.add_non_send_resource(NonSendResource {
+            drops: drops.clone(),
+        });
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let sink = warnings.clone();
+        world.set_warning_handler(move |message| sink.lock().unwrap().push(message.to_owned()));
+        std::thread::spawn(move || drop(world)).join().unwrap();
+
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        let warnings = warnings.lock().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("NonSendResource"));
+
+        // No warning is reported when the resource is dropped on its thread
+        let mut world = World::new();
+        world.add_non_send_resource(NonSendResource {
+            drops: drops.clone(),
+        });
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let sink = warnings.clone();
+        world.set_warning_handler(move |message| sink.lock().unwrap().push(message.to_owned()));
+        drop(world);
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        assert!(warnings.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn grouped_resources() {
+        struct Time(f32);
+        impl Resource for Time {}
+        struct Gravity(f32);
+        impl Resource for Gravity {}
+        struct Scale(f32);
+        impl Resource for Scale {}
+
+        let mut world = World::new();
+        world.add_resource(Time(2.0));
+        world.add_resource(Gravity(9.8));
+        world.add_resource(Scale(0.5));
+
+        let fall = world.run_oneshot_with(|resources: Resources<(Time, Gravity, Scale)>| {
+            let (time, gravity, scale) = *resources;
+            time.0 * gravity.0 * scale.0
+        });
+        assert_eq!(fall, 2.0 * 9.8 * 0.5);
+    }
+
+    #[test]
+    fn optional_grouped_resources() {
+        struct Time(f32);
+        impl Resource for Time {}
+        struct Gravity(f32);
+        impl Resource for Gravity {}
+
+        let mut world = World::new();
+        world.add_resource(Time(2.0));
+        let fall = |resources: Option<Resources<(Time, Gravity)>>| {
+            resources.map(|resources| {
+                let (time, gravity) = *resources;
+                time.0 * gravity.0
+            })
+        };
+
+        // The group is None while any of its resources is missing
+        assert_eq!(world.run_oneshot_with(fall), None);
+        world.add_resource(Gravity(9.8));
+        assert_eq!(world.run_oneshot_with(fall), Some(2.0 * 9.8));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Resource kecs::resources::tests::missing_grouped_resource::Gravity not found"
+    )]
+    fn missing_grouped_resource() {
+        struct Time;
+        impl Resource for Time {}
+        struct Gravity;
+        impl Resource for Gravity {}
+
+        let mut world = World::new();
+        world.add_resource(Time);
+        world.run_oneshot(|_: Resources<(Time, Gravity)>| {});
+    }
+
+    #[test]
+    fn optional_resources() {
+        struct Score(u32);
+        impl Resource for Score {}
+
+        let mut world = World::new();
+        let seen = Arc::new(AtomicUsize::new(0));
+        let seen_2 = seen.clone();
+        world.add_system(0, move |score: Option<Res<Score>>| {
+            seen_2.store(score.map_or(0, |score| score.0 as usize), Ordering::Relaxed);
+        });
+        world.add_system(0, |score: Option<ResMut<Score>>| {
+            if let Some(mut score) = score {
+                score.0 += 1;
+            }
+        });
+
+        // No system panics when the resource is missing
+        world.update(0);
+        assert_eq!(seen.load(Ordering::Relaxed), 0);
+        assert!(world.get_resource::<Score>().is_none());
+
+        world.add_resource(Score(10));
+        world.update(0);
+        world.update(0);
+        assert_eq!(seen.load(Ordering::Relaxed), 11);
+        assert_eq!(world.get_resource::<Score>().unwrap().0, 12);
+    }
+
+    #[test]
+    fn overlapping_resource_borrow() {
+        struct Counter(u32);
+        impl Resource for Counter {}
+
+        let mut world = World::new();
+        world.add_resource(Counter(0));
+        let result = world.try_add_system(
+            0,
+            |counter: Res<Counter>, mut counter_mut: ResMut<Counter>| {
+                counter_mut.0 = counter.0 + 1;
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(InvalidSystemError::ConflictingAccess { component, .. }) if component.ends_with("Counter")
+        ));
+        assert_eq!(world.system_count(0), 0);
+
+        // Reading a resource from more than one parameter is fine
+        world.add_system(0, |_: Res<Counter>, _: Option<Res<Counter>>| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "more than once")]
+    fn overlapping_resource_group_borrow() {
+        struct Counter(u32);
+        impl Resource for Counter {}
+        struct Step(u32);
+        impl Resource for Step {}
+
+        let mut world = World::new();
+        world.add_resource(Counter(0));
+        world.add_resource(Step(1));
+        // The conflict is also detected when the system isn't scheduled
+        world.run_oneshot(
+            |resources: Resources<(Step, Counter)>, mut counter: ResMut<Counter>| {
+                let (step, _) = *resources;
+                counter.0 += step.0;
+            },
+        );
+    }
+
+    #[test]
+    fn register_resource() {
+        struct Counter(u32);
+        impl Resource for Counter {}
+
+        let mut world = World::new();
+        let id = world.register_resource::<Counter>();
+        assert_eq!(world.get_type_registration::<Counter>(), id);
+
+        // The resource is needed only when the system runs
+        world.add_system(0, |mut counter: ResMut<Counter>| counter.0 += 1);
+        world.add_resource(Counter(0));
+        world.update(0);
+        assert_eq!(world.get_resource::<Counter>().unwrap().0, 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Resource kecs::resources::tests::missing_resource::Counter not found"
+    )]
+    fn missing_resource() {
+        struct Counter;
+        impl Resource for Counter {}
+
+        let mut world = World::new();
+        world.add_system(0, |_: Res<Counter>| {});
+        world.update(0);
+    }
+
+    #[test]
+    fn sequential_resource_borrows() {
+        struct Counter(u32);
+        impl Resource for Counter {}
+
+        let mut world = World::new();
+        world.add_resource(Counter(0));
+        world.add_system("a", |mut counter: ResMut<Counter>| counter.0 += 1);
+        world.add_system("b", |mut counter: ResMut<Counter>| counter.0 += 1);
+        world.add_system("b", |_: Res<Counter>| {});
+        world.add_system("b", |_: Res<Counter>| {});
+
+        // Each borrow is released when the system returns
+        for _ in 0..3 {
+            world.update("a");
+            world.update("b");
+        }
+        assert_eq!(world.get_resource::<Counter>().unwrap().0, 6);
+    }
+
+    #[test]
+    fn res_mut_is_changed() {
+        struct Config(u32);
+        impl Resource for Config {}
+
+        let mut world = World::new();
+        world.add_resource(Config(0));
+
+        let changed = Arc::new(AtomicBool::new(false));
+        let changed_2 = changed.clone();
+        world.add_system("observe", move |config: ResMut<Config>| {
+            changed_2.store(config.is_changed(), Ordering::Relaxed);
+        });
+        world.add_system("write", |mut config: ResMut<Config>| {
+            config.0 += 1;
+        });
+        world.add_system("touch", |mut config: ResMut<Config>| {
+            config.set_changed();
+        });
+
+        // The resource was added before the first run
+        world.update("observe");
+        assert!(changed.load(Ordering::Relaxed));
+        world.update("observe");
+        assert!(!changed.load(Ordering::Relaxed));
+
+        world.update("write");
+        world.update("observe");
+        assert!(changed.load(Ordering::Relaxed));
+        assert_eq!(world.get_resource::<Config>().unwrap().0, 1);
+
+        world.update("touch");
+        world.update("observe");
+        assert!(changed.load(Ordering::Relaxed));
+
+        world.get_resource_mut::<Config>().unwrap().0 = 5;
+        world.update("observe");
+        assert!(changed.load(Ordering::Relaxed));
+        world.update("observe");
+        assert!(!changed.load(Ordering::Relaxed));
+
+        // Reading through get_resource_mut_tracked doesn't mark the resource as changed
+        assert_eq!(world.get_resource_mut_tracked::<Config>().unwrap().0, 5);
+        world.update("observe");
+        assert!(!changed.load(Ordering::Relaxed));
+        world.get_resource_mut_tracked::<Config>().unwrap().0 = 6;
+        world.update("observe");
+        assert!(changed.load(Ordering::Relaxed));
+    }
+}
@@ -1,19 +1,23 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::vec;
 
+use petgraph::algo::{is_cyclic_directed, tarjan_scc};
 use petgraph::dot::Dot;
 use petgraph::graph::NodeIndex;
 use petgraph::visit::EdgeRef;
 use petgraph::{Directed, Graph};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::ThreadPool;
 
 use crate::query::AccessMode;
 use crate::sparse_set::SparseSet;
 use crate::system::{IntoSystem, System};
-use crate::{ComponentId, Entity, WorldContainer};
+use crate::{ComponentId, WorldContainer};
 
 /// # Safety
 ///   The implementer must ensure that:
@@ -34,18 +38,55 @@ pub unsafe trait Scheduler: Default {
         system: S,
     ) -> Self::SystemId;
 
+    /// Implement this function to remove a system from the Scheduler, returning true if the system existed.
+    /// The ids of the other systems must stay valid after the removal
+    fn remove_system(&mut self, id: Self::SystemId) -> bool;
+
+    /// Implement this function to return true if the system was added to the Scheduler and was not removed
+    fn contains_system(&self, id: Self::SystemId) -> bool;
+
     /// Implement this function to run the scheduler systems
     fn execute(&mut self, world: &mut WorldContainer);
 
-    /// This method will be called when a new entity changes somehow (e.g an entity is created,
-    /// a component is added/removed etc...)
-    fn on_entity_updated(&mut self, world: &mut WorldContainer, entity: Entity);
+    /// Like [`Scheduler::execute`], but the scheduler can call `flush` between its systems to apply the queued
+    /// [`crate::Commands`], see [`LinearScheduler::set_flush_between_systems`]. During `flush`, `self` may be
+    /// temporarily replaced and must not be accessed. The default implementation never calls `flush`
+    fn execute_with_flush<W: AsMut<WorldContainer>>(
+        &mut self,
+        world: &mut W,
+        flush: &mut dyn FnMut(&mut Self, &mut W),
+    ) {
+        let _ = flush;
+        self.execute(world.as_mut());
+    }
+
+    /// Implement this function to run a single system on the current thread, ignoring its dependencies,
+    /// returning true if the system existed
+    fn run_system(&mut self, world: &mut WorldContainer, id: Self::SystemId) -> bool;
+
+    /// Implement this function to return the number of systems added to the Scheduler
+    fn len(&self) -> usize;
+
+    /// Returns true if no system was added to the Scheduler
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Implement this function to return the peak number of systems that can be run at the same time
+    fn max_parallelism(&self) -> usize;
 }
 
-/// This scheduler runs all the systems on the same thread sequentially
+/// This scheduler runs all the systems on the same thread sequentially, in the order they were added
+/// unless an ordering is forced with [`LinearScheduler::add_ordering`]
 #[derive(Default)]
 pub struct LinearScheduler {
-    systems: Vec<Box<dyn System>>,
+    // Removed systems leave a hole, so that the ids of the other systems stay valid
+    systems: Vec<Option<Box<dyn System>>>,
+    // The (before, after) pairs of systems added through add_ordering
+    orderings: Vec<(usize, usize)>,
+    // Recomputed only when the systems or the orderings change
+    cached_order: Option<Vec<usize>>,
+    flush_between_systems: bool,
 }
 
 /// The [`GraphScheduler`] will put the systems into a graph where the nodes are the systems and
@@ -54,10 +95,29 @@ pub struct GraphScheduler {
     current_dependencies: SparseSet<ComponentId, GraphResourceOwnership>,
     graph: Graph<SystemGraphNode, SystemGraphEdge, Directed>,
     root_node_idx: NodeIndex,
+    last_exclusive: Option<NodeIndex>,
+    // The systems reading the whole world placed since the last exclusive system
+    world_readers: HashSet<NodeIndex>,
+    system_nodes: BTreeMap<GraphSystemId, NodeIndex>,
+    // The (before, after) pairs of systems added through add_ordering, added again to the graph by compact
+    orderings: Vec<(GraphSystemId, GraphSystemId)>,
+    next_system_id: usize,
+    dead_nodes: usize,
     changed_schedule: bool,
     cached_schedule: Schedules,
+    // When None, the systems are run on rayon's global thread pool
+    thread_pool: Option<Arc<ThreadPool>>,
+    record_timings: bool,
 }
 
+/// Identifies a system added to a [`GraphScheduler`]: unlike the nodes of the graph, the id
+/// stays valid when other systems are removed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GraphSystemId(usize);
+
+/// When the removed systems exceed this fraction of the graph nodes, the graph is compacted
+const MAX_DEAD_NODE_RATIO: f32 = 0.5;
+
 impl Default for GraphScheduler {
     fn default() -> Self {
         Self::new()
@@ -82,26 +142,151 @@ unsafe impl Scheduler for LinearScheduler {
         let mut system = system.into_system();
         system.init(world);
 
-        self.systems.push(Box::new(system));
+        self.systems.push(Some(Box::new(system)));
+        self.cached_order = None;
         id
     }
 
+    fn remove_system(&mut self, id: Self::SystemId) -> bool {
+        self.cached_order = None;
+        self.systems.get_mut(id).and_then(Option::take).is_some()
+    }
+
+    fn contains_system(&self, id: Self::SystemId) -> bool {
+        self.systems.get(id).is_some_and(Option::is_some)
+    }
+
     fn execute(&mut self, world: &mut WorldContainer) {
-        for system in self.systems.iter_mut() {
-            system.run(world);
+        let order = self.take_order();
+        for id in &order {
+            if let Some(system) = self.systems[*id].as_mut() {
+                system.run(world);
+                world.commands.finish_batch();
+            }
         }
+        self.cached_order = Some(order);
     }
 
-    fn on_entity_updated(&mut self, world: &mut WorldContainer, entity: Entity) {
-        if let Some(info) = world.get_entity_info(entity) {
-            self.systems
-                .iter_mut()
-                .for_each(|s| s.on_entity_changed(world, entity, info))
-        } else {
-            self.systems
-                .iter_mut()
-                .for_each(|s| s.on_entity_destroyed(world, entity))
+    fn execute_with_flush<W: AsMut<WorldContainer>>(
+        &mut self,
+        world: &mut W,
+        flush: &mut dyn FnMut(&mut Self, &mut W),
+    ) {
+        if !self.flush_between_systems {
+            return self.execute(world.as_mut());
+        }
+        // The order is kept outside of self, since flush may temporarily replace the scheduler
+        let order = self.take_order();
+        for (i, id) in order.iter().enumerate() {
+            if let Some(system) = self.systems[*id].as_mut() {
+                system.run(world.as_mut());
+                world.as_mut().commands.finish_batch();
+            }
+            // Like with execute, the commands queued by the last system are flushed by the next update
+            if i + 1 < order.len() {
+                flush(self, world);
+            }
         }
+        self.cached_order = Some(order);
+    }
+
+    fn run_system(&mut self, world: &mut WorldContainer, id: Self::SystemId) -> bool {
+        let Some(system) = self.systems.get_mut(id).and_then(Option::as_mut) else {
+            return false;
+        };
+        system.run(world);
+        world.commands.finish_batch();
+        true
+    }
+
+    fn len(&self) -> usize {
+        self.systems.iter().flatten().count()
+    }
+
+    fn max_parallelism(&self) -> usize {
+        1
+    }
+}
+
+impl LinearScheduler {
+    /// Forces the system `before` to run before the system `after`, regardless of the order in which they were added.
+    /// Returns false if any of the two systems doesn't exist, or an error if `after` already has to run before `before`:
+    /// in that case the ordering is not added
+    pub fn add_ordering(
+        &mut self,
+        before: usize,
+        after: usize,
+    ) -> Result<bool, ScheduleCycleError> {
+        let exists = |id: usize| self.systems.get(id).is_some_and(Option::is_some);
+        if !exists(before) || !exists(after) {
+            return Ok(false);
+        }
+        self.orderings.push((before, after));
+        if let Err(error) = self.compute_order() {
+            self.orderings.pop();
+            return Err(error);
+        }
+        self.cached_order = None;
+        Ok(true)
+    }
+
+    /// When enabled, the [`crate::Commands`] queued by each system are applied before the next system runs,
+    /// e.g so that the entities spawned by a setup system are visible to the next systems in the same
+    /// [`crate::KecsWorld::update`]. Disabled by default
+    pub fn set_flush_between_systems(&mut self, flush_between_systems: bool) {
+        self.flush_between_systems = flush_between_systems;
+    }
+
+    /// Returns true if the commands are applied between the systems, see [`LinearScheduler::set_flush_between_systems`]
+    pub fn flush_between_systems(&self) -> bool {
+        self.flush_between_systems
+    }
+
+    fn take_order(&mut self) -> Vec<usize> {
+        match self.cached_order.take() {
+            Some(order) => order,
+            None => self
+                .compute_order()
+                .unwrap_or_else(|error| panic!("{error}")),
+        }
+    }
+
+    /// Computes the order in which the systems are run: the systems not constrained by [`LinearScheduler::add_ordering`]
+    /// run in the order they were added
+    pub fn compute_order(&self) -> Result<Vec<usize>, ScheduleCycleError> {
+        let live = |id: usize| self.systems[id].is_some();
+        let mut successors = vec![vec![]; self.systems.len()];
+        let mut predecessors = vec![0usize; self.systems.len()];
+        for (before, after) in self.orderings.iter().copied() {
+            if live(before) && live(after) {
+                successors[before].push(after);
+                predecessors[after] += 1;
+            }
+        }
+
+        // Always pick the first system added among the ones that can run
+        let mut ready = (0..self.systems.len())
+            .filter(|id| live(*id) && predecessors[*id] == 0)
+            .collect::<BTreeSet<_>>();
+        let mut order = vec![];
+        while let Some(id) = ready.pop_first() {
+            order.push(id);
+            for successor in &successors[id] {
+                predecessors[*successor] -= 1;
+                if predecessors[*successor] == 0 {
+                    ready.insert(*successor);
+                }
+            }
+        }
+
+        if order.len() < self.len() {
+            let systems = (0..self.systems.len())
+                .filter(|id| live(*id) && predecessors[*id] > 0)
+                .map(|id| self.systems[id].as_ref().unwrap().get_name())
+                .collect();
+            return Err(ScheduleCycleError { systems });
+        }
+        Ok(order)
     }
 }
 
@@ -109,21 +294,25 @@ unsafe impl Scheduler for LinearScheduler {
 /// The scheduler will run systems in parallel only when their dependencies (or access modes) don't overlap
 /// Otherwise, the systems will be run sequentially
 unsafe impl Scheduler for GraphScheduler {
-    type SystemId = NodeIndex;
+    type SystemId = GraphSystemId;
 
     fn new() -> Self {
         let mut graph = Graph::default();
-        let root_node = SystemGraphNode {
-            system: None,
-            dependencies: Default::default(),
-        };
-        let root_node_idx = graph.add_node(root_node);
+        let root_node_idx = graph.add_node(SystemGraphNode::root());
         Self {
             current_dependencies: Default::default(),
             graph,
             root_node_idx,
+            last_exclusive: None,
+            world_readers: Default::default(),
+            system_nodes: Default::default(),
+            orderings: Default::default(),
+            next_system_id: 0,
+            dead_nodes: 0,
             changed_schedule: true,
             cached_schedule: Default::default(),
+            thread_pool: None,
+            record_timings: false,
         }
     }
 
@@ -135,94 +324,268 @@ unsafe impl Scheduler for GraphScheduler {
         let mut system = system.into_system();
         system.init(world);
 
-        let system_is_exclusive = system.is_exclusive(world);
-
-        let system_dependencies = system.compute_dependencies(world);
+        let id = GraphSystemId(self.next_system_id);
+        self.next_system_id += 1;
 
+        let dependencies = system.compute_dependencies(world);
+        let world_id = world.get_or_create_component_id::<WorldContainer>();
         let system_node = SystemGraphNode {
+            id: Some(id),
+            exclusive: system.is_exclusive(world),
+            reads_world: dependencies.get(&world_id) == Some(&AccessMode::Read),
+            dependencies,
             system: Some(Box::new(system)),
-            dependencies: system_dependencies.clone(),
+            last_duration: None,
         };
-        let system_node_idx = self.graph.add_node(system_node);
+        self.place_system(system_node);
+        id
+    }
 
-        if system_is_exclusive {
-            // If a system is exclusive, place a dependency on all the leaf nodes
-            self.place_system_dependency_on_leaves(system_node_idx);
-        } else {
-            let node_dependencies = self.compute_node_dependencies(&system_dependencies);
+    fn remove_system(&mut self, id: Self::SystemId) -> bool {
+        let Some(node_idx) = self.system_nodes.remove(&id) else {
+            return false;
+        };
 
-            if node_dependencies.is_empty() {
-                // System writes to a set of components never encountered before, place it at the beginning
-                self.place_system_at_graph_begin(system_dependencies, system_node_idx);
-            } else {
-                self.place_system_dependencies(node_dependencies, system_node_idx);
-            }
+        // The node is kept in the graph, so that the systems depending on it are still ordered correctly
+        self.graph[node_idx].system = None;
+        self.dead_nodes += 1;
+        self.changed_schedule = true;
+
+        if self.dead_nodes as f32 / self.graph.node_count() as f32 > MAX_DEAD_NODE_RATIO {
+            self.compact();
         }
+        true
+    }
 
-        self.changed_schedule = true;
-        system_node_idx
+    fn contains_system(&self, id: Self::SystemId) -> bool {
+        self.system_nodes.contains_key(&id)
     }
 
     fn execute(&mut self, world: &mut WorldContainer) {
         #[derive(Clone)]
-        struct SystemPtr(*mut dyn System);
+        struct SystemPtr(*mut SystemGraphNode);
 
         unsafe impl Send for SystemPtr {}
         unsafe impl Sync for SystemPtr {}
 
         if self.changed_schedule {
-            self.cached_schedule = self.compute_schedule();
+            self.cached_schedule = self
+                .compute_schedule()
+                .unwrap_or_else(|error| panic!("{error}"));
             self.changed_schedule = false;
         }
         for schedule in self.cached_schedule.groups.iter() {
             let world_ptr = unsafe { world.get_mut_ptr() };
-            let job_ptrs =
-                schedule
-                    .jobs
-                    .iter()
-                    .map(|&job| {
-                        let system = self.graph.node_weight_mut(job).unwrap();
-                        SystemPtr(
-                            system.system.as_mut().map(|m| m.as_mut()).unwrap() as *mut dyn System
-                        )
-                    })
-                    .collect::<Vec<_>>();
-
-            // SAFETY: All the pointers point to different systems, and the scheduler took care of ensuring
-            // that systems in this schedule don't write to the same resources
+            let job_ptrs = schedule
+                .jobs
+                .iter()
+                .map(|job| {
+                    let node = self.graph.node_weight_mut(self.system_nodes[job]).unwrap();
+                    SystemPtr(node as *mut SystemGraphNode)
+                })
+                .collect::<Vec<_>>();
+
+            let record_timings = self.record_timings;
+            // SAFETY: All the pointers point to different nodes, and the scheduler took care of ensuring
+            // that systems in this schedule don't write to the same resources.
+            // Each job is the only one writing the last_duration of its own node
             let exec_system = |sys: &SystemPtr| unsafe {
-                let system = sys.0.as_mut().unwrap();
-                system.run(world_ptr.copied().get_mut())
+                let node = sys.0.as_mut().unwrap();
+                let system = node.system.as_mut().unwrap();
+                if record_timings {
+                    let start = Instant::now();
+                    system.run(world_ptr.copied().get_mut());
+                    node.last_duration = Some(start.elapsed());
+                } else {
+                    system.run(world_ptr.copied().get_mut())
+                }
             };
             if cfg!(miri) {
                 // Don't use rayon with miri, since the global rayon pool is never destroyed
                 // even after the main thread exits (miri complains about that)
                 job_ptrs.iter().for_each(exec_system)
+            } else if let Some(thread_pool) = &self.thread_pool {
+                thread_pool.install(|| job_ptrs.par_iter().for_each(exec_system))
             } else {
                 job_ptrs.par_iter().for_each(exec_system)
             }
+            // The commands of the systems in the group are ordered by source, regardless of which system ran first
+            world.commands.finish_batch();
         }
     }
 
-    fn on_entity_updated(&mut self, world: &mut WorldContainer, entity: Entity) {
-        if let Some(info) = world.get_entity_info(entity) {
-            self.graph.node_weights_mut().for_each(|s| {
-                if let Some(s) = &mut s.system {
-                    s.on_entity_changed(world, entity, info)
-                }
-            })
+    fn run_system(&mut self, world: &mut WorldContainer, id: Self::SystemId) -> bool {
+        let Some(node_idx) = self.system_nodes.get(&id) else {
+            return false;
+        };
+        let system = self.graph[*node_idx]
+            .system
+            .as_mut()
+            .expect("A live system node has no system");
+        system.run(world);
+        world.commands.finish_batch();
+        true
+    }
+
+    fn len(&self) -> usize {
+        self.system_nodes.len()
+    }
+
+    fn max_parallelism(&self) -> usize {
+        self.schedule_groups()
+            .iter()
+            .map(|group| group.len())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl GraphScheduler {
+    /// Creates a new [`GraphScheduler`] that runs the systems on `thread_pool` instead of rayon's global thread pool,
+    /// see [`crate::KecsWorld::set_scheduler`]
+    pub fn with_thread_pool(thread_pool: Arc<ThreadPool>) -> Self {
+        Self {
+            thread_pool: Some(thread_pool),
+            ..Self::new()
+        }
+    }
+
+    /// Enables or disables measuring how long each system takes to run in [`Scheduler::execute`],
+    /// see [`GraphScheduler::last_timings`]. Disabling the measurements clears the recorded timings
+    pub fn set_record_timings(&mut self, record_timings: bool) {
+        self.record_timings = record_timings;
+        if !record_timings {
+            self.graph
+                .node_weights_mut()
+                .for_each(|node| node.last_duration = None);
+        }
+    }
+
+    /// Returns true if the scheduler is measuring how long each system takes to run
+    pub fn record_timings(&self) -> bool {
+        self.record_timings
+    }
+
+    /// Iterates the time each system took in the last [`Scheduler::execute`], ordered by [`GraphSystemId`].
+    /// Only the systems that ran while the timings were being recorded are returned
+    pub fn last_timings(&self) -> impl Iterator<Item = (GraphSystemId, Duration)> + '_ {
+        self.system_nodes
+            .iter()
+            .filter_map(|(id, node_idx)| Some((*id, self.graph[*node_idx].last_duration?)))
+    }
+
+    /// Rebuilds the graph using only the systems that were not removed, placing them again in the order
+    /// they were added: the ids of the systems stay valid.
+    /// This is done automatically when too many systems are removed, see [`Scheduler::remove_system`]
+    pub fn compact(&mut self) {
+        let (nodes, _) = std::mem::take(&mut self.graph).into_nodes_edges();
+        let mut nodes: Vec<_> = nodes.into_iter().map(|node| Some(node.weight)).collect();
+
+        self.current_dependencies = Default::default();
+        self.root_node_idx = self.graph.add_node(SystemGraphNode::root());
+        self.last_exclusive = None;
+        self.world_readers.clear();
+        self.dead_nodes = 0;
+        self.changed_schedule = true;
+
+        // The ids are assigned incrementally, so this iterates the systems in insertion order
+        for node_idx in std::mem::take(&mut self.system_nodes).into_values() {
+            let node = nodes[node_idx.index()]
+                .take()
+                .expect("Each system must have its own node");
+            self.place_system(node);
+        }
+
+        // The orderings of the removed systems are dropped along with their nodes
+        let system_nodes = &self.system_nodes;
+        self.orderings.retain(|(before, after)| {
+            system_nodes.contains_key(before) && system_nodes.contains_key(after)
+        });
+        for (before, after) in &self.orderings {
+            self.graph.add_edge(
+                self.system_nodes[before],
+                self.system_nodes[after],
+                SystemGraphEdge::default(),
+            );
+        }
+    }
+
+    /// Forces the system `before` to run before the system `after`, regardless of the components they access.
+    /// Returns false if any of the two systems doesn't exist, or an error if `after` already has to run before `before`:
+    /// in that case the ordering is not added
+    pub fn add_ordering(
+        &mut self,
+        before: GraphSystemId,
+        after: GraphSystemId,
+    ) -> Result<bool, ScheduleCycleError> {
+        let (Some(&before_node), Some(&after_node)) = (
+            self.system_nodes.get(&before),
+            self.system_nodes.get(&after),
+        ) else {
+            return Ok(false);
+        };
+        let edge = self
+            .graph
+            .add_edge(before_node, after_node, SystemGraphEdge::default());
+        if is_cyclic_directed(&self.graph) {
+            let error = self.find_cycle();
+            self.graph.remove_edge(edge);
+            return Err(error);
+        }
+        self.orderings.push((before, after));
+        self.changed_schedule = true;
+        Ok(true)
+    }
+
+    fn place_system(&mut self, system_node: SystemGraphNode) {
+        let id = system_node
+            .id
+            .expect("Only systems can be placed in the graph");
+        let system_is_exclusive = system_node.exclusive;
+        let system_reads_world = system_node.reads_world;
+        let system_dependencies = system_node.dependencies.clone();
+        let system_node_idx = self.graph.add_node(system_node);
+
+        if system_is_exclusive {
+            // If a system is exclusive, place a dependency on all the leaf nodes
+            self.place_system_dependency_on_leaves(system_dependencies, system_node_idx);
         } else {
-            self.graph.node_weights_mut().for_each(|s| {
-                if let Some(s) = &mut s.system {
-                    s.on_entity_destroyed(world, entity)
+            // The components never encountered before may have been read by the systems reading the whole world
+            for (component, _) in system_dependencies.iter() {
+                if self.current_dependencies.get(&component).is_none()
+                    && !self.world_readers.is_empty()
+                {
+                    self.current_dependencies.insert(
+                        component,
+                        GraphResourceOwnership {
+                            access_mode: AccessMode::Read,
+                            last_writing: None,
+                            last_accessing: self.world_readers.clone(),
+                        },
+                    );
                 }
-            })
+            }
+            let node_dependencies = self.compute_node_dependencies(&system_dependencies);
+
+            if system_reads_world {
+                self.place_world_reader(system_dependencies, system_node_idx);
+            } else if node_dependencies.is_empty() {
+                // System writes to a set of components never encountered before, place it at the beginning
+                self.place_system_at_graph_begin(system_dependencies, system_node_idx);
+            } else {
+                self.place_system_dependencies(
+                    system_dependencies,
+                    node_dependencies,
+                    system_node_idx,
+                );
+            }
         }
+
+        self.system_nodes.insert(id, system_node_idx);
+        self.changed_schedule = true;
     }
-}
 
-impl GraphScheduler {
-    fn compute_schedule(&self) -> Schedules {
+    fn compute_schedule(&self) -> Result<Schedules, ScheduleCycleError> {
         let mut previous_scheduled_nodes = HashSet::new();
         previous_scheduled_nodes.insert(self.root_node_idx);
         let mut current_jobs: HashSet<NodeIndex> = self
@@ -242,28 +605,55 @@ impl GraphScheduler {
                 let all_parents_scheduled =
                     parents.all(|p| previous_scheduled_nodes.contains(&p.source()));
 
-                // A system can only be scheduled if all of its parents have been scheduled
+                // A system can only be scheduled if all of its parents have been scheduled in the previous groups
                 if all_parents_scheduled {
                     self.graph.edges(job).map(|e| e.target()).for_each(|j| {
                         next_jobs.insert(j);
                     });
                     current_schedule.push(job);
-                    previous_scheduled_nodes.insert(job);
                 }
             }
-
-            if !current_schedule.is_empty() {
-                schedules.push(Schedule {
-                    jobs: current_schedule,
+            previous_scheduled_nodes.extend(current_schedule.iter().copied());
+
+            // Removed systems are still in the graph, but they must not be run
+            let jobs: Vec<_> = current_schedule
+                .into_iter()
+                .filter_map(|job| {
+                    let node = &self.graph[job];
+                    node.system.as_ref().and(node.id)
                 })
+                .collect();
+            if !jobs.is_empty() {
+                schedules.push(Schedule { jobs })
             }
             current_jobs = next_jobs;
         }
 
-        Schedules { groups: schedules }
+        // A system is never scheduled only if one of its ancestors depends on itself
+        if previous_scheduled_nodes.len() < self.graph.node_count() {
+            return Err(self.find_cycle());
+        }
+
+        Ok(Schedules { groups: schedules })
     }
 
-    fn place_system_dependency_on_leaves(&mut self, system_node_idx: NodeIndex) {
+    fn find_cycle(&self) -> ScheduleCycleError {
+        let systems = tarjan_scc(&self.graph)
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || self.graph.contains_edge(component[0], component[0])
+            })
+            .flatten()
+            .map(|node| self.graph[node].name())
+            .collect();
+        ScheduleCycleError { systems }
+    }
+
+    fn place_system_dependency_on_leaves(
+        &mut self,
+        system_dependencies: SparseSet<ComponentId, AccessMode>,
+        system_node_idx: NodeIndex,
+    ) {
         let leaves: HashSet<NodeIndex> = self
             .graph
             .node_indices()
@@ -287,6 +677,53 @@ impl GraphScheduler {
             dep.last_accessing.clear();
             dep.last_writing = Some(system_node_idx);
         }
+        for (component, _) in system_dependencies.iter() {
+            self.current_dependencies.insert(
+                component,
+                GraphResourceOwnership {
+                    access_mode: AccessMode::Write,
+                    last_writing: Some(system_node_idx),
+                    last_accessing: HashSet::default(),
+                },
+            );
+        }
+        // The systems accessing resources never encountered before must also run after the exclusive system
+        self.last_exclusive = Some(system_node_idx);
+        self.world_readers.clear();
+    }
+
+    // A system reading the whole world runs after the last system writing each component,
+    // and before the next systems writing any component
+    fn place_world_reader(
+        &mut self,
+        system_dependencies: SparseSet<ComponentId, AccessMode>,
+        system_node_idx: NodeIndex,
+    ) {
+        for (component, _) in system_dependencies.iter() {
+            if self.current_dependencies.get(&component).is_none() {
+                self.current_dependencies.insert(
+                    component,
+                    GraphResourceOwnership {
+                        access_mode: AccessMode::Read,
+                        last_writing: None,
+                        last_accessing: HashSet::default(),
+                    },
+                );
+            }
+        }
+        let mut writers = HashSet::new();
+        for dep in self.current_dependencies.iter_mut() {
+            writers.extend(dep.last_writing);
+            dep.last_accessing.insert(system_node_idx);
+        }
+        if writers.is_empty() {
+            writers.insert(self.last_exclusive.unwrap_or(self.root_node_idx));
+        }
+        for writer in writers {
+            self.graph
+                .add_edge(writer, system_node_idx, SystemGraphEdge::default());
+        }
+        self.world_readers.insert(system_node_idx);
     }
 
     fn compute_node_dependencies(
@@ -304,26 +741,20 @@ impl GraphScheduler {
                         // No previous reader, depend on the latest writing
                         if ownership.last_accessing.is_empty() {
                             if let Some(writer) = ownership.last_writing {
-                                node_dependencies.insert(
-                                    writer,
-                                    SystemGraphEdge {
-                                        changes: vec![SystemGraphChange {
-                                            component,
-                                            new_access_mode: access,
-                                        }],
+                                node_dependencies.entry(writer).or_default().changes.push(
+                                    SystemGraphChange {
+                                        component,
+                                        new_access_mode: access,
                                     },
                                 );
                             }
                         } else {
                             // Depend on the latest readers
                             for reading in &ownership.last_accessing {
-                                node_dependencies.insert(
-                                    *reading,
-                                    SystemGraphEdge {
-                                        changes: vec![SystemGraphChange {
-                                            component,
-                                            new_access_mode: access,
-                                        }],
+                                node_dependencies.entry(*reading).or_default().changes.push(
+                                    SystemGraphChange {
+                                        component,
+                                        new_access_mode: access,
                                     },
                                 );
                             }
@@ -332,13 +763,10 @@ impl GraphScheduler {
                     // If a system reads a resource, it depends on the latest one writing it
                     AccessMode::Read => {
                         if let Some(writer) = ownership.last_writing {
-                            node_dependencies.insert(
-                                writer,
-                                SystemGraphEdge {
-                                    changes: vec![SystemGraphChange {
-                                        component,
-                                        new_access_mode: access,
-                                    }],
+                            node_dependencies.entry(writer).or_default().changes.push(
+                                SystemGraphChange {
+                                    component,
+                                    new_access_mode: access,
                                 },
                             );
                         }
@@ -355,15 +783,21 @@ impl GraphScheduler {
         system_node_idx: NodeIndex,
     ) {
         for (component, access) in system_dependencies.iter() {
+            // The readers of a component that was never written keep accessing it
+            let mut last_accessing = self
+                .current_dependencies
+                .get(&component)
+                .filter(|_| *access == AccessMode::Read)
+                .map(|dep| dep.last_accessing.clone())
+                .unwrap_or_default();
+            if *access == AccessMode::Read {
+                last_accessing.insert(system_node_idx);
+            }
             self.current_dependencies.insert(
                 component,
                 GraphResourceOwnership {
                     access_mode: *access,
-                    last_accessing: if *access == AccessMode::Read {
-                        HashSet::from_iter([system_node_idx])
-                    } else {
-                        HashSet::default()
-                    },
+                    last_accessing,
                     last_writing: if *access == AccessMode::Write {
                         Some(system_node_idx)
                     } else {
@@ -373,7 +807,7 @@ impl GraphScheduler {
             );
         }
         self.graph.add_edge(
-            self.root_node_idx,
+            self.last_exclusive.unwrap_or(self.root_node_idx),
             system_node_idx,
             SystemGraphEdge::default(),
         );
@@ -381,9 +815,37 @@ impl GraphScheduler {
 
     fn place_system_dependencies(
         &mut self,
+        system_dependencies: SparseSet<ComponentId, AccessMode>,
         node_dependencies: HashMap<NodeIndex, SystemGraphEdge>,
         system_node_idx: NodeIndex,
     ) {
+        // The components without a dependency must still be tracked, so that the next writers run after this system
+        for (component, access) in system_dependencies.iter() {
+            match self.current_dependencies.get_mut(component) {
+                Some(dep) if *access == AccessMode::Read && dep.last_writing.is_none() => {
+                    dep.last_accessing.insert(system_node_idx);
+                }
+                Some(_) => {}
+                None => {
+                    self.current_dependencies.insert(
+                        component,
+                        GraphResourceOwnership {
+                            access_mode: *access,
+                            last_accessing: if *access == AccessMode::Read {
+                                HashSet::from_iter([system_node_idx])
+                            } else {
+                                HashSet::default()
+                            },
+                            last_writing: if *access == AccessMode::Write {
+                                Some(system_node_idx)
+                            } else {
+                                None
+                            },
+                        },
+                    );
+                }
+            }
+        }
         for (owner, changes) in node_dependencies {
             for change in &changes.changes {
                 let dep = self.current_dependencies.get_mut(change.component).unwrap();
@@ -401,9 +863,39 @@ impl GraphScheduler {
     }
 }
 
+/// This error is returned when the systems of a [`GraphScheduler`] (or the orderings of a [`LinearScheduler`])
+/// depend on each other in a cycle, making it impossible to find an order in which they can be run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduleCycleError {
+    /// The names of the systems involved in the cycle
+    pub systems: Vec<Cow<'static, str>>,
+}
+
+impl std::fmt::Display for ScheduleCycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The schedule contains a cycle between the systems: {}",
+            self.systems.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ScheduleCycleError {}
+
+/// The structure of the schedule of a [`GraphScheduler`], see [`GraphScheduler::export_schedule`].
+/// With the `serde` feature the export can be serialized, e.g to compare the schedule across runs or against a golden file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScheduleExport {
+    /// The names of the systems in each group, in the order the groups are executed.
+    /// The names in each group are sorted
+    pub groups: Vec<Vec<String>>,
+}
+
 #[derive(Default, Debug)]
 struct Schedule {
-    jobs: Vec<NodeIndex>,
+    jobs: Vec<GraphSystemId>,
 }
 
 #[derive(Default, Debug)]
@@ -412,29 +904,129 @@ struct Schedules {
 }
 
 impl GraphScheduler {
-    /// This method prints the current job graph to stdout in Dot format, which can be viewed e.g
+    /// Gets the name of the system identified by `id`, if it exists
+    pub fn system_name(&self, id: GraphSystemId) -> Option<Cow<'static, str>> {
+        self.system_nodes
+            .get(&id)
+            .and_then(|&node| self.graph[node].system.as_ref())
+            .map(|system| system.get_name())
+    }
+
+    /// Gets the dependencies of the system identified by `id`, if it exists
+    pub fn system_dependencies(
+        &self,
+        id: GraphSystemId,
+    ) -> Option<&SparseSet<ComponentId, AccessMode>> {
+        self.system_nodes
+            .get(&id)
+            .map(|&node| &self.graph[node].dependencies)
+    }
+
+    /// Explains why the system `to` must run after the system `from`, returning the name of each component
+    /// causing the dependency along with how `to` accesses it.
+    /// Returns None if either system doesn't exist, or if `to` doesn't directly depend on `from`
+    pub fn explain_edge(
+        &self,
+        from: GraphSystemId,
+        to: GraphSystemId,
+    ) -> Option<Vec<(String, AccessMode)>> {
+        let from = *self.system_nodes.get(&from)?;
+        let to = *self.system_nodes.get(&to)?;
+        let edge = self.graph.find_edge(from, to)?;
+        Some(
+            self.graph[edge]
+                .changes
+                .iter()
+                .map(|change| (change.component.name().to_owned(), change.new_access_mode))
+                .collect(),
+        )
+    }
+
+    /// Returns the groups of systems in the order they will be executed: the systems in a group
+    /// can be run in parallel, and each group runs only after the previous one has completed
+    /// # Panics
+    /// This method panics if the systems depend on each other in a cycle, see [`ScheduleCycleError`]
+    pub fn schedule_groups(&self) -> Vec<Vec<GraphSystemId>> {
+        self.compute_schedule()
+            .unwrap_or_else(|error| panic!("{error}"))
+            .groups
+            .into_iter()
+            .map(|group| group.jobs)
+            .collect()
+    }
+
+    /// Exports the names of the systems in each group of the schedule, see [`GraphScheduler::schedule_groups`]
+    pub fn export_schedule(&self) -> ScheduleExport {
+        let groups = self
+            .schedule_groups()
+            .into_iter()
+            .map(|group| {
+                let mut names: Vec<_> = group
+                    .into_iter()
+                    .map(|id| self.system_name(id).unwrap().into_owned())
+                    .collect();
+                names.sort();
+                names
+            })
+            .collect();
+        ScheduleExport { groups }
+    }
+
+    /// Returns true if the schedule has the same structure of a previously exported schedule, see [`GraphScheduler::export_schedule`]
+    pub fn matches_schedule(&self, expected: &ScheduleExport) -> bool {
+        self.export_schedule() == *expected
+    }
+
+    /// Returns the current job graph in Dot format, which can be viewed e.g
     /// using [https://viz-js.com/](https://viz-js.com/)
+    pub fn dot_string(&self) -> String {
+        Dot::new(&self.graph).to_string()
+    }
+
+    /// This method prints the current job graph to stdout in Dot format, see [`GraphScheduler::dot_string`]
     pub fn print_jobs(&self) {
-        let dot = Dot::new(&self.graph);
-        println!("{}", dot);
+        println!("{}", self.dot_string());
     }
 }
 
 pub struct SystemGraphNode {
+    // None for the root node
+    id: Option<GraphSystemId>,
+    // None for the root node and for the removed systems
     system: Option<Box<dyn System>>,
     dependencies: SparseSet<ComponentId, AccessMode>,
+    exclusive: bool,
+    // True if the system reads the whole world through &WorldContainer
+    reads_world: bool,
+    // Written by execute only when the timings are being recorded
+    last_duration: Option<Duration>,
+}
+
+impl SystemGraphNode {
+    fn root() -> Self {
+        Self {
+            id: None,
+            system: None,
+            dependencies: Default::default(),
+            exclusive: false,
+            reads_world: false,
+            last_duration: None,
+        }
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        match (&self.system, self.id) {
+            (Some(system), _) => system.get_name(),
+            (None, Some(_)) => Cow::Borrowed("Removed"),
+            (None, None) => Cow::Borrowed("Root"),
+        }
+    }
 }
 
 impl std::fmt::Debug for SystemGraphNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SystemGraphNode")
-            .field(
-                "system",
-                &self
-                    .system
-                    .as_ref()
-                    .map_or_else(|| Cow::Borrowed("Root"), |sys| sys.get_name()),
-            )
+            .field("system", &self.name())
             .field("dependencies", &self.dependencies)
             .finish()
     }
@@ -464,13 +1056,7 @@ pub struct GraphResourceOwnership {
 
 impl std::fmt::Display for SystemGraphNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(
-            &self
-                .system
-                .as_ref()
-                .map(|s| s.get_name())
-                .unwrap_or("Root".into()),
-        )?;
+        f.write_str(&self.name())?;
 
         if !self.dependencies.is_empty() {
             writeln!(f)?;
@@ -507,9 +1093,14 @@ impl std::fmt::Display for SystemGraphEdge {
 
 #[cfg(test)]
 mod tests {
-    use crate::{commands::Commands, query::Query, Entity, WorldContainer};
+    use crate::{
+        commands::Commands, query::Query, AccessMode, ComponentId, Entity, Res, ResMut, Resource,
+        Resources, WorldContainer,
+    };
+
+    use std::time::Duration;
 
-    use super::{GraphScheduler, Scheduler};
+    use super::{GraphScheduler, GraphSystemId, LinearScheduler, Scheduler};
 
     #[derive(Default)]
     struct Component1;
@@ -526,14 +1117,15 @@ mod tests {
 
     fn read_component_1(_: Query<&Component1>) {}
     fn non_parallel_system(_: &mut WorldContainer) {}
-    fn read_write_component_1(_: Query<&Component1>, _: Query<&mut Component1>) {}
+    fn read_write_component_1(_: Query<&mut Component1>) {}
 
     #[test]
     fn empty_schedule() {
         let scheduler = GraphScheduler::new();
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
         assert!(schedule.groups.is_empty());
+        assert_eq!(scheduler.max_parallelism(), 0);
     }
 
     #[test]
@@ -544,7 +1136,7 @@ mod tests {
         let system_0 = scheduler.add_system(&mut world, write_component_1);
         let system_1 = scheduler.add_system(&mut world, write_component_2);
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
         assert_eq!(schedule.groups.len(), 1);
         assert!(schedule.groups[0].jobs.contains(&system_0));
         assert!(schedule.groups[0].jobs.contains(&system_1));
@@ -560,7 +1152,7 @@ mod tests {
 
         scheduler.print_jobs();
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
         assert_eq!(schedule.groups.len(), 2);
         assert!(schedule.groups[0].jobs.contains(&system_0));
         assert!(schedule.groups[1].jobs.contains(&system_1));
@@ -574,7 +1166,7 @@ mod tests {
         let system_0 = scheduler.add_system(&mut world, read_component_1);
         let system_1 = scheduler.add_system(&mut world, read_component_1);
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
         assert_eq!(schedule.groups.len(), 1);
         assert!(schedule.groups[0].jobs.contains(&system_0));
         assert!(schedule.groups[0].jobs.contains(&system_1));
@@ -588,12 +1180,86 @@ mod tests {
         let system_0 = scheduler.add_system(&mut world, write_component_1);
         let system_1 = scheduler.add_system(&mut world, write_component_1);
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
         assert_eq!(schedule.groups.len(), 2);
         assert!(schedule.groups[0].jobs.contains(&system_0));
         assert!(schedule.groups[1].jobs.contains(&system_1));
     }
 
+    #[test]
+    fn parallel_entity_queries() {
+        fn entities_write_1(_: Query<Entity>, _: Query<(Entity, &mut Component1)>) {}
+        fn entities_write_2(_: Query<Entity>, _: Query<(Entity, &mut Component2)>) {}
+        fn only_entities(_: Query<Entity>) {}
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        // Entity accesses no components, so it doesn't add any dependency between the systems
+        let system_0 = scheduler.add_system(&mut world, entities_write_1);
+        let system_1 = scheduler.add_system(&mut world, entities_write_2);
+        let system_2 = scheduler.add_system(&mut world, only_entities);
+
+        let schedule = scheduler.compute_schedule().unwrap();
+        assert_eq!(schedule.groups.len(), 1);
+        for system in [system_0, system_1, system_2] {
+            assert!(schedule.groups[0].jobs.contains(&system));
+        }
+        assert_eq!(scheduler.explain_edge(system_0, system_1), None);
+    }
+
+    #[test]
+    fn resource_access() {
+        struct Config;
+        impl Resource for Config {}
+
+        fn read_config(_: Res<Config>) {}
+        fn write_config(_: ResMut<Config>) {}
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        let system_0 = scheduler.add_system(&mut world, write_config);
+        let system_1 = scheduler.add_system(&mut world, write_config);
+        let system_2 = scheduler.add_system(&mut world, read_config);
+        let system_3 = scheduler.add_system(&mut world, read_config);
+
+        let schedule = scheduler.compute_schedule().unwrap();
+        assert_eq!(schedule.groups.len(), 3);
+        assert!(schedule.groups[0].jobs.contains(&system_0));
+        assert!(schedule.groups[1].jobs.contains(&system_1));
+        // Two readers can still run in parallel
+        assert!(schedule.groups[2].jobs.contains(&system_2));
+        assert!(schedule.groups[2].jobs.contains(&system_3));
+    }
+
+    #[test]
+    fn record_timings() {
+        fn slow_system(_: Query<&mut Component1>) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+        let slow = scheduler.add_system(&mut world, slow_system);
+        let fast = scheduler.add_system(&mut world, write_component_2);
+
+        // Timings are opt-in
+        scheduler.execute(&mut world);
+        assert_eq!(scheduler.last_timings().count(), 0);
+
+        scheduler.set_record_timings(true);
+        scheduler.execute(&mut world);
+        let timings = scheduler.last_timings().collect::<Vec<_>>();
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].0, slow);
+        assert!(timings[0].1 >= Duration::from_millis(5));
+        assert_eq!(timings[1].0, fast);
+
+        scheduler.set_record_timings(false);
+        assert_eq!(scheduler.last_timings().count(), 0);
+    }
+
     #[test]
     fn read_then_write() {
         let mut world = make_world_container();
@@ -602,7 +1268,7 @@ mod tests {
         let system_0 = scheduler.add_system(&mut world, read_component_1);
         let system_1 = scheduler.add_system(&mut world, write_component_1);
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
         assert_eq!(schedule.groups.len(), 2);
         assert!(schedule.groups[0].jobs.contains(&system_0));
         assert!(schedule.groups[1].jobs.contains(&system_1));
@@ -620,7 +1286,7 @@ mod tests {
 
         let system_2 = scheduler.add_system(&mut world, read_component_1);
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
 
         scheduler.print_jobs();
         assert_eq!(schedule.groups.len(), 3);
@@ -640,7 +1306,7 @@ mod tests {
         // If a system takes a &mut World, it's an exclusive system: it cannot be run in parallel in any case
         let system_1 = scheduler.add_system(&mut world, non_parallel_system);
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
 
         scheduler.print_jobs();
         assert_eq!(schedule.groups.len(), 2);
@@ -649,12 +1315,73 @@ mod tests {
         assert!(schedule.groups[1].jobs.contains(&system_1));
     }
 
+    #[test]
+    fn parallel_world_readers() {
+        fn read_world_a(_: &WorldContainer) {}
+        fn read_world_b(_: &WorldContainer) {}
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        let writer = scheduler.add_system(&mut world, non_parallel_system);
+        let reader_a = scheduler.add_system(&mut world, read_world_a);
+        let reader_b = scheduler.add_system(&mut world, read_world_b);
+        let next_writer = scheduler.add_system(&mut world, non_parallel_system);
+
+        // The readers run together, but only after the previous world writer has completed
+        let groups = scheduler.schedule_groups();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0], vec![writer]);
+        assert_eq!(groups[1].len(), 2);
+        assert!(groups[1].contains(&reader_a));
+        assert!(groups[1].contains(&reader_b));
+        assert_eq!(groups[2], vec![next_writer]);
+    }
+
+    #[test]
+    fn world_readers_dont_run_with_component_writers() {
+        struct Health;
+        struct Position;
+
+        fn reader(_: &WorldContainer) {}
+        fn health_writer(_: Query<&mut Health>) {}
+        fn position_writer(_: Query<&mut Position>) {}
+        fn health_reader(_: Query<&Health>) {}
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        // A writer placed before the reader
+        let first_writer = scheduler.add_system(&mut world, health_writer);
+        let world_reader = scheduler.add_system(&mut world, reader);
+        let groups = scheduler.schedule_groups();
+        assert_eq!(groups, vec![vec![first_writer], vec![world_reader]]);
+
+        // A reader of a component can still run with the world reader
+        let other_reader = scheduler.add_system(&mut world, health_reader);
+        // Writers placed after the reader, including one of a component never seen before
+        let second_writer = scheduler.add_system(&mut world, health_writer);
+        let new_writer = scheduler.add_system(&mut world, position_writer);
+
+        let groups = scheduler.schedule_groups();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0], vec![first_writer]);
+        assert_eq!(groups[1].len(), 2);
+        assert!(groups[1].contains(&world_reader));
+        assert!(groups[1].contains(&other_reader));
+        assert_eq!(groups[2].len(), 2);
+        assert!(groups[2].contains(&second_writer));
+        assert!(groups[2].contains(&new_writer));
+    }
+
     /// System A, B, C read from the same component but write to different components
     /// then F writes to the world
     /// Finally D uses A's result with a non-send resource
     /// The schedule should be (A, B, C) -> (F) -> (D)
-    #[test]
-    fn multi_nodes() {
+    // Adds the systems used by the multi_nodes test: a, b, c, exclusive, d, e, f
+    fn make_multi_nodes_scheduler(
+        world: &mut WorldContainer,
+    ) -> (GraphScheduler, [GraphSystemId; 7]) {
         struct SharedByABC;
         struct WrittenByA;
         struct WrittenByB;
@@ -666,20 +1393,28 @@ mod tests {
         fn exclusive_sys(_: &mut WorldContainer) {}
         fn sys_d(_: &mut WorldContainer) {}
         fn sys_e(_: Query<&WrittenByA>, _: &WorldContainer) {}
-        fn sys_f(_: Query<(&mut WrittenByB, &WrittenByC)>, _: &WorldContainer) {}
+        fn sys_f(_: Query<(&WrittenByB, &WrittenByC)>, _: &WorldContainer) {}
 
-        let mut world = make_world_container();
         let mut scheduler = GraphScheduler::new();
+        let ids = [
+            scheduler.add_system(world, sys_a),
+            scheduler.add_system(world, sys_b),
+            scheduler.add_system(world, sys_c),
+            scheduler.add_system(world, exclusive_sys),
+            scheduler.add_system(world, sys_d),
+            scheduler.add_system(world, sys_e),
+            scheduler.add_system(world, sys_f),
+        ];
+        (scheduler, ids)
+    }
 
-        let sys_a_id = scheduler.add_system(&mut world, sys_a);
-        let sys_b_id = scheduler.add_system(&mut world, sys_b);
-        let sys_c_id = scheduler.add_system(&mut world, sys_c);
-        let sys_excl_id = scheduler.add_system(&mut world, exclusive_sys);
-        let sys_d_id = scheduler.add_system(&mut world, sys_d);
-        let sys_e_id = scheduler.add_system(&mut world, sys_e);
-        let sys_f_id = scheduler.add_system(&mut world, sys_f);
+    #[test]
+    fn multi_nodes() {
+        let mut world = make_world_container();
+        let (scheduler, [sys_a_id, sys_b_id, sys_c_id, sys_excl_id, sys_d_id, sys_e_id, sys_f_id]) =
+            make_multi_nodes_scheduler(&mut world);
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
 
         scheduler.print_jobs();
         assert_eq!(schedule.groups.len(), 4);
@@ -691,6 +1426,314 @@ mod tests {
         assert!(schedule.groups[2].jobs.contains(&sys_d_id));
         assert!(schedule.groups[3].jobs.contains(&sys_e_id));
         assert!(schedule.groups[3].jobs.contains(&sys_f_id));
+
+        assert_eq!(scheduler.max_parallelism(), 3);
+    }
+
+    #[test]
+    fn optional_resource_added_after_system() {
+        struct Counter(u32);
+        impl Resource for Counter {}
+
+        fn increment(counter: Option<ResMut<Counter>>) {
+            if let Some(mut counter) = counter {
+                counter.0 += 1;
+            }
+        }
+        fn read(_: Res<Counter>) {}
+        fn read_group(_: Option<Resources<(Counter,)>>) {}
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+        let increment_id = scheduler.add_system(&mut world, increment);
+        let read_group_id = scheduler.add_system(&mut world, read_group);
+        world.add_resource(Counter(0));
+        let read_id = scheduler.add_system(&mut world, read);
+
+        // The writer must not run in parallel with the readers, even if the resource was missing when it was added
+        let mut groups = scheduler.schedule_groups();
+        groups[1].sort();
+        assert_eq!(
+            groups,
+            vec![vec![increment_id], vec![read_group_id, read_id]]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn export_schedule() {
+        let mut world = make_world_container();
+        let (scheduler, _) = make_multi_nodes_scheduler(&mut world);
+
+        let export = scheduler.export_schedule();
+        let json = serde_json::to_value(&export).unwrap();
+        let name =
+            |system: &str| format!("kecs::schedule::tests::make_multi_nodes_scheduler::{system}");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "groups": [
+                    [name("sys_a"), name("sys_b"), name("sys_c")],
+                    [name("exclusive_sys")],
+                    [name("sys_d")],
+                    [name("sys_e"), name("sys_f")],
+                ]
+            })
+        );
+
+        let imported: super::ScheduleExport = serde_json::from_value(json).unwrap();
+        assert!(scheduler.matches_schedule(&imported));
+
+        let mut other_world = make_world_container();
+        let mut other = GraphScheduler::new();
+        other.add_system(&mut other_world, write_component_1);
+        assert!(!other.matches_schedule(&imported));
+    }
+
+    #[test]
+    fn system_introspection() {
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        let system_0 = scheduler.add_system(&mut world, write_component_1);
+        let system_1 = scheduler.add_system(&mut world, read_component_1);
+
+        assert!(scheduler
+            .system_name(system_0)
+            .unwrap()
+            .ends_with("write_component_1"));
+        assert!(scheduler
+            .system_name(system_1)
+            .unwrap()
+            .ends_with("read_component_1"));
+
+        let component_1 = world.get_component_id_assertive::<Component1>();
+        let deps = scheduler.system_dependencies(system_0).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps.get(&component_1), Some(&AccessMode::Write));
+        let deps = scheduler.system_dependencies(system_1).unwrap();
+        assert_eq!(deps.get(&component_1), Some(&AccessMode::Read));
+
+        scheduler.remove_system(system_1);
+        assert!(scheduler.system_name(system_1).is_none());
+        assert!(scheduler.system_dependencies(system_1).is_none());
+    }
+
+    #[test]
+    fn schedule_groups_and_dot() {
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        let system_0 = scheduler.add_system(&mut world, write_component_1);
+        let system_1 = scheduler.add_system(&mut world, read_component_1);
+        let system_2 = scheduler.add_system(&mut world, read_component_1);
+
+        let groups = scheduler.schedule_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0], vec![system_0]);
+        assert_eq!(groups[1].len(), 2);
+        assert!(groups[1].contains(&system_1));
+        assert!(groups[1].contains(&system_2));
+
+        let dot = scheduler.dot_string();
+        assert!(dot.starts_with("digraph"));
+        assert!(dot.contains("write_component_1"));
+        assert!(dot.contains("read_component_1"));
+    }
+
+    #[test]
+    fn dot_string_shows_component_names() {
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+        scheduler.add_system(&mut world, write_component_1);
+        scheduler.add_system(&mut world, read_component_1);
+
+        let dot = scheduler.dot_string();
+        assert!(dot.contains(std::any::type_name::<Component1>()));
+        assert!(!dot.contains("<unknown>"));
+        assert_eq!(ComponentId::from(5usize).name(), "<unknown>");
+    }
+
+    #[test]
+    fn cycle_is_reported() {
+        fn system_a(_: Query<&mut Component1>) {}
+        fn system_b(_: Query<&Component1>) {}
+        fn system_c(_: Query<&mut Component2>) {}
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        let system_a_id = scheduler.add_system(&mut world, system_a);
+        let system_b_id = scheduler.add_system(&mut world, system_b);
+        scheduler.add_system(&mut world, system_c);
+
+        // system_b already runs after system_a: force system_a to also run after system_b
+        let error = scheduler
+            .add_ordering(system_b_id, system_a_id)
+            .unwrap_err();
+        assert_eq!(error.systems.len(), 2);
+        assert!(error.systems.iter().any(|s| s.ends_with("system_a")));
+        assert!(error.systems.iter().any(|s| s.ends_with("system_b")));
+        assert!(error.to_string().contains("system_a"));
+
+        // The ordering is not added, so the systems can still be scheduled
+        assert_eq!(scheduler.schedule_groups().len(), 2);
+        assert!(scheduler.add_ordering(system_a_id, system_a_id).is_err());
+    }
+
+    #[test]
+    fn graph_scheduler_ordering() {
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+        let reader_a = scheduler.add_system(&mut world, read_component_1);
+        let reader_b = scheduler.add_system(&mut world, read_component_1);
+        let removed = scheduler.add_system(&mut world, read_component_1);
+        assert_eq!(scheduler.schedule_groups().len(), 1);
+
+        assert_eq!(scheduler.add_ordering(reader_b, reader_a), Ok(true));
+        let mut groups = scheduler.schedule_groups();
+        groups[0].sort();
+        assert_eq!(groups, vec![vec![reader_b, removed], vec![reader_a]]);
+
+        // The orderings are kept when the graph is compacted
+        scheduler.remove_system(removed);
+        assert_eq!(scheduler.add_ordering(removed, reader_a), Ok(false));
+        scheduler.compact();
+        assert_eq!(
+            scheduler.schedule_groups(),
+            vec![vec![reader_b], vec![reader_a]]
+        );
+    }
+
+    #[test]
+    fn linear_scheduler_order() {
+        let mut world = make_world_container();
+        let mut scheduler = LinearScheduler::new();
+        let systems = (0..5)
+            .map(|_| scheduler.add_system(&mut world, write_component_1))
+            .collect::<Vec<_>>();
+        assert_eq!(scheduler.compute_order().unwrap(), systems);
+
+        // Only the constrained systems are moved, the others keep the order they were added in
+        assert_eq!(scheduler.add_ordering(systems[3], systems[1]), Ok(true));
+        assert_eq!(scheduler.add_ordering(systems[4], systems[3]), Ok(true));
+        assert_eq!(
+            scheduler.compute_order().unwrap(),
+            vec![systems[0], systems[2], systems[4], systems[3], systems[1]]
+        );
+
+        // The orderings of a removed system are ignored
+        scheduler.remove_system(systems[4]);
+        assert_eq!(
+            scheduler.compute_order().unwrap(),
+            vec![systems[0], systems[2], systems[3], systems[1]]
+        );
+    }
+
+    #[test]
+    fn remove_system() {
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        let system_0 = scheduler.add_system(&mut world, write_component_1);
+        let system_1 = scheduler.add_system(&mut world, read_component_1);
+        let system_2 = scheduler.add_system(&mut world, write_component_1);
+
+        assert!(scheduler.remove_system(system_1));
+        assert!(!scheduler.remove_system(system_1));
+        assert_eq!(scheduler.len(), 2);
+
+        let groups = scheduler.schedule_groups();
+        assert_eq!(groups, vec![vec![system_0], vec![system_2]]);
+    }
+
+    #[test]
+    fn explain_edge() {
+        fn write_both(_: Query<(&mut Component1, &mut Component2)>) {}
+        fn read_both(_: Query<(&Component1, &Component2)>) {}
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+        let writer = scheduler.add_system(&mut world, write_component_1);
+        let reader = scheduler.add_system(&mut world, read_component_1);
+        let second_writer = scheduler.add_system(&mut world, write_component_1);
+
+        let name = std::any::type_name::<Component1>().to_owned();
+        assert_eq!(
+            scheduler.explain_edge(writer, reader),
+            Some(vec![(name.clone(), AccessMode::Read)])
+        );
+        assert_eq!(
+            scheduler.explain_edge(reader, second_writer),
+            Some(vec![(name, AccessMode::Write)])
+        );
+        assert_eq!(scheduler.explain_edge(reader, writer), None);
+
+        // All the components shared by the two systems explain the edge
+        let mut scheduler = GraphScheduler::new();
+        let writer = scheduler.add_system(&mut world, write_both);
+        let reader = scheduler.add_system(&mut world, read_both);
+        let mut explanation = scheduler.explain_edge(writer, reader).unwrap();
+        explanation.sort();
+        let mut expected = vec![
+            (
+                std::any::type_name::<Component1>().to_owned(),
+                AccessMode::Read,
+            ),
+            (
+                std::any::type_name::<Component2>().to_owned(),
+                AccessMode::Read,
+            ),
+        ];
+        expected.sort();
+        assert_eq!(explanation, expected);
+    }
+
+    #[test]
+    fn contains_system() {
+        let mut world = make_world_container();
+
+        let mut scheduler = GraphScheduler::new();
+        let system_0 = scheduler.add_system(&mut world, write_component_1);
+        let system_1 = scheduler.add_system(&mut world, read_component_1);
+        assert!(scheduler.remove_system(system_0));
+        assert!(!scheduler.contains_system(system_0));
+        assert!(scheduler.contains_system(system_1));
+
+        let mut scheduler = LinearScheduler::new();
+        let system_0 = scheduler.add_system(&mut world, write_component_1);
+        let system_1 = scheduler.add_system(&mut world, read_component_1);
+        assert!(scheduler.remove_system(system_0));
+        assert!(!scheduler.contains_system(system_0));
+        assert!(scheduler.contains_system(system_1));
+        assert!(!scheduler.contains_system(system_1 + 1));
+    }
+
+    #[test]
+    fn compact_bounds_graph_growth() {
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+
+        let writer = scheduler.add_system(&mut world, write_component_1);
+        for _ in 0..100 {
+            let reader = scheduler.add_system(&mut world, read_component_1);
+            let other = scheduler.add_system(&mut world, write_component_2);
+            scheduler.remove_system(reader);
+            scheduler.remove_system(other);
+            // The root, the writer and at most as many removed systems as the live nodes
+            assert!(scheduler.graph.node_count() <= 4);
+        }
+
+        let reader = scheduler.add_system(&mut world, read_component_1);
+        scheduler.remove_system(writer);
+        scheduler.compact();
+        assert_eq!(scheduler.graph.node_count(), 2);
+        assert_eq!(scheduler.len(), 1);
+        assert!(scheduler
+            .system_name(reader)
+            .unwrap()
+            .ends_with("read_component_1"));
+        assert_eq!(scheduler.schedule_groups(), vec![vec![reader]]);
     }
 
     #[test]
@@ -711,8 +1754,8 @@ mod tests {
         }
 
         // A query is an iterator over the entities with the specified components
-        fn update_bullet_position(query: Query<(&Bullet, &mut Transform)>) {
-            for (bullet, transform) in query.iter() {
+        fn update_bullet_position(mut query: Query<(&Bullet, &mut Transform)>) {
+            for (bullet, transform) in query.iter_mut() {
                 transform.position[0] += bullet.direction[0];
                 transform.position[1] += bullet.direction[1];
             }
@@ -744,7 +1787,7 @@ mod tests {
         let print_2 = scheduler.add_system(&mut world, print_transform_system);
         scheduler.print_jobs();
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
 
         assert!(schedule.groups.len() == 2);
         assert!(schedule.groups[0].jobs.contains(&update));
@@ -768,7 +1811,7 @@ mod tests {
         let sys_4 = scheduler.add_system(&mut world, sys_write_a);
         let sys_5 = scheduler.add_system(&mut world, sys_write_a);
 
-        let schedule = scheduler.compute_schedule();
+        let schedule = scheduler.compute_schedule().unwrap();
 
         scheduler.print_jobs();
 
@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{commands::TypedBlob, ComponentId, Entity, WorldContainer};
+
+/// A [`WorldSnapshot`] is a serializable copy of all the entities in a [`WorldContainer`], along with
+/// the components registered through [`WorldContainer::register_serializable`].
+/// Components that were not registered as serializable are skipped
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    /// The entities stored in the snapshot
+    pub entities: Vec<EntitySnapshot>,
+}
+
+/// The serialized state of a single [`Entity`]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    /// The id the entity had when the snapshot was taken
+    pub entity: Entity,
+    /// The serialized components of the entity, keyed by their type name
+    pub components: BTreeMap<String, Value>,
+}
+
+/// The errors that can happen while taking or loading a [`WorldSnapshot`]
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The snapshot contains a component whose type was not registered as serializable
+    UnregisteredComponent(String),
+    /// A component could not be serialized or deserialized
+    Serde {
+        /// The type name of the component
+        component: String,
+        /// The underlying serde error
+        error: serde_json::Error,
+    },
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::UnregisteredComponent(component) => {
+                write!(
+                    f,
+                    "component '{component}' is not registered as serializable"
+                )
+            }
+            SnapshotError::Serde { component, error } => {
+                write!(
+                    f,
+                    "failed to (de)serialize component '{component}': {error}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+pub(crate) struct SerializableComponent {
+    component_id: ComponentId,
+    serialize: fn(&WorldContainer, Entity) -> Option<serde_json::Result<Value>>,
+    deserialize: fn(Value) -> serde_json::Result<TypedBlob>,
+}
+
+fn serialize_component<T: Serialize + 'static>(
+    world: &WorldContainer,
+    entity: Entity,
+) -> Option<serde_json::Result<Value>> {
+    world.get_component::<T>(entity).map(serde_json::to_value)
+}
+
+fn deserialize_component<T: DeserializeOwned + 'static>(
+    value: Value,
+) -> serde_json::Result<TypedBlob> {
+    serde_json::from_value::<T>(value).map(TypedBlob::new)
+}
+
+impl WorldContainer {
+    /// Registers the component `T` as serializable: only registered components are stored in a [`WorldSnapshot`]
+    pub fn register_serializable<T: Serialize + DeserializeOwned + 'static>(&mut self) {
+        let component_id = self.get_or_create_component_id::<T>();
+        self.serializable_components.insert(
+            component_id.name(),
+            SerializableComponent {
+                component_id,
+                serialize: serialize_component::<T>,
+                deserialize: deserialize_component::<T>,
+            },
+        );
+    }
+
+    /// Takes a [`WorldSnapshot`] of all the entities, storing only the serializable components
+    pub fn snapshot(&self) -> Result<WorldSnapshot, SnapshotError> {
+        let mut entities = vec![];
+        for (entity, info) in self.iter_all_entities_sorted() {
+            let mut components = BTreeMap::new();
+            for (name, registration) in &self.serializable_components {
+                if !info.components.contains(&registration.component_id) {
+                    continue;
+                }
+                if let Some(value) = (registration.serialize)(self, entity) {
+                    let value = value.map_err(|error| SnapshotError::Serde {
+                        component: name.to_string(),
+                        error,
+                    })?;
+                    components.insert(name.to_string(), value);
+                }
+            }
+            entities.push(EntitySnapshot { entity, components });
+        }
+        Ok(WorldSnapshot { entities })
+    }
+
+    /// Spawns all the entities stored in the [`WorldSnapshot`], adding their components through the dynamic component path.
+    /// Since the ids stored in the snapshot may be used by other entities, new ids are allocated for each entity:
+    /// the returned map associates each entity in the snapshot to the newly spawned entity
+    pub fn load_snapshot(
+        &mut self,
+        snapshot: WorldSnapshot,
+    ) -> Result<HashMap<Entity, Entity>, SnapshotError> {
+        // Deserialize everything upfront, so that no entity is spawned if the snapshot is invalid
+        let mut components = vec![];
+        for entity in &snapshot.entities {
+            let mut entity_components = vec![];
+            for (name, value) in &entity.components {
+                match self.deserialize_snapshot_component(name, value) {
+                    Ok(blob) => entity_components.push(blob),
+                    Err(error) => {
+                        // The components deserialized so far are dropped with their blobs
+                        return Err(error);
+                    }
+                }
+            }
+            components.push((entity.entity, entity_components));
+        }
+
+        let mut mapping = HashMap::new();
+        for (old_entity, entity_components) in components {
+            let entity = self.new_entity();
+            for component in entity_components {
+                // SAFETY: The typed blob was created by directly taking the typed component
+                unsafe { self.add_component_from_type_id(entity, component) };
+            }
+            mapping.insert(old_entity, entity);
+        }
+        Ok(mapping)
+    }
+
+    fn deserialize_snapshot_component(
+        &self,
+        name: &str,
+        value: &Value,
+    ) -> Result<TypedBlob, SnapshotError> {
+        let registration = self
+            .serializable_components
+            .get(name)
+            .ok_or_else(|| SnapshotError::UnregisteredComponent(name.to_owned()))?;
+        (registration.deserialize)(value.clone()).map_err(|error| SnapshotError::Serde {
+            component: name.to_owned(),
+            error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Query, World};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Name(String);
+
+    struct NotSerializable;
+
+    #[test]
+    fn round_trip() {
+        let mut world = World::new();
+        world.register_serializable::<Position>();
+        world.register_serializable::<Name>();
+
+        let player = world.new_entity();
+        world.add_component(player, Position { x: 1.0, y: 2.0 });
+        world.add_component(player, Name("Player".to_owned()));
+        world.add_component(player, NotSerializable);
+
+        let bullet = world.new_entity();
+        world.add_component(bullet, Position { x: 3.0, y: 4.0 });
+
+        let snapshot = world.snapshot().unwrap();
+        let serialized = serde_json::to_string(&snapshot).unwrap();
+        let snapshot = serde_json::from_str(&serialized).unwrap();
+
+        let mut loaded = World::new();
+        loaded.register_serializable::<Position>();
+        loaded.register_serializable::<Name>();
+        let mapping = loaded.load_snapshot(snapshot).unwrap();
+
+        let player = mapping[&player];
+        let bullet = mapping[&bullet];
+        assert_eq!(
+            loaded.get_component::<Position>(player),
+            Some(&Position { x: 1.0, y: 2.0 })
+        );
+        assert_eq!(
+            loaded.get_component::<Name>(player),
+            Some(&Name("Player".to_owned()))
+        );
+        assert!(loaded.get_component::<NotSerializable>(player).is_none());
+        assert_eq!(
+            loaded.get_component::<Position>(bullet),
+            Some(&Position { x: 3.0, y: 4.0 })
+        );
+        assert!(loaded.get_component::<Name>(bullet).is_none());
+    }
+
+    #[test]
+    fn loaded_entities_are_visible_to_systems() {
+        let mut world = World::new();
+        world.register_serializable::<Position>();
+        let entity = world.new_entity();
+        world.add_component(entity, Position { x: 1.0, y: 1.0 });
+        let snapshot = world.snapshot().unwrap();
+
+        let mut loaded = World::new();
+        loaded.register_serializable::<Position>();
+        loaded.add_system(0, |mut query: Query<&mut Position>| {
+            for position in query.iter_mut() {
+                position.x += 1.0;
+            }
+        });
+        let mapping = loaded.load_snapshot(snapshot).unwrap();
+        loaded.update(0);
+
+        assert_eq!(
+            loaded.get_component::<Position>(mapping[&entity]),
+            Some(&Position { x: 2.0, y: 1.0 })
+        );
+    }
+
+    #[test]
+    fn unregistered_component() {
+        let mut world = World::new();
+        world.register_serializable::<Position>();
+        let entity = world.new_entity();
+        world.add_component(entity, Position { x: 1.0, y: 1.0 });
+        let snapshot = world.snapshot().unwrap();
+
+        let mut loaded = World::new();
+        assert!(loaded.load_snapshot(snapshot).is_err());
+    }
+}
@@ -55,6 +55,29 @@ impl<I: SparseIndex, T> SparseSet<I, T> {
         Self::default()
     }
 
+    /// Creates a new, empty, sparse set with space for `capacity` items, whose indices are lower than `capacity`,
+    /// without reallocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            dense: Vec::with_capacity(capacity),
+            sparse: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Releases the memory that is not used by the items currently in the sparse set: the sparse storage
+    /// only grows to fit the highest index ever inserted, so this is useful after removing high indices
+    pub fn shrink_to_fit(&mut self) {
+        let sparse_len = self
+            .dense
+            .iter()
+            .map(|d| d.index.index() + 1)
+            .max()
+            .unwrap_or(0);
+        self.sparse.truncate(sparse_len);
+        self.sparse.shrink_to_fit();
+        self.dense.shrink_to_fit();
+    }
+
     /// The number of items in this sparse set
     pub fn len(&self) -> usize {
         self.dense.len()
@@ -100,6 +123,24 @@ impl<I: SparseIndex, T> SparseSet<I, T> {
         self.get_key(index).map(|s| &mut self.dense[s].value)
     }
 
+    /// Gets mutable references to the items associated with `a` and `b` at the same time.
+    /// Returns None if `a` and `b` are the same index or if any of the two items doesn't exist
+    pub fn get_disjoint_mut(&mut self, a: I, b: I) -> Option<(&mut T, &mut T)> {
+        let key_a = self.get_key(a.index())?;
+        let key_b = self.get_key(b.index())?;
+        if key_a == key_b {
+            return None;
+        }
+        let (first, second) = (key_a.min(key_b), key_a.max(key_b));
+        let (head, tail) = self.dense.split_at_mut(second);
+        let (value_first, value_second) = (&mut head[first].value, &mut tail[0].value);
+        if key_a < key_b {
+            Some((value_first, value_second))
+        } else {
+            Some((value_second, value_first))
+        }
+    }
+
     /// Tries to remove an item from the sparse set
     /// Returns `true` if the item with index `index` was present
     pub fn remove(&mut self, index: I) -> bool {
@@ -117,11 +158,24 @@ impl<I: SparseIndex, T> SparseSet<I, T> {
         false
     }
 
-    /// Iterates all the item in the sparse set, along with their values
+    /// Iterates all the item in the sparse set, along with their values.
+    /// The iteration order depends on the order of the insertions and removals: when the output must be
+    /// reproducible (e.g when hashing or printing the items) use [`SparseSet::iter_sorted`]
     pub fn iter(&self) -> impl Iterator<Item = (I, &T)> {
         self.dense.iter().map(|d| (d.index, &d.value))
     }
 
+    /// Iterates all the item in the sparse set, along with their values, in ascending index order.
+    /// This is slower than [`SparseSet::iter`], since the items must be sorted before being iterated
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (I, &T)> {
+        let mut keys = (0..self.dense.len()).collect::<Vec<_>>();
+        keys.sort_unstable_by_key(|&key| self.dense[key].index.index());
+        keys.into_iter().map(|key| {
+            let d = &self.dense[key];
+            (d.index, &d.value)
+        })
+    }
+
     /// Iterates mutably all the item in the sparse set, along with their values
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
         self.dense.iter_mut().map(|d| &mut d.value)
@@ -132,6 +186,27 @@ impl<I: SparseIndex, T> SparseSet<I, T> {
         self.dense.clear();
     }
 
+    /// Keeps only the items for which `f` returns true, removing all the others
+    pub fn retain(&mut self, mut f: impl FnMut(&I, &mut T) -> bool) {
+        let mut i = 0;
+        while i < self.dense.len() {
+            let key = &mut self.dense[i];
+            if f(&key.index, &mut key.value) {
+                i += 1;
+            } else {
+                self.dense.swap_remove(i);
+                if let Some(moved) = self.dense.get(i) {
+                    self.sparse[moved.index.index()] = i;
+                }
+            }
+        }
+    }
+
+    /// Removes all items from the sparse set, returning them in an iterator
+    pub fn drain(&mut self) -> impl Iterator<Item = (I, T)> + '_ {
+        self.dense.drain(..).map(|d| (d.index, d.value))
+    }
+
     fn get_key(&self, index: usize) -> Option<usize> {
         if index >= self.sparse.len() {
             return None;
@@ -163,6 +238,30 @@ impl<I: SparseIndex, T> SparseSet<I, T> {
     }
 }
 
+impl<I: SparseIndex, T> std::ops::Index<I> for SparseSet<I, T> {
+    type Output = T;
+
+    /// Gets a reference to the item associated with the given `index`, panicking if it doesn't exist
+    fn index(&self, index: I) -> &T {
+        let index = index.index();
+        match self.get_key(index) {
+            Some(key) => &self.dense[key].value,
+            None => panic!("SparseSet has no item at index {index}"),
+        }
+    }
+}
+
+impl<I: SparseIndex, T> std::ops::IndexMut<I> for SparseSet<I, T> {
+    /// Gets a mutable reference to the item associated with the given `index`, panicking if it doesn't exist
+    fn index_mut(&mut self, index: I) -> &mut T {
+        let index = index.index();
+        match self.get_key(index) {
+            Some(key) => &mut self.dense[key].value,
+            None => panic!("SparseSet has no item at index {index}"),
+        }
+    }
+}
+
 impl<C: std::fmt::Debug, I: SparseIndex + std::fmt::Debug> std::fmt::Debug for SparseKey<I, C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SparseKey")
@@ -231,4 +330,131 @@ mod tests {
 
         assert!(sparse_set.is_empty());
     }
+
+    #[test]
+    fn index() {
+        let mut sparse_set = SparseSet::<usize, String>::new();
+        sparse_set.insert(3, "three".to_owned());
+        assert_eq!(sparse_set[3], "three");
+
+        sparse_set[3].push('!');
+        assert_eq!(sparse_set.get(&3).unwrap(), "three!");
+
+        let result = std::panic::catch_unwind(|| sparse_set[4].len());
+        assert!(result.is_err());
+        sparse_set.remove(3);
+        let result = std::panic::catch_unwind(|| sparse_set[3].len());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut sparse_set = SparseSet::<usize, usize>::with_capacity(16);
+        assert!(sparse_set.dense.capacity() >= 16);
+        assert!(sparse_set.sparse.capacity() >= 16);
+
+        for i in [1, 5, 10_000] {
+            sparse_set.insert(i, i * 2);
+        }
+        sparse_set.remove(10_000);
+        sparse_set.shrink_to_fit();
+        assert_eq!(sparse_set.sparse.len(), 6);
+        assert!(sparse_set.sparse.capacity() < 10_000);
+
+        // The indices past the shrunk storage are still bounds checked
+        assert!(!sparse_set.contains(&10_000));
+        assert_eq!(sparse_set.get(&5), Some(&10));
+        assert!(sparse_set.get_mut(100).is_none());
+        assert!(!sparse_set.remove(100));
+        assert!(sparse_set.insert(10_000, 3));
+        assert_eq!(sparse_set.get(&10_000), Some(&3));
+
+        sparse_set.clear();
+        sparse_set.shrink_to_fit();
+        assert!(sparse_set.sparse.is_empty());
+        assert!(!sparse_set.contains(&1));
+    }
+
+    #[test]
+    fn iter_sorted() {
+        let mut sparse_set = SparseSet::<usize, usize>::new();
+        for i in [7, 3, 9, 1, 5] {
+            sparse_set.insert(i, i * 10);
+        }
+        sparse_set.remove(3);
+        sparse_set.insert(0, 0);
+
+        let sorted = sparse_set.iter_sorted().collect::<Vec<_>>();
+        assert_eq!(
+            sorted,
+            vec![(0, &0), (1, &10), (5, &50), (7, &70), (9, &90)]
+        );
+    }
+
+    #[test]
+    fn retain() {
+        let mut sparse_set = SparseSet::<usize, usize>::new();
+        for i in 0..100 {
+            sparse_set.insert(i, i * 2);
+        }
+
+        sparse_set.retain(|index, value| {
+            *value += 1;
+            index % 3 == 0
+        });
+
+        assert_eq!(sparse_set.len(), 34);
+        for i in 0..100 {
+            if i % 3 == 0 {
+                assert_eq!(sparse_set.get(&i), Some(&(i * 2 + 1)));
+            } else {
+                assert!(!sparse_set.contains(&i));
+            }
+        }
+
+        assert!(sparse_set.remove(3));
+        assert!(sparse_set.insert(4, 0));
+        assert_eq!(sparse_set.len(), 34);
+        assert_eq!(sparse_set.get(&99), Some(&199));
+    }
+
+    #[test]
+    fn get_disjoint_mut() {
+        let mut sparse_set = SparseSet::<usize, Vec<u32>>::new();
+        sparse_set.insert(7, vec![1, 2]);
+        sparse_set.insert(2, vec![]);
+
+        // Move the items from one value to the other, in both orders
+        let (from, to) = sparse_set.get_disjoint_mut(7, 2).unwrap();
+        to.append(from);
+        assert_eq!(sparse_set.get(&2), Some(&vec![1, 2]));
+        let (from, to) = sparse_set.get_disjoint_mut(2, 7).unwrap();
+        to.push(from.pop().unwrap());
+        assert_eq!(sparse_set.get(&7), Some(&vec![2]));
+        assert_eq!(sparse_set.get(&2), Some(&vec![1]));
+
+        assert!(sparse_set.get_disjoint_mut(7, 7).is_none());
+        assert!(sparse_set.get_disjoint_mut(7, 3).is_none());
+        assert!(sparse_set.get_disjoint_mut(100, 2).is_none());
+    }
+
+    #[test]
+    fn drain() {
+        let mut sparse_set = SparseSet::<usize, String>::new();
+        for i in 0..10 {
+            sparse_set.insert(i, i.to_string());
+        }
+
+        let mut drained = sparse_set.drain().collect::<Vec<_>>();
+        drained.sort();
+        assert_eq!(
+            drained,
+            (0..10).map(|i| (i, i.to_string())).collect::<Vec<_>>()
+        );
+        assert!(sparse_set.is_empty());
+        assert!(!sparse_set.contains(&0));
+
+        assert!(sparse_set.insert(5, "five".to_owned()));
+        assert_eq!(sparse_set.get(&5).map(String::as_str), Some("five"));
+    }
 }
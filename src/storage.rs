@@ -1,7 +1,7 @@
-use std::marker::PhantomData;
+use std::{alloc::Layout, marker::PhantomData, sync::atomic::AtomicU64};
 
 use crate::{
-    erased_data_vec::{ErasedVec, UnsafeMutPtr, UnsafePtr},
+    erased_data_vec::{ErasedPtr, ErasedVec, UnsafeMutPtr, UnsafePtr},
     sparse_set::SparseSet,
     ComponentId, Entity,
 };
@@ -42,6 +42,8 @@ pub trait StorageType: Send + Sync + 'static {
     ///   2. The caller must ensure that there is no other access to the component
     unsafe fn erase_entity_component(&mut self, entity: Entity, component_id: ComponentId);
 
+    /// Replaces the component: implementors should store the new component before dropping the old one,
+    /// so that the storage stays valid if the old component panics while being dropped
     /// # Safety
     ///   1. The caller must ensure that the specified entity has the specified component
     ///   2. The caller must ensure that there is no other access to the component
@@ -55,6 +57,7 @@ pub trait StorageType: Send + Sync + 'static {
         self.add_entity_component(entity, component_id, component);
     }
 
+    /// Like [`StorageType::replace_entity_component`], but the new component is stored in the first element of `data`
     /// # Safety
     ///   1. The caller must ensure that the specified entity has the specified component
     ///   2. The caller must ensure that there is no other access to the component
@@ -78,7 +81,7 @@ pub trait StorageType: Send + Sync + 'static {
         &self,
         entity: Entity,
         component_id: ComponentId,
-    ) -> UnsafePtr<T>;
+    ) -> UnsafePtr<'_, T>;
 
     /// # Safety
     ///   1. The caller must ensure that T corresponds to the type of ComponentId
@@ -90,11 +93,37 @@ pub trait StorageType: Send + Sync + 'static {
         &self,
         entity: Entity,
         component_id: ComponentId,
-    ) -> UnsafeMutPtr<T>;
+    ) -> UnsafeMutPtr<'_, T>;
+
+    /// # Safety
+    ///   1. The caller must ensure that the specified entity has the specified component
+    ///   2. The caller must ensure to not write the component while it's being read
+    ///   3. The caller must ensure that, while this or any pointer is alive, no changes to the underlying data structures
+    ///      used by the storage type must be done
+    unsafe fn get_component_erased(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> ErasedPtr<'_>;
+
+    /// Gets the layout of the component stored for `component_id`, if any entity ever had the component
+    fn component_layout(&self, component_id: ComponentId) -> Option<Layout>;
+
+    /// Gets the tick at which the component was last changed
+    /// # Safety
+    ///   1. The caller must ensure that the specified entity has the specified component
+    unsafe fn get_changed_tick(&self, entity: Entity, component_id: ComponentId) -> &AtomicU64;
+
+    /// Gets the tick at which the component was added to the entity
+    /// # Safety
+    ///   1. The caller must ensure that the specified entity has the specified component
+    unsafe fn get_added_tick(&self, entity: Entity, component_id: ComponentId) -> &AtomicU64;
 }
 
 pub struct TableStorage {
     columns: SparseSet<ComponentId, ErasedVec>,
+    changed_ticks: SparseSet<ComponentId, Vec<AtomicU64>>,
+    added_ticks: SparseSet<ComponentId, Vec<AtomicU64>>,
     num_entities: usize,
 }
 
@@ -102,17 +131,37 @@ impl TableStorage {
     pub fn new() -> Self {
         Self {
             columns: Default::default(),
+            changed_ticks: Default::default(),
+            added_ticks: Default::default(),
             num_entities: 0,
         }
     }
+
+    fn ensure_ticks_column(&mut self, component_id: ComponentId) {
+        let num_entities = self.num_entities;
+        self.changed_ticks
+            .get_or_insert(component_id, Vec::new)
+            .resize_with(num_entities, Default::default);
+        self.added_ticks
+            .get_or_insert(component_id, Vec::new)
+            .resize_with(num_entities, Default::default);
+    }
 }
 
 impl StorageType for TableStorage {
-    unsafe fn register_new_entity(&mut self, _entity: Entity) {
-        self.num_entities += 1;
+    unsafe fn register_new_entity(&mut self, entity: Entity) {
+        // Entities spawned with a specific id can skip some indices
+        self.num_entities = self.num_entities.max(entity.0 as usize + 1);
         for column in self.columns.iter_mut() {
             column.ensure_len(self.num_entities);
         }
+        for ticks in self
+            .changed_ticks
+            .iter_mut()
+            .chain(self.added_ticks.iter_mut())
+        {
+            ticks.resize_with(self.num_entities, Default::default);
+        }
     }
 
     unsafe fn erase_entity(&mut self, _entity: Entity) {
@@ -131,6 +180,7 @@ impl StorageType for TableStorage {
             vec
         });
         component_storage.insert_at(entity.0 as usize, component);
+        self.ensure_ticks_column(component_id);
     }
 
     unsafe fn add_entity_component_dynamic(
@@ -145,19 +195,50 @@ impl StorageType for TableStorage {
             vec
         });
         component_storage.copy_from(entity.0 as usize, data, 0);
+        self.ensure_ticks_column(component_id);
     }
 
     unsafe fn erase_entity_component(&mut self, entity: Entity, component_id: ComponentId) {
-        let component_storage = self.columns.get_mut(component_id).unwrap();
+        let component_storage = &mut self.columns[component_id];
         unsafe { component_storage.drop_at(entity.0 as usize) };
     }
 
+    unsafe fn replace_entity_component<T: 'static>(
+        &mut self,
+        entity: Entity,
+        component_id: ComponentId,
+        component: T,
+    ) {
+        let old = self
+            .get_component_mut::<T>(entity, component_id)
+            .0
+            .replace(component);
+        // The new component is already stored, so a panic while dropping the old one leaves the slot valid
+        drop(old);
+    }
+
+    unsafe fn replace_entity_component_dynamic(
+        &mut self,
+        entity: Entity,
+        component_id: ComponentId,
+        data: &ErasedVec,
+    ) {
+        let component_storage = &mut self.columns[component_id];
+        let index = entity.0 as usize;
+        // Move the old component out before storing the new one, see replace_entity_component
+        let mut old = ErasedVec::new(component_storage.layout, component_storage.drop_fn, 1);
+        old.ensure_len(1);
+        old.copy_from(0, component_storage, index);
+        component_storage.copy_from(index, data, 0);
+        old.drop_at(0);
+    }
+
     unsafe fn get_component<T: 'static>(
         &self,
         entity: Entity,
         component_id: ComponentId,
-    ) -> UnsafePtr<T> {
-        let component_storage = self.columns.get(&component_id).unwrap();
+    ) -> UnsafePtr<'_, T> {
+        let component_storage = &self.columns[component_id];
         unsafe {
             let ptr = component_storage.get_ptr(entity.0 as usize).cast::<T>().0;
             UnsafePtr(ptr, PhantomData)
@@ -171,8 +252,8 @@ impl StorageType for TableStorage {
         &self,
         entity: Entity,
         component_id: ComponentId,
-    ) -> UnsafeMutPtr<T> {
-        let component_storage = self.columns.get(&component_id).unwrap();
+    ) -> UnsafeMutPtr<'_, T> {
+        let component_storage = &self.columns[component_id];
         unsafe {
             let ptr = component_storage
                 .get_ptr(entity.0 as usize)
@@ -181,6 +262,26 @@ impl StorageType for TableStorage {
             UnsafeMutPtr(ptr, PhantomData)
         }
     }
+
+    unsafe fn get_component_erased(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> ErasedPtr<'_> {
+        self.columns[component_id].get_ptr(entity.0 as usize)
+    }
+
+    fn component_layout(&self, component_id: ComponentId) -> Option<Layout> {
+        self.columns.get(&component_id).map(|column| column.layout)
+    }
+
+    unsafe fn get_changed_tick(&self, entity: Entity, component_id: ComponentId) -> &AtomicU64 {
+        &self.changed_ticks[component_id][entity.0 as usize]
+    }
+
+    unsafe fn get_added_tick(&self, entity: Entity, component_id: ComponentId) -> &AtomicU64 {
+        &self.added_ticks[component_id][entity.0 as usize]
+    }
 }
 
 unsafe impl Send for TableStorage {}
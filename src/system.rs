@@ -1,11 +1,17 @@
 use crate::{
+    change_detection::{SystemHandle, SystemTicks},
     erased_data_vec::ErasedVec,
-    query::{AccessMode, Query, QueryParam, QueryState},
-    resources::{Res, ResMut, Resource},
+    query::{AccessMode, Query, QueryFilter, QueryParam, QueryState},
+    resources::{Res, ResMut, Resource, ResourceGroup, Resources},
     sparse_set::SparseSet,
-    ComponentId, Entity, EntityInfo, WorldContainer,
+    ComponentId, Scheduler, WorldContainer,
+};
+use std::{
+    any::Any,
+    borrow::Cow,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
 };
-use std::{borrow::Cow, marker::PhantomData};
 
 /// The trait used to identify all the types that can be used as system parameters
 /// (e.g [`Query`], [`Res`]/[`ResMut`]).
@@ -13,36 +19,53 @@ pub trait SystemParam: Sized {
     /// The state used by this parameter
     type State: Send + Sync + 'static;
 
+    /// The parameter created by [`SystemParam::create`], borrowing the world for `'world` and its state for `'state`
+    /// (e.g `Query<'world, 'state, A, F>` for a [`Query`])
+    type Item<'world, 'state>;
+
     /// `true` if the parameter is `&mut WorldContainer`
     const IS_MUT_WORLD: bool;
 
+    /// `true` if the parameter is [`In`], which can only be used by the systems run with [`crate::KecsWorld::run_oneshot_in`]
+    const IS_INPUT: bool = false;
+
     /// This method is used to add this parameter's dependencies to the `components` [`SparseSet`]
     fn add_dependencies(
         store: &mut WorldContainer,
         components: &mut SparseSet<ComponentId, AccessMode>,
     );
 
-    /// This method is used to create the parameter
-    fn create<'world, 'state>(data: &'state Self::State, store: &'world mut WorldContainer) -> Self
-    where
-        'world: 'state;
+    /// This method is used to create the parameter: `ticks` are the ticks of the system's current run
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state>;
 
     /// This method is used to create the parameter's [`Self::State`]
     fn create_initial_state(store: &mut WorldContainer) -> Self::State;
 
-    /// This method is called when the state of an [`Entity`] changes (e.g a new [`Entity`] is created/a new component is added etc...)
-    fn on_entity_changed(
-        state: &mut Self::State,
-        store: &WorldContainer,
-        entity: Entity,
-        info: &EntityInfo,
-    );
-
-    /// This method is called when  an [`Entity`] is destroyed
-    fn on_entity_destroyed(state: &mut Self::State, store: &WorldContainer, entity: Entity);
-
     /// This method should return true if the parameter exclusively accesses a parameter
     fn is_exclusive(world: &mut WorldContainer) -> bool;
+
+    /// This method should return true if the parameter may read any component or resource (e.g `&WorldContainer`),
+    /// so that it can't be used along with a parameter writing to one of them
+    fn reads_all_components() -> bool {
+        false
+    }
+
+    /// This method should return a component or resource that the parameter accesses more than once, when one of the
+    /// accesses is a write (e.g a tuple of [`Res`] and [`ResMut`] of the same resource)
+    fn conflicting_access(_store: &mut WorldContainer) -> Option<ComponentId> {
+        None
+    }
+
+    /// This method is called each time before the system named `system_name` runs
+    fn before_run(_state: &mut Self::State, _store: &WorldContainer, _system_name: &str) {}
+
+    /// This method is called with the input passed to [`crate::KecsWorld::run_oneshot_in`] before the system runs,
+    /// so that the parameter can take it
+    fn set_input(_state: &mut Self::State, _input: &mut Option<Box<dyn Any + Send>>) {}
 }
 
 /// The trait implemented by all systems, which can be added into a [`crate::Scheduler`].
@@ -62,26 +85,122 @@ pub trait System: Send + Sync + 'static {
         world: &mut WorldContainer,
     ) -> SparseSet<ComponentId, AccessMode>;
 
-    /// Called when an entity is destroyed
-    fn on_entity_destroyed(&mut self, store: &WorldContainer, entity: Entity);
-
-    /// Called when the state of an entity changes
-    fn on_entity_changed(&mut self, store: &WorldContainer, entity: Entity, info: &EntityInfo);
-
     /// Must return true if the system should be scheduled on the main thread
     fn is_exclusive(&self, world: &mut WorldContainer) -> bool;
+
+    /// Called after [`System::init`] to pass the input of [`crate::KecsWorld::run_oneshot_in`] to the system.
+    /// By default the input is dropped
+    fn set_input(&mut self, _input: Box<dyn Any + Send>) {}
 }
 
 pub trait IntoSystem<ARGS> {
     type SystemType: System;
     const HAS_MUT_WORLD: bool;
+    const HAS_INPUT: bool;
     const NUM_PARAMS: usize;
 
     fn into_system(self) -> Self::SystemType;
+
+    /// Like [`IntoSystem::into_system`], but the system can have an [`In`] parameter
+    fn into_system_with_input(self) -> Self::SystemType;
+
+    /// Checks that the `fn` can be turned into a [`System`]
+    fn validate() -> Result<(), InvalidSystemError> {
+        validate_params(
+            Self::HAS_MUT_WORLD,
+            Self::HAS_INPUT,
+            Self::NUM_PARAMS,
+            Cow::Borrowed(std::any::type_name::<Self>()),
+        )
+    }
+
+    /// Checks that the parameters of the `fn` don't conflict with each other
+    /// (e.g a [`Res`] and a [`ResMut`] of the same resource)
+    fn validate_access(world: &mut WorldContainer) -> Result<(), InvalidSystemError>;
+}
+
+fn validate_params(
+    has_mut_world: bool,
+    has_input: bool,
+    num_params: usize,
+    system: Cow<'static, str>,
+) -> Result<(), InvalidSystemError> {
+    if has_mut_world && num_params > 1 {
+        return Err(InvalidSystemError::MutWorldNotAlone { system });
+    }
+    if has_input {
+        return Err(InvalidSystemError::InputNotOneshot { system });
+    }
+    Ok(())
+}
+
+/// This error is returned when a `fn` can't be turned into a [`System`], see [`crate::KecsWorld::try_add_system`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidSystemError {
+    /// The system has a parameter of `&mut WorldContainer` along with other parameters
+    MutWorldNotAlone {
+        /// The name of the system
+        system: Cow<'static, str>,
+    },
+    /// The system has an [`In`] parameter, but it's not run with [`crate::KecsWorld::run_oneshot_in`]
+    InputNotOneshot {
+        /// The name of the system
+        system: Cow<'static, str>,
+    },
+    /// The system accesses a component or resource more than once, and one of the accesses is a write
+    ConflictingAccess {
+        /// The name of the system
+        system: Cow<'static, str>,
+        /// The name of the component or resource
+        component: &'static str,
+    },
+}
+
+impl std::fmt::Display for InvalidSystemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidSystemError::MutWorldNotAlone { system } => write!(
+                f,
+                "If a system has a parameter of &mut WorldContainer, then that parameter must be the only parameter (system '{system}')"
+            ),
+            InvalidSystemError::InputNotOneshot { system } => write!(
+                f,
+                "A system with an In<T> parameter can only be run with run_oneshot_in (system '{system}')"
+            ),
+            InvalidSystemError::ConflictingAccess { system, component } => write!(
+                f,
+                "A system can't access {component} more than once if one of the accesses is a write (system '{system}')"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidSystemError {}
+
+/// Implemented by the tuples of up to 16 systems, so that they can be added at once, see [`crate::KecsWorld::add_systems`]
+pub trait SystemTuple<ARGS> {
+    /// Checks that all the systems in the tuple are valid, see [`crate::KecsWorld::try_add_system`]
+    fn validate(world: &mut WorldContainer) -> Result<(), InvalidSystemError>;
+
+    /// Adds all the systems in the tuple to the scheduler, in order, returning their ids
+    fn add_to_scheduler<S: Scheduler>(
+        self,
+        scheduler: &mut S,
+        world: &mut WorldContainer,
+    ) -> Vec<S::SystemId>;
+}
+
+/// Implemented by all the `fn`s returning a value that can be run as a system, see [`crate::KecsWorld::run_oneshot_with`]
+pub trait IntoSystemWithOutput<ARGS, OUT> {
+    /// Turns self into a system that stores its output into `output` each time it runs
+    fn into_system_with_output(self, output: Arc<Mutex<Option<OUT>>>) -> Box<dyn System>;
 }
 
-impl<'qworld, 'qstate, A: QueryParam> SystemParam for Query<'qworld, 'qstate, A> {
+impl<'qworld, 'qstate, A: QueryParam, F: QueryFilter> SystemParam
+    for Query<'qworld, 'qstate, A, F>
+{
     type State = QueryState;
+    type Item<'world, 'state> = Query<'world, 'state, A, F>;
     const IS_MUT_WORLD: bool = false;
 
     fn add_dependencies(
@@ -89,59 +208,178 @@ impl<'qworld, 'qstate, A: QueryParam> SystemParam for Query<'qworld, 'qstate, A>
         components: &mut SparseSet<ComponentId, AccessMode>,
     ) {
         A::compute_component_set(store, components);
+        F::compute_component_set(store, components);
     }
-    fn create<'world, 'state>(data: &'state Self::State, store: &'world mut WorldContainer) -> Self
-    where
-        'world: 'state,
-    {
-        // SAFETY: We know that 'world: 'state, so we should be good to go
-        unsafe { std::mem::transmute(Query::<'_, '_, A>::create_query(data, store.get_mut_ptr())) }
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        // SAFETY: The scheduler ensures that no other system accesses the components of the query in parallel
+        Query::create_query(data, unsafe { store.get_mut_ptr() }, ticks)
     }
 
     fn create_initial_state(store: &mut WorldContainer) -> Self::State {
-        let mut component_set = Default::default();
-        Self::add_dependencies(store, &mut component_set);
+        // The optional parameters are accessed, but not required by the query
+        let mut required_set = Default::default();
+        A::compute_required_set(store, &mut required_set);
+        F::compute_component_set(store, &mut required_set);
 
-        let state = QueryState {
+        let mut state = QueryState {
             query_archetype: store
                 .get_archetype_manager_mut()
-                .archetype_of(&component_set),
+                .archetype_of(&required_set),
             ..Default::default()
         };
+        state.update_archetypes(store.get_archetype_manager());
         state
     }
 
-    fn on_entity_changed(
-        state: &mut Self::State,
-        store: &WorldContainer,
-        entity: Entity,
-        info: &EntityInfo,
-    ) {
+    fn is_exclusive(_world: &mut WorldContainer) -> bool {
+        A::is_exclusive()
+    }
+
+    fn reads_all_components() -> bool {
+        // e.g EntityRef
+        A::is_exclusive()
+    }
+
+    fn before_run(state: &mut Self::State, store: &WorldContainer, system_name: &str) {
         let archetype_manager = store.get_archetype_manager();
-        let system_archetype = archetype_manager
-            .get_archetype(state.query_archetype)
-            .expect("Failed to get system archetype");
-        let entity_archetype = archetype_manager
-            .get_archetype(info.archetype_id)
-            .expect("Failed to get archetype for entity");
-        if entity_archetype.includes_fully(system_archetype) {
-            state.entities.insert(entity);
-        } else {
-            state.entities.remove(&entity);
+        state.update_archetypes(archetype_manager);
+
+        let Some(threshold) = store.empty_query_warning() else {
+            return;
+        };
+        let is_empty = state
+            .archetype_entities(archetype_manager)
+            .all(|entities| entities.is_empty());
+        if !is_empty {
+            state.empty_runs = 0;
+            return;
+        }
+
+        state.empty_runs += 1;
+        // Warn only once each time the query stops matching entities
+        if state.empty_runs == threshold {
+            let archetype = store
+                .get_archetype_manager()
+                .get_archetype(state.query_archetype)
+                .expect("Failed to get system archetype");
+            let mut components: Vec<_> = archetype.components.iter().map(|c| c.name()).collect();
+            components.sort();
+            store.warn(format!(
+                "The system '{system_name}' has a query which matched no entities for {threshold} consecutive runs, \
+                 the query requires the components [{}]",
+                components.join(", ")
+            ));
         }
     }
+}
+
+/// A system parameter containing the input passed to [`crate::KecsWorld::run_oneshot_in`]:
+/// the systems with an `In` parameter can't be added to a scheduler, see [`InvalidSystemError::InputNotOneshot`].
+/// e.g
+/// ```
+/// use kecs::{In, Query, World};
+/// struct Enemy(u32);
+/// let mut world = World::new();
+/// let entity = world.new_entity();
+/// world.add_component(entity, Enemy(10));
+///
+/// world.run_oneshot_in(5u32, |In(min): In<u32>, query: Query<&Enemy>| {
+///     assert_eq!(query.iter().filter(|enemy| enemy.0 >= min).count(), 1);
+/// });
+/// ```
+pub struct In<T>(pub T);
+
+impl<T> In<T> {
+    /// Takes the input
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Send + 'static> SystemParam for In<T> {
+    // Set by set_input and taken by create
+    type State = Mutex<Option<T>>;
+    type Item<'world, 'state> = Self;
+    const IS_MUT_WORLD: bool = false;
+    const IS_INPUT: bool = true;
+
+    fn add_dependencies(
+        _store: &mut WorldContainer,
+        _components: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+    }
+
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        _store: &'world mut WorldContainer,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        let input = data
+            .lock()
+            .unwrap()
+            .take()
+            .expect("In<T> can only be used by a system run with run_oneshot_in");
+        In(input)
+    }
+
+    fn create_initial_state(_store: &mut WorldContainer) -> Self::State {
+        Mutex::new(None)
+    }
 
     fn is_exclusive(_world: &mut WorldContainer) -> bool {
         false
     }
 
-    fn on_entity_destroyed(state: &mut Self::State, _store: &WorldContainer, entity: Entity) {
-        state.entities.remove(&entity);
+    fn set_input(state: &mut Self::State, input: &mut Option<Box<dyn Any + Send>>) {
+        let Some(input) = input.take() else {
+            return;
+        };
+        let input = input.downcast::<T>().unwrap_or_else(|_| {
+            panic!(
+                "The input passed to run_oneshot_in is not a {}",
+                std::any::type_name::<T>()
+            )
+        });
+        *state.get_mut().unwrap() = Some(*input);
+    }
+}
+
+impl<H: 'static> SystemParam for SystemHandle<H> {
+    type State = ComponentId;
+    type Item<'world, 'state> = Self;
+    const IS_MUT_WORLD: bool = false;
+
+    fn add_dependencies(
+        _store: &mut WorldContainer,
+        _components: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+    }
+
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        store.set_system_handle_tick(*data, ticks.this_run);
+        SystemHandle { _ph: PhantomData }
+    }
+
+    fn create_initial_state(store: &mut WorldContainer) -> Self::State {
+        store.register_system_handle::<H>()
+    }
+
+    fn is_exclusive(_world: &mut WorldContainer) -> bool {
+        false
     }
 }
 
 impl SystemParam for &mut WorldContainer {
     type State = ();
+    type Item<'world, 'state> = &'world mut WorldContainer;
     const IS_MUT_WORLD: bool = true;
 
     fn add_dependencies(
@@ -152,25 +390,16 @@ impl SystemParam for &mut WorldContainer {
         components.insert(id_of_world, AccessMode::Write);
     }
 
-    fn create<'world, 'state>(_data: &'state Self::State, store: &'world mut WorldContainer) -> Self
-    where
-        'world: 'state,
-    {
-        unsafe { std::mem::transmute(store) }
+    fn create<'world, 'state>(
+        _data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        store
     }
 
     fn create_initial_state(_store: &mut WorldContainer) -> Self::State {}
 
-    fn on_entity_changed(
-        _state: &mut Self::State,
-        _store: &WorldContainer,
-        _entity: Entity,
-        _info: &EntityInfo,
-    ) {
-    }
-
-    fn on_entity_destroyed(_state: &mut Self::State, _store: &WorldContainer, _entity: Entity) {}
-
     fn is_exclusive(_world: &mut WorldContainer) -> bool {
         true
     }
@@ -178,6 +407,7 @@ impl SystemParam for &mut WorldContainer {
 
 impl SystemParam for &WorldContainer {
     type State = ();
+    type Item<'world, 'state> = &'world WorldContainer;
     const IS_MUT_WORLD: bool = false;
 
     fn add_dependencies(
@@ -188,52 +418,50 @@ impl SystemParam for &WorldContainer {
         components.insert(id_of_world, AccessMode::Read);
     }
 
-    fn create<'world, 'state>(_data: &'state Self::State, store: &'world mut WorldContainer) -> Self
-    where
-        'world: 'state,
-    {
-        unsafe { std::mem::transmute(store) }
+    fn create<'world, 'state>(
+        _data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        store
     }
 
     fn create_initial_state(_store: &mut WorldContainer) -> Self::State {}
 
-    fn on_entity_changed(
-        _state: &mut Self::State,
-        _store: &WorldContainer,
-        _entity: Entity,
-        _info: &EntityInfo,
-    ) {
-    }
-
-    fn on_entity_destroyed(_state: &mut Self::State, _store: &WorldContainer, _entity: Entity) {}
-
     fn is_exclusive(_world: &mut WorldContainer) -> bool {
         false
     }
+
+    fn reads_all_components() -> bool {
+        true
+    }
 }
 
-/// Wrapper type for a `fn` system
-pub struct SystemContainer<F, A> {
+/// Wrapper type for a `fn` system, whose output `OUT` is stored after each run, see [`IntoSystemWithOutput`]
+pub struct SystemContainer<F, A, OUT = ()> {
     _args: PhantomData<A>,
     fun: F,
     system_data: Vec<ErasedVec>,
     fun_name: Cow<'static, str>,
+    last_run: u64,
+    output: Option<Arc<Mutex<Option<OUT>>>>,
 }
 
-impl<F, A> SystemContainer<F, A> {
+impl<F, A, OUT> SystemContainer<F, A, OUT> {
     pub(crate) fn new(fun: F, name: Cow<'static, str>) -> Self {
         Self {
             _args: PhantomData,
             fun,
             system_data: vec![],
             fun_name: name,
+            last_run: 0,
+            output: None,
         }
     }
-}
 
-impl<F, A> Drop for SystemContainer<F, A> {
-    fn drop(&mut self) {
-        for data in &mut self.system_data {
+    // Drops the state of each parameter, so that the system can be initialized again
+    fn clear_system_data(&mut self) {
+        for data in self.system_data.drain(..) {
             unsafe {
                 data.drop_at(0);
             }
@@ -241,10 +469,18 @@ impl<F, A> Drop for SystemContainer<F, A> {
     }
 }
 
+impl<F, A, OUT> Drop for SystemContainer<F, A, OUT> {
+    fn drop(&mut self) {
+        self.clear_system_data();
+    }
+}
+
 macro_rules! impl_system {
     ($($param:ident:$idx:expr)*) => {
-        impl<$($param: SystemParam + Send + Sync + 'static,)* FUN: Fn($($param,)*) + Send + Sync + 'static> System
-            for SystemContainer<FUN, ($($param,)*)>
+        impl<$($param: SystemParam + Send + Sync + 'static,)* OUT: Send + 'static, FUN> System
+            for SystemContainer<FUN, ($($param,)*), OUT>
+        where
+            FUN: for<'world, 'state> Fn($($param::Item<'world, 'state>,)*) -> OUT + Send + Sync + 'static,
         {
 
             #[allow(unused_variables)]
@@ -254,42 +490,33 @@ macro_rules! impl_system {
 
             #[allow(unused_variables)]
             fn init(&mut self, store: &mut WorldContainer) {
+                // The parameters borrow the same world, so they must not conflict even if the scheduler doesn't
+                // need the dependencies
+                self.compute_dependencies(store);
+                // The system may be initialized again, e.g when it's added to another scheduler
+                self.clear_system_data();
                 $(
-                self.system_data.push({
-                    let mut erased = unsafe { ErasedVec::new_typed::<$param::State>(true, 1) };
-                    let data = $param::create_initial_state(store);
-                    unsafe { erased.push_back(data) };
-                    erased
-                });
+                let data = $param::create_initial_state(store);
+                self.system_data.push(unsafe { ErasedVec::from_iter_typed(std::iter::once(data)) });
                 )*
-
-                for (entity, info) in store.iter_all_entities() {
-                    self.on_entity_changed(store, entity, info)
-                }
             }
 
             #[allow(unused_variables)]
             fn run(&mut self, store: &mut WorldContainer) {
-                (self.fun)($($param::create(unsafe {self.system_data[$idx].get::<$param::State>(0) }, store),)*);
-            }
-
-            #[allow(unused_variables)]
-            fn on_entity_changed(&mut self, store: &WorldContainer, entity: Entity, info: &EntityInfo) {
-                {
-                    $(
-                        let state = unsafe { self.system_data[$idx].get_mut::<$param::State>(0) };
-                        $param::on_entity_changed(state, store, entity, info);
-                    )*
-                }
-            }
-
-            #[allow(unused_variables)]
-            fn on_entity_destroyed(&mut self, store: &WorldContainer, entity: Entity) {
-                {
-                    $(
-                        let state = unsafe { self.system_data[$idx].get_mut::<$param::State>(0) };
-                        $param::on_entity_destroyed(state, store, entity);
-                    )*
+                let ticks = SystemTicks {
+                    last_run: self.last_run,
+                    this_run: store.increment_change_tick(),
+                };
+                self.last_run = ticks.this_run;
+                $(
+                    let state = unsafe { self.system_data[$idx].get_mut::<$param::State>(0) };
+                    $param::before_run(state, store, &self.fun_name);
+                )*
+                let store: *mut WorldContainer = store;
+                // SAFETY: The parameters of a system don't conflict with each other, so each of them can borrow the world
+                let output = (self.fun)($($param::create(unsafe {self.system_data[$idx].get::<$param::State>(0) }, unsafe { &mut *store }, ticks),)*);
+                if let Some(sink) = &self.output {
+                    *sink.lock().expect("Failed to store the system output") = Some(output);
                 }
             }
 
@@ -298,12 +525,12 @@ macro_rules! impl_system {
                 #[allow(unused_mut)]
                 let mut deps = Default::default();
                 $(
-                {
-                    let mut param_deps = Default::default();
-                    $param::add_dependencies(world, &mut param_deps);
-                    add_dependencies(param_deps, &mut deps);
+                if let Err(component) = merge_param_dependencies::<$param>(world, &mut deps) {
+                    panic!("{}", InvalidSystemError::ConflictingAccess { system: self.fun_name.clone(), component: component.name() });
                 }
                 )*
+                let reads_all_components = $($param::reads_all_components() ||)* false;
+                check_reads_all_components(reads_all_components, &deps, &self.fun_name);
                 deps
             }
 
@@ -314,24 +541,82 @@ macro_rules! impl_system {
                     $param::is_exclusive(world) ||
                 )* false
             }
+
+            #[allow(unused_variables, unused_mut)]
+            fn set_input(&mut self, input: Box<dyn Any + Send>) {
+                let mut input = Some(input);
+                $(
+                    let state = unsafe { self.system_data[$idx].get_mut::<$param::State>(0) };
+                    $param::set_input(state, &mut input);
+                )*
+            }
         }
 
-        impl<$($param,)* FUN: Fn($($param,)*) + Send + Sync + 'static> IntoSystem<($($param,)*)> for FUN
+        impl<$($param,)* FUN> IntoSystem<($($param,)*)> for FUN
         where
             $($param: SystemParam + Send + Sync + 'static,)*
+            FUN: Fn($($param,)*) + for<'world, 'state> Fn($($param::Item<'world, 'state>,)*) + Send + Sync + 'static,
         {
             const HAS_MUT_WORLD : bool = $( $param::IS_MUT_WORLD || ) * false;
+            const HAS_INPUT : bool = $( $param::IS_INPUT || ) * false;
             const NUM_PARAMS: usize = $(count_params::<$param>() + )* 0;
 
             type SystemType = SystemContainer<FUN, ($($param,)*)>;
 
             fn into_system(self) -> Self::SystemType {
-                if Self::HAS_MUT_WORLD && Self::NUM_PARAMS > 1 {
-                    panic!("If a system has a parameter of &mut WorldContainer, then that parameter must be the only parameter");
+                if let Err(error) = Self::validate() {
+                    panic!("{error}");
                 }
 
                 SystemContainer::new(self, Cow::Borrowed(std::any::type_name::<FUN>()))
             }
+
+            fn into_system_with_input(self) -> Self::SystemType {
+                let name = Cow::Borrowed(std::any::type_name::<FUN>());
+                if let Err(error) = validate_params(Self::HAS_MUT_WORLD, false, Self::NUM_PARAMS, name.clone()) {
+                    panic!("{error}");
+                }
+
+                SystemContainer::new(self, name)
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn validate_access(world: &mut WorldContainer) -> Result<(), InvalidSystemError> {
+                let mut deps = SparseSet::<ComponentId, AccessMode>::default();
+                $(
+                merge_param_dependencies::<$param>(world, &mut deps).map_err(|component| {
+                    InvalidSystemError::ConflictingAccess {
+                        system: Cow::Borrowed(std::any::type_name::<Self>()),
+                        component: component.name(),
+                    }
+                })?;
+                )*
+                Ok(())
+            }
+        }
+
+        impl<$($param,)* OUT: Send + 'static, FUN> IntoSystemWithOutput<($($param,)*), OUT> for FUN
+        where
+            $($param: SystemParam + Send + Sync + 'static,)*
+            FUN: Fn($($param,)*) -> OUT
+                + for<'world, 'state> Fn($($param::Item<'world, 'state>,)*) -> OUT
+                + Send
+                + Sync
+                + 'static,
+        {
+            fn into_system_with_output(self, output: Arc<Mutex<Option<OUT>>>) -> Box<dyn System> {
+                let has_mut_world = $( $param::IS_MUT_WORLD || ) * false;
+                let has_input = $( $param::IS_INPUT || ) * false;
+                let num_params = $(count_params::<$param>() + )* 0;
+                let name = Cow::Borrowed(std::any::type_name::<FUN>());
+                if let Err(error) = validate_params(has_mut_world, has_input, num_params, name.clone()) {
+                    panic!("{error}");
+                }
+
+                let mut system = SystemContainer::<FUN, ($($param,)*), OUT>::new(self, name);
+                system.output = Some(output);
+                Box::new(system)
+            }
         }
     };
 }
@@ -355,28 +640,246 @@ impl_system!(A:0 B:1 C:2 D:3 E:4 F:5 G:6 H:7 I:8 J:9 K:10 L:11 M:12 N:13 O:14);
 impl_system!(A:0 B:1 C:2 D:3 E:4 F:5 G:6 H:7 I:8 J:9 K:10 L:11 M:12 N:13 O:14 P:15);
 impl_system!(A:0 B:1 C:2 D:3 E:4 F:5 G:6 H:7 I:8 J:9 K:10 L:11 M:12 N:13 O:14 P:15 Q:16);
 
+macro_rules! impl_system_tuple {
+    ($($sys:ident:$args:ident)*) => {
+        impl<$($args, $sys: IntoSystem<$args>,)*> SystemTuple<($($args,)*)> for ($($sys,)*) {
+            fn validate(world: &mut WorldContainer) -> Result<(), InvalidSystemError> {
+                $(
+                    $sys::validate()?;
+                    $sys::validate_access(world)?;
+                )*
+                Ok(())
+            }
+
+            #[allow(non_snake_case)]
+            fn add_to_scheduler<S: Scheduler>(
+                self,
+                scheduler: &mut S,
+                world: &mut WorldContainer,
+            ) -> Vec<S::SystemId> {
+                let ($($sys,)*) = self;
+                vec![$(scheduler.add_system(world, $sys),)*]
+            }
+        }
+    };
+}
+
+impl_system_tuple!(A:AA);
+impl_system_tuple!(A:AA B:BA);
+impl_system_tuple!(A:AA B:BA C:CA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA I:IA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA I:IA J:JA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA I:IA J:JA K:KA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA I:IA J:JA K:KA L:LA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA I:IA J:JA K:KA L:LA M:MA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA I:IA J:JA K:KA L:LA M:MA N:NA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA I:IA J:JA K:KA L:LA M:MA N:NA O:OA);
+impl_system_tuple!(A:AA B:BA C:CA D:DA E:EA F:FA G:GA H:HA I:IA J:JA K:KA L:LA M:MA N:NA O:OA P:PA);
+
 #[allow(clippy::extra_unused_type_parameters)]
 const fn count_params<A>() -> usize {
     1
 }
 
-fn add_dependencies(
-    param_deps: SparseSet<ComponentId, AccessMode>,
-    system_deps: &mut SparseSet<ComponentId, AccessMode>,
+macro_rules! impl_system_param_for_tuple {
+    ($($param:ident)*) => {
+        impl<$($param: SystemParam,)*> SystemParam for ($($param,)*) {
+            type State = ($($param::State,)*);
+            type Item<'world, 'state> = ($($param::Item<'world, 'state>,)*);
+            const IS_MUT_WORLD: bool = $($param::IS_MUT_WORLD ||)* false;
+            const IS_INPUT: bool = $($param::IS_INPUT ||)* false;
+
+            fn add_dependencies(
+                store: &mut WorldContainer,
+                components: &mut SparseSet<ComponentId, AccessMode>,
+            ) {
+                $(
+                if let Err(component) = merge_param_dependencies::<$param>(store, components) {
+                    let system = Cow::Borrowed(std::any::type_name::<Self>());
+                    panic!("{}", InvalidSystemError::ConflictingAccess { system, component: component.name() });
+                }
+                )*
+                check_reads_all_components(Self::reads_all_components(), components, std::any::type_name::<Self>());
+            }
+
+            fn conflicting_access(store: &mut WorldContainer) -> Option<ComponentId> {
+                let mut components = Default::default();
+                $(
+                if let Err(component) = merge_param_dependencies::<$param>(store, &mut components) {
+                    return Some(component);
+                }
+                )*
+                None
+            }
+
+            #[allow(non_snake_case)]
+            fn create<'world, 'state>(
+                data: &'state Self::State,
+                store: &'world mut WorldContainer,
+                ticks: SystemTicks,
+            ) -> Self::Item<'world, 'state> {
+                let ($($param,)*) = data;
+                let store: *mut WorldContainer = store;
+                // SAFETY: The parameters of a system don't conflict with each other, so each of them can borrow the world
+                ($($param::create($param, unsafe { &mut *store }, ticks),)*)
+            }
+
+            fn create_initial_state(store: &mut WorldContainer) -> Self::State {
+                // The world would be aliased by the other parameters of the tuple
+                assert!(
+                    !Self::IS_MUT_WORLD,
+                    "&mut WorldContainer can't be part of a tuple of system parameters"
+                );
+                ($($param::create_initial_state(store),)*)
+            }
+
+            fn is_exclusive(world: &mut WorldContainer) -> bool {
+                $($param::is_exclusive(world) ||)* false
+            }
+
+            fn reads_all_components() -> bool {
+                $($param::reads_all_components() ||)* false
+            }
+
+            #[allow(non_snake_case)]
+            fn before_run(state: &mut Self::State, store: &WorldContainer, system_name: &str) {
+                let ($($param,)*) = state;
+                $($param::before_run($param, store, system_name);)*
+            }
+
+            #[allow(non_snake_case)]
+            fn set_input(state: &mut Self::State, input: &mut Option<Box<dyn Any + Send>>) {
+                let ($($param,)*) = state;
+                $($param::set_input($param, input);)*
+            }
+        }
+    };
+}
+
+impl_system_param_for_tuple!(A);
+impl_system_param_for_tuple!(A B);
+impl_system_param_for_tuple!(A B C);
+impl_system_param_for_tuple!(A B C D);
+impl_system_param_for_tuple!(A B C D E);
+impl_system_param_for_tuple!(A B C D E F);
+impl_system_param_for_tuple!(A B C D E F G);
+impl_system_param_for_tuple!(A B C D E F G H);
+
+/// Stores the state of the [`SystemParam`]s `P`, so that they can be created from the [`WorldContainer`] inside an
+/// exclusive system (a system whose only parameter is `&mut WorldContainer`), e.g
+/// ```
+/// use kecs::{Query, Res, Resource, SystemState, World, WorldContainer};
+/// struct Config {
+///     speed: f32,
+/// }
+/// impl Resource for Config {}
+/// struct Position(f32);
+///
+/// fn exclusive(world: &mut WorldContainer) {
+///     let mut state = SystemState::<(Res<Config>, Query<&mut Position>)>::new(world);
+///     let (config, mut query) = state.get(world);
+///     for position in query.iter_mut() {
+///         position.0 += config.speed;
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.add_resource(Config { speed: 2.0 });
+/// let entity = world.new_entity();
+/// world.add_component(entity, Position(1.0));
+/// world.add_system(0, exclusive);
+/// world.update(0);
+/// assert_eq!(world.get_component::<Position>(entity).unwrap().0, 3.0);
+/// ```
+pub struct SystemState<P: SystemParam> {
+    state: P::State,
+    last_run: u64,
+}
+
+impl<P: SystemParam> SystemState<P> {
+    /// Creates the state of the parameters, panicking if they conflict with each other (e.g a query that both reads and
+    /// writes the same component), or if they contain `&mut WorldContainer`.
+    /// The state can be stored and reused across runs, like the state of a system
+    pub fn new(world: &mut WorldContainer) -> Self {
+        assert!(
+            !P::IS_MUT_WORLD,
+            "A SystemState can't create &mut WorldContainer, since the world is passed to SystemState::get"
+        );
+        P::add_dependencies(world, &mut Default::default());
+        let state = P::create_initial_state(world);
+        Self { state, last_run: 0 }
+    }
+
+    /// Creates the parameters, which borrow both the state and the world
+    pub fn get<'world>(
+        &'world mut self,
+        world: &'world mut WorldContainer,
+    ) -> P::Item<'world, 'world> {
+        let ticks = SystemTicks {
+            last_run: self.last_run,
+            this_run: world.increment_change_tick(),
+        };
+        self.last_run = ticks.this_run;
+        P::before_run(&mut self.state, world, std::any::type_name::<P>());
+        P::create(&self.state, world, ticks)
+    }
+}
+
+// A parameter reading any component (e.g Query<EntityRef>) could read the components written by the others
+fn check_reads_all_components(
+    reads_all_components: bool,
+    deps: &SparseSet<ComponentId, AccessMode>,
+    system: &str,
 ) {
+    if !reads_all_components {
+        return;
+    }
+    if let Some((id, _)) = deps
+        .iter()
+        .find(|(_, access)| **access == AccessMode::Write)
+    {
+        panic!(
+            "System {system} reads all the components, but also writes {}",
+            id.name()
+        );
+    }
+}
+
+// Merges the dependencies of the parameter P into the system's, returning the first component or resource
+// accessed by both when one of them writes it
+fn merge_param_dependencies<P: SystemParam>(
+    store: &mut WorldContainer,
+    system_deps: &mut SparseSet<ComponentId, AccessMode>,
+) -> Result<(), ComponentId> {
+    if let Some(component) = P::conflicting_access(store) {
+        return Err(component);
+    }
+    let mut param_deps = SparseSet::default();
+    P::add_dependencies(store, &mut param_deps);
     for (component, access) in param_deps.iter() {
-        if let Some(sys_access) = system_deps.get_mut(component) {
-            if *access == AccessMode::Write {
-                *sys_access = *access;
+        match system_deps.get(&component) {
+            Some(sys_access)
+                if *access == AccessMode::Write || *sys_access == AccessMode::Write =>
+            {
+                return Err(component);
+            }
+            Some(_) => {}
+            None => {
+                system_deps.insert(component, *access);
             }
-        } else {
-            system_deps.insert(component, *access);
         }
     }
+    Ok(())
 }
 
 impl<'rworld, 'res, R: Resource + 'static> SystemParam for Res<'rworld, 'res, R> {
     type State = ();
+    type Item<'world, 'state> = Res<'world, 'world, R>;
     const IS_MUT_WORLD: bool = false;
 
     fn add_dependencies(
@@ -387,34 +890,61 @@ impl<'rworld, 'res, R: Resource + 'static> SystemParam for Res<'rworld, 'res, R>
         components.insert(id, AccessMode::Read);
     }
 
-    fn create<'world, 'state>(_data: &'state Self::State, store: &'world mut WorldContainer) -> Self
-    where
-        'world: 'state,
-    {
+    fn create<'world, 'state>(
+        _data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
         let id = store.get_or_create_component_id::<R>();
         // SAFETY: The scheduler MUST ensure that no system will mutably access this resource in parallel with this access
         unsafe {
             let res = store.send_resources.get_unsafe_ref::<R>(id);
-            let ptr = res.expect("Resource not found!");
+            let ptr =
+                res.unwrap_or_else(|| panic!("Resource {} not found", std::any::type_name::<R>()));
             std::mem::transmute(Res {
                 _ph: PhantomData,
                 _ph_world: PhantomData,
                 ptr,
+                #[cfg(debug_assertions)]
+                _borrow: store.send_resources.borrow(id).unwrap(),
             })
         }
     }
 
     fn create_initial_state(_store: &mut WorldContainer) -> Self::State {}
 
-    fn on_entity_changed(
-        _state: &mut Self::State,
-        _store: &WorldContainer,
-        _entity: Entity,
-        _info: &EntityInfo,
+    fn is_exclusive(_world: &mut WorldContainer) -> bool {
+        false
+    }
+}
+
+impl<'res, T: ResourceGroup> SystemParam for Resources<'res, T> {
+    // The ids of the resources in the group
+    type State = Vec<ComponentId>;
+    type Item<'world, 'state> = Resources<'world, T>;
+    const IS_MUT_WORLD: bool = false;
+
+    fn add_dependencies(
+        store: &mut WorldContainer,
+        components: &mut SparseSet<ComponentId, AccessMode>,
     ) {
+        T::add_dependencies(store, components);
+    }
+
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        _ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        // SAFETY: The scheduler MUST ensure that no system will mutably access these resources in parallel with this access
+        unsafe { Resources::extract(store, data) }
     }
 
-    fn on_entity_destroyed(_state: &mut Self::State, _store: &WorldContainer, _entity: Entity) {}
+    fn create_initial_state(store: &mut WorldContainer) -> Self::State {
+        let mut components = SparseSet::new();
+        T::add_dependencies(store, &mut components);
+        components.iter().map(|(id, _)| id).collect()
+    }
 
     fn is_exclusive(_world: &mut WorldContainer) -> bool {
         false
@@ -423,6 +953,7 @@ impl<'rworld, 'res, R: Resource + 'static> SystemParam for Res<'rworld, 'res, R>
 
 impl<'rworld, 'res, R: Resource + 'static> SystemParam for ResMut<'rworld, 'res, R> {
     type State = ();
+    type Item<'world, 'state> = ResMut<'world, 'world, R>;
     const IS_MUT_WORLD: bool = false;
 
     fn add_dependencies(
@@ -430,39 +961,113 @@ impl<'rworld, 'res, R: Resource + 'static> SystemParam for ResMut<'rworld, 'res,
         components: &mut SparseSet<ComponentId, AccessMode>,
     ) {
         let id = store.get_or_create_component_id::<R>();
-        components.insert(id, AccessMode::Read);
+        components.insert(id, AccessMode::Write);
     }
 
-    fn create<'world, 'state>(_data: &'state Self::State, store: &'world mut WorldContainer) -> Self
-    where
-        'world: 'state,
-    {
+    fn create<'world, 'state>(
+        _data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
         let id = store.get_or_create_component_id::<R>();
         // SAFETY: The scheduler MUST ensure that no other access is performed in parallel with this access
-        unsafe {
-            let res = store.send_resources.get_unsafe_mut_ref::<R>(id);
-            let ptr = res.expect("Resource not found!");
-            std::mem::transmute(ResMut {
-                _ph: PhantomData,
-                _ph_world: PhantomData,
-                ptr,
-            })
-        }
+        unsafe { store.send_resources.get_res_mut::<R>(id, ticks) }
+            .unwrap_or_else(|| panic!("Resource {} not found", std::any::type_name::<R>()))
     }
 
     fn create_initial_state(_store: &mut WorldContainer) -> Self::State {}
 
-    fn on_entity_changed(
-        _state: &mut Self::State,
-        _store: &WorldContainer,
-        _entity: Entity,
-        _info: &EntityInfo,
+    fn is_exclusive(_world: &mut WorldContainer) -> bool {
+        true
+    }
+}
+
+impl<'rworld, 'res, R: Resource + 'static> SystemParam for Option<Res<'rworld, 'res, R>> {
+    type State = ();
+    type Item<'world, 'state> = Option<Res<'world, 'world, R>>;
+    const IS_MUT_WORLD: bool = false;
+
+    fn add_dependencies(
+        store: &mut WorldContainer,
+        components: &mut SparseSet<ComponentId, AccessMode>,
     ) {
+        // The access is registered even if the resource is missing, since it can be added after the system
+        Res::<R>::add_dependencies(store, components);
     }
 
-    fn on_entity_destroyed(_state: &mut Self::State, _store: &WorldContainer, _entity: Entity) {}
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        store
+            .contains_send_resource::<R>()
+            .then(|| Res::create(data, store, ticks))
+    }
+
+    fn create_initial_state(_store: &mut WorldContainer) -> Self::State {}
 
     fn is_exclusive(_world: &mut WorldContainer) -> bool {
-        true
+        false
+    }
+}
+
+impl<'rworld, 'res, R: Resource + 'static> SystemParam for Option<ResMut<'rworld, 'res, R>> {
+    type State = ();
+    type Item<'world, 'state> = Option<ResMut<'world, 'world, R>>;
+    const IS_MUT_WORLD: bool = false;
+
+    fn add_dependencies(
+        store: &mut WorldContainer,
+        components: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+        // The access is registered even if the resource is missing, since it can be added after the system
+        ResMut::<R>::add_dependencies(store, components);
+    }
+
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        store
+            .contains_send_resource::<R>()
+            .then(|| ResMut::create(data, store, ticks))
+    }
+
+    fn create_initial_state(_store: &mut WorldContainer) -> Self::State {}
+
+    fn is_exclusive(world: &mut WorldContainer) -> bool {
+        ResMut::<R>::is_exclusive(world)
+    }
+}
+
+impl<'res, T: ResourceGroup> SystemParam for Option<Resources<'res, T>> {
+    type State = Vec<ComponentId>;
+    type Item<'world, 'state> = Option<Resources<'world, T>>;
+    const IS_MUT_WORLD: bool = false;
+
+    fn add_dependencies(
+        store: &mut WorldContainer,
+        components: &mut SparseSet<ComponentId, AccessMode>,
+    ) {
+        // The accesses are registered even if some resource is missing, since it can be added after the system
+        Resources::<T>::add_dependencies(store, components);
+    }
+
+    fn create<'world, 'state>(
+        data: &'state Self::State,
+        store: &'world mut WorldContainer,
+        ticks: SystemTicks,
+    ) -> Self::Item<'world, 'state> {
+        T::contains(store).then(|| Resources::create(data, store, ticks))
+    }
+
+    fn create_initial_state(store: &mut WorldContainer) -> Self::State {
+        Resources::<T>::create_initial_state(store)
+    }
+
+    fn is_exclusive(_world: &mut WorldContainer) -> bool {
+        false
     }
 }
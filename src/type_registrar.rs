@@ -33,6 +33,17 @@ impl TypeRegistrar {
         self.registrations.get(&TypeId::of::<T>()).cloned()
     }
 
+    pub(crate) fn get_maybe_from_type_id(&self, blob_ty_id: TypeId) -> Option<UniqueTypeId> {
+        self.registrations.get(&blob_ty_id).cloned()
+    }
+
+    // Iterates all the registrations in the order the types were registered
+    pub(crate) fn iter(&self) -> impl Iterator<Item = UniqueTypeId> {
+        let mut registrations = self.registrations.values().copied().collect::<Vec<_>>();
+        registrations.sort();
+        registrations.into_iter()
+    }
+
     pub(crate) fn get_from_type_id(
         &mut self,
         blob_ty_id: TypeId,
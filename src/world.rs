@@ -1,12 +1,16 @@
-use std::any::TypeId;
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
 
 use crate::commands::{CommandType, Commands, CommandsReceiver, TypedBlob};
+use crate::{entity_manager::EntityAllocator, ComponentId, EntityInfo};
 use crate::{
-    system::IntoSystem, Entity, GraphScheduler, Resource, Scheduler, System, WorldContainer,
+    system::{IntoSystem, IntoSystemWithOutput, SystemTuple},
+    Bundle, Entity, EntityMut, EntityScope, GraphScheduler, InvalidSystemError, ResMut, Resource,
+    Scheduler, System, WorldContainer,
 };
-use crate::{ComponentId, EntityInfo};
 
 /// The [`KecsWorld`] is a wrapper around a [`Scheduler`] and the [`WorldContainer`] it acts on
 pub struct KecsWorld<S: Scheduler = GraphScheduler> {
@@ -45,28 +49,172 @@ impl<S: Scheduler> KecsWorld<S> {
         }
     }
 
+    /// Creates a new [`KecsWorld`] that allocates the entity indices starting from `start`, e.g so that the entities
+    /// spawned by a client don't collide with the ones replicated from a server through [`KecsWorld::get_or_spawn`].
+    /// The destroyed entities below `start` are never recycled. Since the components are stored by entity index,
+    /// `start` should not be much larger than the number of entities in the world
+    pub fn new_with_entity_range(start: u32) -> Self {
+        let mut world = Self::new();
+        world.container.entity_manager = EntityAllocator::with_id_range(start);
+        world
+    }
+
     /// Creates a new entity
     pub fn new_entity(&mut self) -> Entity {
         self.container.new_entity()
     }
 
-    /// Destroys an entity, along with all of its components
+    /// Destroys an entity, along with all of its components.
+    /// The entity is removed from the [`crate::Children`] of its parent, and its children lose their [`crate::Parent`]
     pub fn destroy_entity(&mut self, entity: Entity) {
         self.container.remove_entity(entity);
-        self.update_systems(entity);
+    }
+
+    /// Destroys an entity along with all of its descendants, walking their [`crate::Children`] depth-first.
+    /// The entity is also removed from the [`crate::Children`] of its [`crate::Parent`]
+    pub fn despawn_recursive(&mut self, entity: Entity) {
+        for descendant in self.container.collect_descendants(entity) {
+            self.destroy_entity(descendant);
+        }
+    }
+
+    /// Makes `parent` the [`crate::Parent`] of `child`, see [`WorldContainer::set_parent`]
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) {
+        self.container.set_parent(child, parent);
+    }
+
+    /// Returns an iterator that destroys each entity of the world as it is iterated, yielding its id.
+    /// The entities that exist when this method is called are drained: if the iterator is dropped early,
+    /// the entities that were not yielded are left in the world
+    pub fn drain_entities(&mut self) -> impl Iterator<Item = Entity> + '_ {
+        // Collect the ids first, since destroying an entity changes the set of entities
+        let entities: Vec<_> = self
+            .container
+            .iter_all_entities()
+            .map(|(entity, _)| entity)
+            .collect();
+        entities
+            .into_iter()
+            .inspect(move |&entity| self.destroy_entity(entity))
     }
 
     /// Adds a component to the [`Entity`]: if the entity already had the component, it is overwritten
     pub fn add_component<T: 'static>(&mut self, entity: Entity, component: T) {
         self.container.add_component(entity, component);
-        self.update_systems(entity);
+    }
+
+    /// Adds all the components of a [`Bundle`] to an entity, replacing any old ones of the same type
+    pub fn add_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        self.container.add_bundle(entity, bundle);
+    }
+
+    /// Edits an [`Entity`] through an [`EntityScope`]: all the components are added/removed at once, so that the entity's
+    /// archetype is recomputed only once
+    /// e.g
+    /// ```
+    /// use kecs::World;
+    /// struct Position([f32; 2]);
+    /// struct Velocity([f32; 2]);
+    /// struct Frozen;
+    /// let mut world = World::new();
+    /// let entity = world.new_entity();
+    /// world.add_component(entity, Frozen);
+    ///
+    /// world.entity_scope(entity, |scope| {
+    ///     scope
+    ///         .add(Position([0.0; 2]))
+    ///         .add(Velocity([1.0, 0.0]))
+    ///         .remove::<Frozen>();
+    /// });
+    /// ```
+    pub fn entity_scope(&mut self, entity: Entity, fun: impl FnOnce(&mut EntityScope)) {
+        self.container.entity_scope(entity, fun);
+    }
+
+    /// Gets an [`EntityMut`] to edit the entity, if it exists: the entity's archetype is recomputed only once,
+    /// when the [`EntityMut`] is dropped
+    /// e.g
+    /// ```
+    /// use kecs::World;
+    /// struct Position([f32; 2]);
+    /// struct Velocity([f32; 2]);
+    /// let mut world = World::new();
+    /// let entity = world.new_entity();
+    ///
+    /// let mut entity_mut = world.entity_mut(entity).unwrap();
+    /// entity_mut.insert(Position([0.0; 2])).insert(Velocity([1.0, 0.0]));
+    /// assert_eq!(entity_mut.get::<Velocity>().unwrap().0, [1.0, 0.0]);
+    /// ```
+    pub fn entity_mut(&mut self, entity: Entity) -> Option<EntityMut<'_>> {
+        self.container.entity_mut(entity)
+    }
+
+    /// Enables the warning for the queries matching no entities, see [`WorldContainer::set_empty_query_warning`]
+    pub fn set_empty_query_warning(&mut self, runs: Option<usize>) {
+        self.container.set_empty_query_warning(runs);
+    }
+
+    /// Sets the function receiving the diagnostic warnings, see [`WorldContainer::set_warning_handler`]
+    pub fn set_warning_handler(&mut self, handler: impl Fn(&str) + Send + Sync + 'static) {
+        self.container.set_warning_handler(handler);
+    }
+
+    /// Registers `R` as a component required by `A`, see [`WorldContainer::register_required`]
+    /// e.g
+    /// ```
+    /// use kecs::World;
+    /// #[derive(Default)]
+    /// struct Transform([f32; 2]);
+    /// struct Velocity([f32; 2]);
+    /// let mut world = World::new();
+    /// world.register_required::<Velocity, _>(Transform::default);
+    ///
+    /// let entity = world.new_entity();
+    /// world.add_component(entity, Velocity([1.0, 0.0]));
+    /// assert!(world.get_component::<Transform>(entity).is_some());
+    /// ```
+    pub fn register_required<A: 'static, R: 'static>(
+        &mut self,
+        constructor: impl Fn() -> R + Send + Sync + 'static,
+    ) {
+        self.container.register_required::<A, R>(constructor);
+    }
+
+    /// Registers `T` as a component that can be copied by [`KecsWorld::clone_entity`]
+    pub fn register_clonable<T: Clone + 'static>(&mut self) {
+        self.container.register_clonable::<T>();
+    }
+
+    /// Spawns a copy of `src` with its clonable components, see [`WorldContainer::clone_entity`]
+    pub fn clone_entity(&mut self, src: Entity) -> Option<Entity> {
+        self.container.clone_entity(src)
+    }
+
+    /// Registers a hook called each time the component `T` is added to an entity, see [`WorldContainer::register_on_add`]
+    pub fn register_on_add<T: 'static>(
+        &mut self,
+        hook: impl Fn(&mut WorldContainer, Entity) + Send + Sync + 'static,
+    ) {
+        self.container.register_on_add::<T>(hook);
+    }
+
+    /// Registers a hook called each time the component `T` is removed from an entity, see [`WorldContainer::register_on_remove`]
+    pub fn register_on_remove<T: 'static>(
+        &mut self,
+        hook: impl Fn(&mut WorldContainer, Entity) + Send + Sync + 'static,
+    ) {
+        self.container.register_on_remove::<T>(hook);
     }
 
     /// Removes a Component from the [`Entity`], if it has one
     pub fn remove_component<T: 'static>(&mut self, entity: Entity) {
         self.container.remove_component::<T>(entity);
+    }
 
-        self.update_systems(entity);
+    /// Removes the component `T` from all the entities, returning how many entities lost the component,
+    /// see [`WorldContainer::remove_component_from_all`]
+    pub fn remove_component_from_all<T: 'static>(&mut self) -> usize {
+        self.container.remove_component_from_all::<T>()
     }
 
     /// Gets a reference to the Component from the [`Entity`] if it has one
@@ -89,6 +237,17 @@ impl<S: Scheduler> KecsWorld<S> {
         self.container.add_non_send_resource::<T>(resource);
     }
 
+    /// Adds the Send resource `T::default()`, only if the resource doesn't exist yet: unlike
+    /// [`KecsWorld::add_resource`], an existing resource is never overwritten
+    pub fn init_resource<T: 'static + Resource + Send + Sync + Default>(&mut self) {
+        self.container.init_resource::<T>();
+    }
+
+    /// Adds the Non-Send resource `T::default()`, only if the resource doesn't exist yet
+    pub fn init_non_send_resource<T: 'static + Resource + Default>(&mut self) {
+        self.container.init_non_send_resource::<T>();
+    }
+
     /// Gets a reference to the resource, if it exists
     pub fn get_resource<T: 'static + Resource>(&self) -> Option<&T> {
         self.container.get_resource()
@@ -99,44 +258,243 @@ impl<S: Scheduler> KecsWorld<S> {
         self.container.get_resource_mut()
     }
 
-    /// Adds a system to the world, that will then be scheduled according to the [`crate::Scheduler`]
+    /// Gets mutable access to the resource, marking it as changed only when it's written,
+    /// see [`WorldContainer::get_resource_mut_tracked`]
+    pub fn get_resource_mut_tracked<T: 'static + Resource>(&mut self) -> Option<ResMut<'_, '_, T>> {
+        self.container.get_resource_mut_tracked()
+    }
+
+    /// Gets a reference to the resource, panicking if it doesn't exist
+    pub fn resource<T: 'static + Resource>(&self) -> &T {
+        self.container.resource()
+    }
+
+    /// Gets a mutable reference to the resource, panicking if it doesn't exist
+    pub fn resource_mut<T: 'static + Resource>(&mut self) -> &mut T {
+        self.container.resource_mut()
+    }
+
+    /// Removes a resource, returning it if it existed
+    pub fn remove_resource<T: 'static + Resource>(&mut self) -> Option<T> {
+        self.container.remove_resource()
+    }
+
+    /// Adds a system to the world, that will then be scheduled according to the [`crate::Scheduler`].
+    /// Panics if the system is not valid, see [`KecsWorld::try_add_system`]
     pub fn add_system<ARGS, SYS: IntoSystem<ARGS>>(
         &mut self,
         label: impl IntoLabel,
         system: SYS,
     ) -> S::SystemId {
-        self.schedulers
+        self.try_add_system(label, system)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Adds all the systems in the tuple to the world with the same [`Label`], in order, returning their ids.
+    /// Panics if any of the systems is not valid: in that case, no system is added
+    /// e.g
+    /// ```
+    /// use kecs::{Query, World};
+    /// struct Position(f32);
+    /// struct Velocity(f32);
+    ///
+    /// fn move_player(mut query: Query<(&mut Position, &Velocity)>) {
+    ///     for (position, velocity) in query.iter_mut() {
+    ///         position.0 += velocity.0;
+    ///     }
+    /// }
+    /// fn apply_gravity(mut query: Query<&mut Velocity>) {
+    ///     for velocity in query.iter_mut() {
+    ///         velocity.0 -= 9.8;
+    ///     }
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world.add_systems("update", (move_player, apply_gravity));
+    /// assert_eq!(world.system_count("update"), 2);
+    /// ```
+    pub fn add_systems<ARGS, SYS: SystemTuple<ARGS>>(
+        &mut self,
+        label: impl IntoLabel,
+        systems: SYS,
+    ) -> Vec<S::SystemId> {
+        SYS::validate(&mut self.container).unwrap_or_else(|error| panic!("{error}"));
+        systems.add_to_scheduler(
+            self.schedulers.entry(label.into_label()).or_default(),
+            &mut self.container,
+        )
+    }
+
+    /// Adds a system to the world, returning an error if the system is not valid
+    /// (e.g it has a parameter of `&mut WorldContainer` along with other parameters, or a [`crate::Res`] and a
+    /// [`crate::ResMut`] of the same resource)
+    pub fn try_add_system<ARGS, SYS: IntoSystem<ARGS>>(
+        &mut self,
+        label: impl IntoLabel,
+        system: SYS,
+    ) -> Result<S::SystemId, InvalidSystemError> {
+        SYS::validate()?;
+        SYS::validate_access(&mut self.container)?;
+        Ok(self
+            .schedulers
             .entry(label.into_label())
             .or_default()
-            .add_system(&mut self.container, system)
+            .add_system(&mut self.container, system))
+    }
+
+    /// Removes a system added with the given [`Label`], returning true if the system existed
+    pub fn remove_system(&mut self, label: impl IntoLabel, id: S::SystemId) -> bool {
+        self.schedulers
+            .get_mut(&label.into_label())
+            .is_some_and(|scheduler| scheduler.remove_system(id))
+    }
+
+    /// Runs a system added with the given [`Label`] immediately, outside of the schedule, keeping the system's state.
+    /// Returns true if the system existed
+    pub fn run_system(&mut self, label: impl IntoLabel, id: S::SystemId) -> bool {
+        self.schedulers
+            .get_mut(&label.into_label())
+            .is_some_and(|scheduler| scheduler.run_system(&mut self.container, id))
     }
 
     /// Runs a system exclusively
     pub fn run_oneshot<ARGS, SYS: IntoSystem<ARGS>>(&mut self, system: SYS) {
         let mut system = system.into_system();
-        system.init(&mut self.container);
+        self.run_system_once(&mut system, None);
+    }
 
-        self.container.iter_all_entities().for_each(|(e, info)| {
-            system.on_entity_changed(&self.container, e, info);
-        });
+    /// Runs a system exclusively, returning the value returned by the system
+    /// e.g
+    /// ```
+    /// use kecs::{World, Query};
+    /// struct Enemy;
+    /// let mut world = World::new();
+    /// let entity = world.new_entity();
+    /// world.add_component(entity, Enemy);
+    ///
+    /// let count = world.run_oneshot_with(|query: Query<&Enemy>| query.iter().count());
+    /// assert_eq!(count, 1);
+    /// ```
+    pub fn run_oneshot_with<ARGS, OUT, SYS: IntoSystemWithOutput<ARGS, OUT>>(
+        &mut self,
+        system: SYS,
+    ) -> OUT {
+        let output = Arc::new(Mutex::new(None));
+        let mut system = system.into_system_with_output(output.clone());
+        self.run_system_once(system.as_mut(), None);
+        let output = output
+            .lock()
+            .expect("Failed to get the system output")
+            .take();
+        output.expect("The system did not produce an output")
+    }
 
-        system.run(&mut self.container);
+    /// Runs a system exclusively, passing `input` to its [`crate::In`] parameter
+    /// e.g
+    /// ```
+    /// use kecs::{In, World};
+    /// let mut world = World::new();
+    /// world.run_oneshot_in(42u32, |In(n): In<u32>| assert_eq!(n, 42));
+    /// ```
+    pub fn run_oneshot_in<T: Send + 'static, ARGS, SYS: IntoSystem<ARGS>>(
+        &mut self,
+        input: T,
+        system: SYS,
+    ) {
+        let mut system = system.into_system_with_input();
+        // The input is owned by the system, so it's dropped with it if the system has no In parameter or panics
+        self.run_system_once(&mut system, Some(Box::new(input)));
     }
 
-    /// Executes the queued [`Commands`] and runs all the scheduled [`crate::System`] within a [`Label`]
+    /// Executes the queued [`Commands`] and runs all the scheduled [`crate::System`] within a [`Label`].
+    /// The commands are always flushed before running the systems, see [`KecsWorld::flush`]
     pub fn update(&mut self, label: impl IntoLabel) {
+        self.flush();
+        let label = label.into_label();
+        // The scheduler is taken out of the world while it runs, so that it can flush the commands between its systems
+        let mut scheduler = self.schedulers.remove(&label).unwrap_or_default();
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            scheduler
+                .execute_with_flush(&mut RunningScheduler { world: self }, &mut |_, running| {
+                    running.world.flush()
+                })
+        }));
+        self.schedulers.insert(label, scheduler);
+        if let Err(panic) = result {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    /// Runs the systems of each [`Label`] in sequence, like calling [`KecsWorld::update`] once per label.
+    /// The commands are flushed before each stage, so e.g the entities spawned by the systems of a stage
+    /// are visible to the systems of the next stages. The commands queued by the last stage are flushed
+    /// by the next update
+    /// e.g
+    /// ```
+    /// use kecs::{Commands, Query, World};
+    /// struct Enemy;
+    /// let mut world = World::new();
+    /// world.add_system("pre_update", |mut commands: Commands| {
+    ///     let mut builder = commands.spawn_entity();
+    ///     builder.with_component(Enemy);
+    ///     builder.build();
+    /// });
+    /// world.add_system("update", |query: Query<&Enemy>| {
+    ///     assert_eq!(query.iter().count(), 1);
+    /// });
+    /// world.update_stages(&["pre_update", "update"]);
+    /// ```
+    pub fn update_stages(&mut self, labels: &[impl IntoLabel + Hash]) {
+        for label in labels {
+            self.update(label);
+        }
+    }
+
+    /// Executes the queued [`Commands`] without running any [`crate::System`]
+    /// e.g
+    /// ```
+    /// use kecs::World;
+    /// struct Enemy;
+    /// let mut world = World::new();
+    /// let mut commands = world.commands();
+    /// let mut builder = commands.spawn_entity();
+    /// builder.with_component(Enemy);
+    /// let entity = builder.build();
+    /// assert!(!world.contains(entity));
+    ///
+    /// world.flush();
+    /// assert!(world.contains(entity));
+    /// ```
+    pub fn flush(&mut self) {
         self.execute_commands();
-        self.schedulers
-            .entry(label.into_label())
-            .or_default()
-            .execute(&mut self.container);
+    }
+
+    /// Returns the number of queued [`Commands`] that were not executed yet, see [`KecsWorld::flush`]
+    pub fn pending_command_count(&self) -> usize {
+        self.commands_receiver.len()
+    }
+
+    /// Panics in debug builds if there are queued [`Commands`] that were not executed yet: this is useful in tests,
+    /// to catch assertions done before the commands are applied by [`KecsWorld::flush`] or [`KecsWorld::update`]
+    pub fn debug_assert_no_pending_commands(&self) {
+        debug_assert_eq!(
+            self.pending_command_count(),
+            0,
+            "There are pending commands, call flush() or update() to execute them"
+        );
     }
 
     /// Creates the [`Commands`] for this World
-    pub fn commands(&self) -> Commands {
+    pub fn commands(&self) -> Commands<'_> {
         self.container.commands()
     }
 
+    /// Installs `scheduler` for the given [`Label`], e.g to use a [`crate::GraphScheduler::with_thread_pool`]:
+    /// this should be done before adding the systems, since the replaced scheduler is returned along with its systems
+    pub fn set_scheduler(&mut self, label: impl IntoLabel, scheduler: S) -> Option<S> {
+        self.schedulers.insert(label.into_label(), scheduler)
+    }
+
     /// Gets a reference to this world's [`Scheduler`]
     pub fn scheduler(&self, label: impl IntoLabel) -> &S {
         self.schedulers
@@ -144,15 +502,93 @@ impl<S: Scheduler> KecsWorld<S> {
             .expect("Failed to find systems with this label")
     }
 
+    /// Gets a mutable reference to the scheduler used by the given [`Label`], e.g to add orderings
+    /// to a [`crate::LinearScheduler`]
+    pub fn scheduler_mut(&mut self, label: impl IntoLabel) -> &mut S {
+        self.schedulers
+            .get_mut(&label.into_label())
+            .expect("Failed to find systems with this label")
+    }
+
+    /// Returns the number of systems added with the given [`Label`]
+    pub fn system_count(&self, label: impl IntoLabel) -> usize {
+        self.schedulers
+            .get(&label.into_label())
+            .map_or(0, |scheduler| scheduler.len())
+    }
+
+    /// Returns the number of systems added across all the [`Label`]s
+    pub fn total_system_count(&self) -> usize {
+        self.schedulers
+            .values()
+            .map(|scheduler| scheduler.len())
+            .sum()
+    }
+
     /// Gets the ComponentId for the type T, creating it if it does not exists
     pub fn get_type_registration<T: 'static>(&mut self) -> ComponentId {
         self.container.get_or_create_component_id::<T>()
     }
 
+    /// Registers the component `T` ahead of time, without adding it to any entity, returning its [`ComponentId`]
+    pub fn register_component<T: 'static>(&mut self) -> ComponentId {
+        self.get_type_registration::<T>()
+    }
+
+    /// Registers the resource `R` ahead of time, returning its [`ComponentId`]. The systems taking [`crate::Res`]/[`crate::ResMut`]
+    /// of `R` can be added before the resource, but the resource must be added before they run
+    pub fn register_resource<R: Resource + 'static>(&mut self) -> ComponentId {
+        self.get_type_registration::<R>()
+    }
+
+    /// Returns true if the entity is alive, see [`WorldContainer::contains`]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.container.contains(entity)
+    }
+
+    /// Returns the number of components of the [`Entity`], see [`WorldContainer::component_count`]
+    pub fn component_count(&self, entity: Entity) -> usize {
+        self.container.component_count(entity)
+    }
+
+    /// Lists the components of the [`Entity`] with their type names, see [`WorldContainer::components_of`]
+    pub fn components_of(&self, entity: Entity) -> Option<Vec<(ComponentId, &'static str)>> {
+        self.container.components_of(entity)
+    }
+
+    /// Ensures that an entity with the exact id of `entity` exists, returning true if it was spawned,
+    /// see [`WorldContainer::get_or_spawn`]
+    pub fn get_or_spawn(&mut self, entity: Entity) -> bool {
+        self.container.get_or_spawn(entity)
+    }
+
     /// Gets the given entity's info, if it exists
     pub fn get_entity_info(&self, id: Entity) -> Option<EntityInfo> {
         self.container.entity_manager.entity_info(id).cloned()
     }
+
+    /// Registers the component `T` as serializable, see [`WorldContainer::register_serializable`]
+    #[cfg(feature = "serde")]
+    pub fn register_serializable<T: serde::Serialize + serde::de::DeserializeOwned + 'static>(
+        &mut self,
+    ) {
+        self.container.register_serializable::<T>();
+    }
+
+    /// Takes a [`crate::WorldSnapshot`] of the world, see [`WorldContainer::snapshot`]
+    #[cfg(feature = "serde")]
+    pub fn snapshot(&self) -> Result<crate::WorldSnapshot, crate::SnapshotError> {
+        self.container.snapshot()
+    }
+
+    /// Spawns the entities stored in a [`crate::WorldSnapshot`], see [`WorldContainer::load_snapshot`]
+    #[cfg(feature = "serde")]
+    pub fn load_snapshot(
+        &mut self,
+        snapshot: crate::WorldSnapshot,
+    ) -> Result<HashMap<Entity, Entity>, crate::SnapshotError> {
+        self.container.load_snapshot(snapshot)
+    }
 }
 
 impl<S: Scheduler> Drop for KecsWorld<S> {
@@ -164,13 +600,13 @@ impl<S: Scheduler> Drop for KecsWorld<S> {
 
 impl<S: Scheduler> KecsWorld<S> {
     fn execute_commands(&mut self) {
-        while let Some(command_type) = self.commands_receiver.try_get() {
+        for command_type in self.commands_receiver.drain() {
             match command_type {
                 CommandType::NewEntity { entity, components } => {
                     self.spawn_new_entity(entity, components);
                 }
                 CommandType::AddComponent { entity, component } => {
-                    self.add_component_dynamic(entity, component, true);
+                    self.add_component_dynamic(entity, component);
                 }
                 CommandType::RemoveComponent {
                     entity,
@@ -182,7 +618,16 @@ impl<S: Scheduler> KecsWorld<S> {
                 CommandType::AddResource { resource, send } => {
                     self.add_resource_dynamic(resource, send);
                 }
+                CommandType::RemoveResource { component_ty } => {
+                    self.container.remove_resource_dynamic(component_ty);
+                }
                 CommandType::DestroyEntity { entity } => self.destroy_entity(entity),
+                CommandType::InsertOrSpawnBatch { entities } => {
+                    for (entity, components) in entities {
+                        self.insert_or_spawn(entity, components);
+                    }
+                }
+                CommandType::Custom(command) => command(&mut self.container),
             }
         }
     }
@@ -192,19 +637,28 @@ impl<S: Scheduler> KecsWorld<S> {
         unsafe { self.container.new_entity_with_id(entity) }
 
         for (_, component) in components {
-            self.add_component_dynamic(entity, component, false);
+            self.add_component_dynamic(entity, component);
         }
+    }
 
-        self.update_systems(entity);
+    fn insert_or_spawn(&mut self, entity: Entity, components: HashMap<TypeId, TypedBlob>) {
+        if self.container.is_stale(entity) {
+            // The components are dropped with their blobs
+            self.container.warn(format!(
+                "Tried to insert or spawn entity {entity:?}, which is older than the live entity using its index: the components were dropped"
+            ));
+            return;
+        }
+        self.get_or_spawn(entity);
+
+        for (_, component) in components {
+            self.add_component_dynamic(entity, component);
+        }
     }
 
-    fn add_component_dynamic(&mut self, entity: Entity, component: TypedBlob, update_entity: bool) {
+    fn add_component_dynamic(&mut self, entity: Entity, component: TypedBlob) {
         // SAFETY: The typed blob was created by directly taking the typed component
         unsafe { self.container.add_component_from_type_id(entity, component) };
-
-        if update_entity {
-            self.update_systems(entity);
-        }
     }
 
     fn remove_component_dynamic(
@@ -215,8 +669,6 @@ impl<S: Scheduler> KecsWorld<S> {
     ) {
         self.container
             .remove_component_from_type_id(entity, component_ty, type_name);
-
-        self.update_systems(entity);
     }
 
     fn add_resource_dynamic(&mut self, resource: TypedBlob, send: bool) {
@@ -241,9 +693,23 @@ impl<S: Scheduler> Default for KecsWorld<S> {
 }
 
 impl<S: Scheduler> KecsWorld<S> {
-    fn update_systems(&mut self, entity: Entity) {
-        self.schedulers.values_mut().for_each(|s| {
-            s.on_entity_updated(&mut self.container, entity);
-        });
+    fn run_system_once(&mut self, system: &mut dyn System, input: Option<Box<dyn Any + Send>>) {
+        system.init(&mut self.container);
+        if let Some(input) = input {
+            system.set_input(input);
+        }
+        system.run(&mut self.container);
+        self.container.commands.finish_batch();
+    }
+}
+
+// The world of a scheduler taken out of the world by KecsWorld::update
+struct RunningScheduler<'world, S: Scheduler> {
+    world: &'world mut KecsWorld<S>,
+}
+
+impl<S: Scheduler> AsMut<WorldContainer> for RunningScheduler<'_, S> {
+    fn as_mut(&mut self) -> &mut WorldContainer {
+        &mut self.world.container
     }
 }
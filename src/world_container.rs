@@ -1,11 +1,19 @@
-use std::{any::TypeId, marker::PhantomData};
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use crate::{
     archetype::ArchetypeManager,
-    commands::{Commands, CommandsSender, TypedBlob},
+    change_detection::SystemTicks,
+    commands::{Bundle, Commands, CommandsSender, TypedBlob},
     entity_manager::EntityAllocator,
-    erased_data_vec::{ErasedVec, UnsafeMutPtr, UnsafePtr},
-    resources::{Resource, Resources},
+    erased_data_vec::{ErasedPtr, ErasedVec, UnsafeMutPtr, UnsafePtr},
+    resources::{ResMut, Resource, ResourceStorage},
     sparse_set::SparseSet,
     storage::{StorageType, TableStorage},
     type_registrar::{TypeRegistrar, UniqueTypeId},
@@ -22,6 +30,13 @@ impl From<ComponentId> for usize {
     }
 }
 
+/// Builds a [`ComponentId`] from its raw index: since the type is unknown, its name is `<unknown>`
+impl From<usize> for ComponentId {
+    fn from(value: usize) -> Self {
+        Self(UniqueTypeId(value, ""))
+    }
+}
+
 impl From<Entity> for usize {
     fn from(value: Entity) -> Self {
         value.0 as usize
@@ -48,16 +63,33 @@ pub struct WorldContainer {
     pub(crate) entity_manager: EntityAllocator,
     pub(crate) archetype_manager: ArchetypeManager,
     pub(crate) commands: CommandsSender,
-    pub(crate) send_resources: Resources<true>,
-    pub(crate) non_send_resources: Resources<false>,
+    pub(crate) send_resources: ResourceStorage<true>,
+    pub(crate) non_send_resources: ResourceStorage<false>,
     // This SparseSet contains true if the resource is Send, false otherwise
     pub(crate) resource_sendness: SparseSet<ComponentId, bool>,
+    // Incremented each time a system runs or the world is changed from outside of a system
+    change_tick: AtomicU64,
+    // When set, a warning is emitted for the queries that matched no entities for this many consecutive runs
+    empty_query_warning: Option<usize>,
+    // Receives the warnings instead of stderr, see set_warning_handler
+    warning_handler: Option<WarningHandler>,
+    // The components that must be added along with a component, see register_required
+    required_components: SparseSet<ComponentId, Vec<RequiredComponent>>,
+    // The hooks run when a component is added to or removed from an entity, see register_on_add
+    component_hooks: SparseSet<ComponentId, ComponentHooks>,
+    // The tick of the last run of the systems identified by a SystemHandle, keyed by the handle type
+    system_handle_ticks: SparseSet<ComponentId, AtomicU64>,
+    // The functions cloning the components registered with register_clonable, see clone_entity
+    component_cloners: SparseSet<ComponentId, unsafe fn(ErasedPtr<'_>) -> TypedBlob>,
+    #[cfg(feature = "serde")]
+    pub(crate) serializable_components:
+        std::collections::HashMap<&'static str, crate::snapshot::SerializableComponent>,
 }
 
 // Functions exposed to systems
 impl WorldContainer {
     /// Creates a [`Commands`] instance that can be used to send deferred commands
-    pub fn commands(&self) -> Commands {
+    pub fn commands(&self) -> Commands<'_> {
         Commands::new(self)
     }
 
@@ -87,8 +119,12 @@ impl WorldContainer {
             .get_entity_info(entity)
             .is_some_and(|info| info.components.contains(&id));
         if has_component {
+            let tick = self.increment_change_tick();
             unsafe {
                 // SAFETY: We chechked that the entity has the component, and that the component ID exists
+                self.storage
+                    .get_changed_tick(entity, id)
+                    .store(tick, Ordering::Relaxed);
                 let ptr = self.storage.get_component_mut(entity, id);
                 Some(ptr.into_mut())
             }
@@ -102,14 +138,38 @@ impl WorldContainer {
     pub fn add_resource<R: 'static + Resource>(&mut self, resource: R) {
         let id = self.get_or_create_component_id::<R>();
         self.resource_sendness.insert(id, true);
-        self.send_resources.add(id, resource);
+        let tick = self.increment_change_tick();
+        self.send_resources.add(id, resource, tick);
     }
 
-    /// Creates a new `!Send` resource: accessing this resource can only be done on the main thread
+    /// Creates a new `!Send` resource: accessing this resource can only be done on the main thread.
+    /// If the [`WorldContainer`] is dropped on a thread different from the one that added the resource,
+    /// the resource is leaked instead of being dropped
     pub fn add_non_send_resource<R: 'static>(&mut self, resource: R) {
         let id = self.get_or_create_component_id::<R>();
         self.resource_sendness.insert(id, false);
-        self.non_send_resources.add(id, resource);
+        let tick = self.increment_change_tick();
+        self.non_send_resources.add(id, resource, tick);
+    }
+
+    /// Adds the resource `R::default()`, only if the resource doesn't exist yet
+    pub fn init_resource<R: 'static + Resource + Default>(&mut self) {
+        if !self.contains_resource::<R>() {
+            self.add_resource(R::default());
+        }
+    }
+
+    /// Adds the `!Send` resource `R::default()`, only if the resource doesn't exist yet, see [`WorldContainer::add_non_send_resource`]
+    pub fn init_non_send_resource<R: 'static + Default>(&mut self) {
+        if !self.contains_resource::<R>() {
+            self.add_non_send_resource(R::default());
+        }
+    }
+
+    // Returns true if R was added either as a Send or as a !Send resource
+    fn contains_resource<R: 'static>(&self) -> bool {
+        self.get_component_id::<R>()
+            .is_some_and(|id| self.resource_sendness.contains(&id))
     }
 
     /// Gets a reference to a resource, if it exists
@@ -117,7 +177,7 @@ impl WorldContainer {
         self.get_component_id::<R>()
             // SAFETY: This is safe because we're accessing a &R through a &World
             .and_then(|id| unsafe {
-                let is_send = *self.resource_sendness.get(&id).unwrap();
+                let is_send = *self.resource_sendness.get(&id)?;
                 if is_send {
                     self.send_resources.get_ptr(id)
                 } else {
@@ -127,26 +187,237 @@ impl WorldContainer {
             .map(|p| unsafe { std::mem::transmute::<&R, &R>(p.get()) })
     }
 
-    /// Gets a mutable reference to a resource, if it exists
+    /// Gets a mutable reference to a resource, if it exists: the resource is marked as changed.
+    /// Use [`Self::get_resource_mut_tracked`] to mark it only when it's actually written
     pub fn get_resource_mut<R: Resource + 'static>(&mut self) -> Option<&mut R> {
         self.get_component_id::<R>()
             // SAFETY: This is safe because we're accessing a &mut R through a &mut World
             .and_then(|id| unsafe {
-                let is_send = *self.resource_sendness.get(&id).unwrap();
+                let is_send = *self.resource_sendness.get(&id)?;
+                let tick = self.increment_change_tick();
                 if is_send {
+                    self.send_resources.mark_changed(id, tick);
                     self.send_resources.get_mut_ptr(id)
                 } else {
+                    self.non_send_resources.mark_changed(id, tick);
                     self.non_send_resources.get_mut_ptr(id)
                 }
             })
             .map(|mut p| unsafe { std::mem::transmute::<&mut R, &mut R>(p.get_mut()) })
     }
 
+    /// Gets mutable access to a resource, if it exists.
+    /// Like with [`ResMut`], the resource is marked as changed only when it is mutably dereferenced
+    pub fn get_resource_mut_tracked<R: Resource + 'static>(&mut self) -> Option<ResMut<'_, '_, R>> {
+        let id = self.get_component_id::<R>()?;
+        let is_send = *self.resource_sendness.get(&id)?;
+        // The world is treated like a system that never ran before
+        let ticks = SystemTicks {
+            last_run: 0,
+            this_run: self.increment_change_tick(),
+        };
+        // SAFETY: This is safe because we're accessing the resource through a &mut World
+        unsafe {
+            if is_send {
+                self.send_resources.get_res_mut(id, ticks)
+            } else {
+                self.non_send_resources.get_res_mut(id, ticks)
+            }
+        }
+    }
+
+    /// Gets a reference to a resource, panicking if it doesn't exist
+    pub fn resource<R: Resource + 'static>(&self) -> &R {
+        self.get_resource()
+            .unwrap_or_else(|| panic!("Resource {} not found", std::any::type_name::<R>()))
+    }
+
+    /// Gets a mutable reference to a resource, panicking if it doesn't exist
+    pub fn resource_mut<R: Resource + 'static>(&mut self) -> &mut R {
+        self.get_resource_mut()
+            .unwrap_or_else(|| panic!("Resource {} not found", std::any::type_name::<R>()))
+    }
+
+    // Returns true if the `Send` resource R exists, the only kind of resource that can be accessed by Res/ResMut
+    pub(crate) fn contains_send_resource<R: 'static>(&self) -> bool {
+        self.get_component_id::<R>()
+            .is_some_and(|id| self.send_resources.get_changed_tick(id).is_some())
+    }
+
+    /// Removes a resource, returning it if it existed
+    pub fn remove_resource<R: Resource + 'static>(&mut self) -> Option<R> {
+        let id = self.get_component_id::<R>()?;
+        let is_send = *self.resource_sendness.get(&id)?;
+        self.resource_sendness.remove(id);
+        // SAFETY: The id was created from R
+        unsafe {
+            if is_send {
+                self.send_resources.take(id)
+            } else {
+                self.non_send_resources.take(id)
+            }
+        }
+    }
+
+    /// Temporarily removes the resource `R` from the world, so that `fun` can access both the world and the resource mutably.
+    /// The resource is added back after `fun` returns (or panics), replacing any `R` added by `fun`.
+    /// Panics if the resource does not exist
+    /// e.g
+    /// ```
+    /// use kecs::{Resource, World, WorldContainer};
+    /// struct Gold(u32);
+    /// impl Resource for Gold {}
+    /// struct Bank(u32);
+    /// impl Resource for Bank {}
+    ///
+    /// fn deposit(world: &mut WorldContainer) {
+    ///     world.resource_scope(|world, bank: &mut Bank| {
+    ///         let gold = world.get_resource_mut::<Gold>().unwrap();
+    ///         bank.0 += gold.0;
+    ///         gold.0 = 0;
+    ///     });
+    /// }
+    /// let mut world = World::new();
+    /// world.add_resource(Gold(10));
+    /// world.add_resource(Bank(5));
+    /// world.add_system(0, deposit);
+    /// world.update(0);
+    /// assert_eq!(world.get_resource::<Bank>().unwrap().0, 15);
+    /// ```
+    pub fn resource_scope<R: Resource + 'static, T>(
+        &mut self,
+        fun: impl FnOnce(&mut WorldContainer, &mut R) -> T,
+    ) -> T {
+        // Adds the resource back even if fun panics
+        struct ScopeGuard<'world, R: Resource + 'static> {
+            world: &'world mut WorldContainer,
+            resource: Option<R>,
+            send: bool,
+        }
+        impl<R: Resource + 'static> Drop for ScopeGuard<'_, R> {
+            fn drop(&mut self) {
+                let resource = self.resource.take().unwrap();
+                if self.send {
+                    self.world.add_resource(resource);
+                } else {
+                    self.world.add_non_send_resource(resource);
+                }
+            }
+        }
+
+        let send = self
+            .get_component_id::<R>()
+            .and_then(|id| self.resource_sendness.get(&id).copied());
+        let resource = send.and_then(|_| self.remove_resource::<R>());
+        let (Some(send), Some(resource)) = (send, resource) else {
+            panic!("Resource {} not found", std::any::type_name::<R>());
+        };
+        let mut guard = ScopeGuard {
+            world: self,
+            resource: Some(resource),
+            send,
+        };
+        let ScopeGuard {
+            world, resource, ..
+        } = &mut guard;
+        fun(world, resource.as_mut().unwrap())
+    }
+
+    /// Iterates the [`ComponentId`]s of all the registered resources, along with `true` if the resource is `Send`
+    pub fn iter_resource_ids(&self) -> impl Iterator<Item = (ComponentId, bool)> + '_ {
+        self.resource_sendness.iter().map(|(id, send)| (id, *send))
+    }
+
+    /// Iterates the [`ComponentId`]s of all the registered types (components and resources), along with their type names,
+    /// in the order the types were registered
+    pub fn registered_components(&self) -> impl Iterator<Item = (ComponentId, &'static str)> {
+        self.registrar
+            .iter()
+            .map(|registration| (ComponentId(registration), registration.1))
+    }
+
+    /// Iterates the [`Entity`]s having all the components in `component_ids`, e.g for components known only at runtime
+    /// through [`WorldContainer::registered_components`]. See [`crate::Query`] for the typed version
+    pub fn iter_entities_with<'a>(
+        &'a self,
+        component_ids: &'a [ComponentId],
+    ) -> impl Iterator<Item = Entity> + 'a {
+        self.archetype_manager
+            .archetypes_from(0)
+            .filter(|(_, archetype)| {
+                component_ids
+                    .iter()
+                    .all(|id| archetype.components.contains(id))
+            })
+            .flat_map(|(_, archetype)| archetype.entities.iter().copied())
+    }
+
+    /// Gets a type-erased pointer to the component `component_id` of the [`Entity`], returns None if the entity
+    /// does not have the component
+    pub fn get_component_erased(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> Option<ErasedPtr<'_>> {
+        let has_component = self
+            .get_entity_info(entity)
+            .is_some_and(|info| info.components.contains(&component_id));
+        // SAFETY: We checked that the entity has the component, and the component can only be read through a &World
+        has_component.then(|| unsafe { self.storage.get_component_erased(entity, component_id) })
+    }
+
     /// Iterates all the [`Entity`]s, along with their [`EntityInfo`]s
     pub fn iter_all_entities(&self) -> impl Iterator<Item = (Entity, &EntityInfo)> + '_ {
         self.entity_manager.iter_all_entities()
     }
 
+    /// Like [`WorldContainer::iter_all_entities`], but the entities are sorted by ascending (index, generation),
+    /// so that the order doesn't depend on the history of spawned and destroyed entities
+    pub fn iter_all_entities_sorted(&self) -> impl Iterator<Item = (Entity, &EntityInfo)> + '_ {
+        let mut entities: Vec<_> = self.iter_all_entities().collect();
+        entities.sort_unstable_by_key(|(entity, _)| *entity);
+        entities.into_iter()
+    }
+
+    /// Returns true if the entity is alive: a handle to a destroyed entity is never contained,
+    /// even when its id was reused by a newer entity
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entity_manager.entity_info(entity).is_some()
+    }
+
+    /// Ensures that an entity with the exact id (index and generation) of `entity` exists, spawning it if needed,
+    /// returning true if a new entity was spawned.
+    /// A live entity using the same index with an older generation is destroyed and replaced, emitting a warning.
+    /// If instead the live entity is newer, `entity` is stale: nothing is spawned, and false is returned with a warning
+    pub fn get_or_spawn(&mut self, entity: Entity) -> bool {
+        if self.contains(entity) {
+            return false;
+        }
+        if let Some(live) = self.entity_manager.live_entity_at(entity) {
+            if self.is_stale(entity) {
+                self.warn(format!(
+                    "Tried to spawn entity {entity:?}, which is older than the live entity {live:?}: nothing was spawned"
+                ));
+                return false;
+            }
+            self.warn(format!(
+                "Spawning entity {entity:?} destroyed the live entity {live:?}, which had an older generation"
+            ));
+            self.remove_entity(live);
+        }
+        self.entity_manager.reserve_id(entity);
+        // SAFETY: The id was just reserved, and no live entity is using it
+        unsafe { self.new_entity_with_id(entity) }
+        true
+    }
+
+    // Returns true if a live entity uses the same index of `entity` with a newer generation
+    pub(crate) fn is_stale(&self, entity: Entity) -> bool {
+        self.entity_manager
+            .live_entity_at(entity)
+            .is_some_and(|live| live.1 > entity.1)
+    }
+
     /// Gets the [`EntityInfo`] associated to an entity
     pub fn get_entity_info(&self, e: Entity) -> Option<&EntityInfo> {
         self.entity_manager.entity_info(e)
@@ -167,6 +438,155 @@ impl WorldContainer {
         ComponentId(self.registrar.get::<A>())
     }
 
+    /// Gets the current change tick of the world: the tick is incremented each time a system runs
+    /// or a component is changed from outside of a system
+    pub fn change_tick(&self) -> u64 {
+        self.change_tick.load(Ordering::Relaxed)
+    }
+
+    /// Debug diagnostic: when `runs` is `Some`, a warning naming the system and the components of the query
+    /// is printed for each query that matched no entities for `runs` consecutive runs of its system.
+    /// This can help e.g finding queries requiring a component that is never added. Disabled by default
+    pub fn set_empty_query_warning(&mut self, runs: Option<usize>) {
+        self.empty_query_warning = runs;
+    }
+
+    /// Gets the number of runs after which a query matching no entities is reported, see [`Self::set_empty_query_warning`]
+    pub fn empty_query_warning(&self) -> Option<usize> {
+        self.empty_query_warning
+    }
+
+    /// Sets the function receiving the diagnostic warnings (e.g the one enabled by [`Self::set_empty_query_warning`]),
+    /// which are printed to stderr by default
+    pub fn set_warning_handler(&mut self, handler: impl Fn(&str) + Send + Sync + 'static) {
+        self.warning_handler = Some(Box::new(handler));
+    }
+
+    pub(crate) fn warn(&self, message: String) {
+        match &self.warning_handler {
+            Some(handler) => handler(&message),
+            None => eprintln!("[kecs] warning: {message}"),
+        }
+    }
+
+    /// Registers `R` as a component required by `A`: each time `A` is added to an entity that does not have `R`,
+    /// `R` is created with `constructor` and added too. Requirements are chained, so if `R` requires other
+    /// components they are added as well.
+    /// Entities which already have `A` are not affected
+    pub fn register_required<A: 'static, R: 'static>(
+        &mut self,
+        constructor: impl Fn() -> R + Send + Sync + 'static,
+    ) {
+        let component_id = self.get_or_create_component_id::<A>();
+        let required_id = self.get_or_create_component_id::<R>();
+        let requirements = self
+            .required_components
+            .get_or_insert(component_id, Vec::new);
+        requirements.retain(|requirement| requirement.component_id != required_id);
+        requirements.push(RequiredComponent {
+            component_id: required_id,
+            constructor: Box::new(move || TypedBlob::new(constructor())),
+        });
+    }
+
+    /// Registers `T` as a component that can be copied by [`WorldContainer::clone_entity`]
+    pub fn register_clonable<T: Clone + 'static>(&mut self) {
+        // SAFETY: The cloner is called only with pointers to a T
+        unsafe fn clone_component<T: Clone + 'static>(component: ErasedPtr<'_>) -> TypedBlob {
+            TypedBlob::new(component.deref::<T>().clone())
+        }
+
+        let component_id = self.get_or_create_component_id::<T>();
+        self.component_cloners
+            .insert(component_id, clone_component::<T>);
+    }
+
+    /// Spawns a new entity with a copy of each component of `src` registered with [`WorldContainer::register_clonable`],
+    /// returning the new entity or None if `src` does not exist. The other components are skipped
+    pub fn clone_entity(&mut self, src: Entity) -> Option<Entity> {
+        let info = self.get_entity_info(src)?;
+        let mut components = vec![];
+        for (component_id, _) in info.components.iter_sorted() {
+            if let Some(clone_fn) = self.component_cloners.get(&component_id) {
+                // SAFETY: The cloner was registered for the component's type, and the entity has the component
+                components.push(unsafe {
+                    clone_fn(self.storage.get_component_erased(src, component_id))
+                });
+            } else if cfg!(debug_assertions) {
+                self.warn(format!(
+                    "Component '{}' of entity {src:?} was not cloned, since it was not registered with register_clonable",
+                    component_id.name()
+                ));
+            }
+        }
+
+        let entity = self.new_entity();
+        for component in components {
+            // SAFETY: The blob was created by the cloner of its component type
+            unsafe { self.add_component_from_type_id(entity, component) };
+        }
+        Some(entity)
+    }
+
+    /// Registers a hook called each time the component `T` is added to an entity that did not have it.
+    /// The hook runs after the component is added and the entity's archetype is updated, so it can freely access
+    /// the [`WorldContainer`]
+    pub fn register_on_add<T: 'static>(
+        &mut self,
+        hook: impl Fn(&mut WorldContainer, Entity) + Send + Sync + 'static,
+    ) {
+        let component_id = self.get_or_create_component_id::<T>();
+        self.component_hooks
+            .get_or_insert(component_id, Default::default)
+            .on_add
+            .push(Arc::new(hook));
+    }
+
+    /// Registers a hook called each time the component `T` is removed from an entity, including when the entity
+    /// is destroyed. Like [`WorldContainer::register_on_add`], the hook runs after the component is removed and
+    /// the entity's archetype is updated
+    pub fn register_on_remove<T: 'static>(
+        &mut self,
+        hook: impl Fn(&mut WorldContainer, Entity) + Send + Sync + 'static,
+    ) {
+        let component_id = self.get_or_create_component_id::<T>();
+        self.component_hooks
+            .get_or_insert(component_id, Default::default)
+            .on_remove
+            .push(Arc::new(hook));
+    }
+
+    /// Gets the tick of the last run of the system identified by [`crate::SystemHandle<H>`],
+    /// `0` if the system never ran. Returns None if no system is identified by `H`
+    pub fn get_system_handle_tick<H: 'static>(&self) -> Option<u64> {
+        let id = self.get_component_id::<H>()?;
+        self.system_handle_ticks
+            .get(&id)
+            .map(|tick| tick.load(Ordering::Relaxed))
+    }
+
+    /// Gets the tick at which the resource `R` was last changed, returns None if the resource does not exist
+    pub fn get_resource_changed_tick<R: 'static>(&self) -> Option<u64> {
+        let id = self.get_component_id::<R>()?;
+        let is_send = *self.resource_sendness.get(&id)?;
+        let tick = if is_send {
+            self.send_resources.get_changed_tick(id)
+        } else {
+            self.non_send_resources.get_changed_tick(id)
+        };
+        tick.map(|tick| tick.load(Ordering::Relaxed))
+    }
+
+    /// Gets the tick at which the component `A` of the [`Entity`] was last changed,
+    /// returns None if the entity does not have the component
+    pub fn get_component_changed_tick<A: 'static>(&self, entity: Entity) -> Option<u64> {
+        let id = self.get_component_id::<A>()?;
+        self.entity_has_component::<A>(entity).then(|| {
+            // SAFETY: We checked that the entity has the component
+            unsafe { self.storage.get_changed_tick(entity, id) }.load(Ordering::Relaxed)
+        })
+    }
+
     /// Returns `true` if the [`Entity`] has a component of type `A`
     pub fn entity_has_component<A: 'static>(&self, entity: Entity) -> bool {
         self.get_component_id::<A>().is_some_and(|id| {
@@ -175,6 +595,24 @@ impl WorldContainer {
         })
     }
 
+    /// Returns the number of components of the [`Entity`], `0` if the entity does not exist
+    pub fn component_count(&self, entity: Entity) -> usize {
+        self.get_entity_info(entity)
+            .map_or(0, |info| info.components.len())
+    }
+
+    /// Lists the [`ComponentId`]s of the [`Entity`]'s components along with their type names, in the order the types
+    /// were registered, e.g for an inspector. Returns None if the entity does not exist
+    pub fn components_of(&self, entity: Entity) -> Option<Vec<(ComponentId, &'static str)>> {
+        let info = self.get_entity_info(entity)?;
+        Some(
+            info.components
+                .iter_sorted()
+                .map(|(id, _)| (id, id.name()))
+                .collect(),
+        )
+    }
+
     pub(crate) fn get_archetype_manager_mut(&mut self) -> &mut ArchetypeManager {
         &mut self.archetype_manager
     }
@@ -188,11 +626,51 @@ impl WorldContainer {
         entity: Entity,
         component: TypedBlob,
     ) {
-        let component_id = self.get_or_create_component_id_dynamic(
-            component.blob_ty_id,
-            component.type_name.expect("No type name"),
-        );
-        self.add_component_dynamic(entity, component_id, &component.data);
+        let type_name = component.type_name.expect("No type name");
+        if !self.contains(entity) {
+            // The entity may have been destroyed by an earlier command: the component is dropped with its blob
+            if cfg!(debug_assertions) {
+                self.warn(format!(
+                    "Tried to add component '{type_name}' to destroyed entity {entity:?}, the component was dropped"
+                ));
+            }
+            return;
+        }
+        let component_id = self.get_or_create_component_id_dynamic(component.blob_ty_id, type_name);
+        // Check the layout here, the raw copy into the storage would fail with a less descriptive message
+        if let Some(layout) = self.storage.component_layout(component_id) {
+            assert!(
+                layout == component.data.layout,
+                "Tried to add component '{type_name}' with layout {:?}, but component {} is stored with layout {layout:?}",
+                component.data.layout,
+                component_id.name(),
+            );
+        }
+        let old_components = self.components_for_hooks(entity);
+        self.add_component_dynamic(entity, component_id, &component.into_data());
+        self.run_component_hooks(entity, old_components);
+    }
+
+    pub(crate) fn add_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        let component_ids = B::component_ids(self);
+        let old_components = self.components_for_hooks(entity);
+        let mut index = 0;
+        bundle.take_components(|component| {
+            let type_name = component.type_name.expect("No type name");
+            // Look up the id from the blob, so that a Bundle passing its components out of order can't store a
+            // component with the wrong type
+            let component_id =
+                self.get_or_create_component_id_dynamic(component.blob_ty_id, type_name);
+            debug_assert_eq!(
+                component_ids.get(index),
+                Some(&component_id),
+                "Bundle '{}' passed component '{type_name}' in a different order than its component ids",
+                std::any::type_name::<B>(),
+            );
+            index += 1;
+            self.add_component_dynamic(entity, component_id, &component.into_data());
+        });
+        self.run_component_hooks(entity, old_components);
     }
 
     fn get_or_create_component_id_dynamic(
@@ -210,6 +688,13 @@ impl WorldContainer {
         component_id: ComponentId,
         data: &ErasedVec,
     ) {
+        // Check the entity before adding the components it requires
+        assert!(
+            self.contains(entity),
+            "Tried to add component '{}' to destroyed entity {entity:?}",
+            component_id.name()
+        );
+        let required_added = self.insert_required_components(entity, component_id);
         let entity_info = self
             .entity_manager
             .entity_info_mut(entity)
@@ -220,6 +705,10 @@ impl WorldContainer {
                 self.storage
                     .replace_entity_component_dynamic(entity, component_id, data);
             };
+            self.mark_component_changed(entity, component_id);
+            if required_added {
+                self.update_entity_archetype(entity);
+            }
             return;
         }
 
@@ -230,6 +719,7 @@ impl WorldContainer {
             self.storage
                 .add_entity_component_dynamic(entity, component_id, data)
         }
+        self.mark_component_added(entity, component_id);
 
         self.update_entity_archetype(entity);
     }
@@ -241,13 +731,14 @@ impl WorldContainer {
         type_name: &'static str,
     ) {
         let component_id = self.get_or_create_component_id_dynamic(component_ty, type_name);
-        Self::remove_component_untyped(
-            entity,
-            self.entity_manager.entity_info_mut(entity).unwrap(),
-            component_id,
-            &mut self.storage,
-        );
+        let old_components = self.components_for_hooks(entity);
+        // The entity may have been destroyed by an earlier command, like remove_component this is a no-op
+        let Some(entity_info) = self.entity_manager.entity_info_mut(entity) else {
+            return;
+        };
+        Self::remove_component_untyped(entity, entity_info, component_id, &mut self.storage);
         self.update_entity_archetype(entity);
+        self.run_component_hooks(entity, old_components);
     }
 
     pub(crate) unsafe fn add_resource_dynamic(&mut self, resource: TypedBlob, send: bool) {
@@ -255,16 +746,156 @@ impl WorldContainer {
             resource.blob_ty_id,
             resource.type_name.expect("No type name"),
         );
+        self.resource_sendness.insert(component_id, send);
+        let tick = self.increment_change_tick();
         if send {
-            self.send_resources.add_dynamic(component_id, resource)
+            self.send_resources
+                .add_dynamic(component_id, resource, tick)
+        } else {
+            self.non_send_resources
+                .add_dynamic(component_id, resource, tick)
+        }
+    }
+
+    pub(crate) fn remove_resource_dynamic(&mut self, resource_ty: TypeId) {
+        let Some(id) = self
+            .registrar
+            .get_maybe_from_type_id(resource_ty)
+            .map(ComponentId)
+        else {
+            return;
+        };
+        let Some(is_send) = self.resource_sendness.get(&id).copied() else {
+            return;
+        };
+        self.resource_sendness.remove(id);
+        if is_send {
+            self.send_resources.remove(id);
+        } else {
+            self.non_send_resources.remove(id);
+        }
+    }
+}
+
+type ComponentHook = Arc<dyn Fn(&mut WorldContainer, Entity) + Send + Sync>;
+type WarningHandler = Box<dyn Fn(&str) + Send + Sync>;
+
+#[derive(Default)]
+struct ComponentHooks {
+    on_add: Vec<ComponentHook>,
+    on_remove: Vec<ComponentHook>,
+}
+
+struct RequiredComponent {
+    component_id: ComponentId,
+    constructor: Box<dyn Fn() -> TypedBlob + Send + Sync>,
+}
+
+/// An [`EntityScope`] is used to batch multiple edits to an [`Entity`]: the changes to the
+/// entity's archetype are applied only once all the edits are done, see [`crate::KecsWorld::entity_scope`]
+pub struct EntityScope<'world> {
+    world: &'world mut WorldContainer,
+    entity: Entity,
+    changed: bool,
+}
+
+impl<'world> EntityScope<'world> {
+    /// The [`Entity`] being edited
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Adds a component to the entity: if the entity already had the component, it is overwritten
+    pub fn add<T: 'static>(&mut self, component: T) -> &mut Self {
+        self.changed |= self.world.insert_component(self.entity, component);
+        self
+    }
+
+    /// Removes a component from the entity, if it has one
+    pub fn remove<T: 'static>(&mut self) -> &mut Self {
+        self.changed |= self.world.take_component::<T>(self.entity);
+        self
+    }
+
+    /// Gets a reference to a component of the entity, if it has one
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.world.get_component(self.entity)
+    }
+
+    /// Gets a mutable reference to a component of the entity, if it has one
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.world.get_component_mut(self.entity)
+    }
+}
+
+/// An [`EntityMut`] gives mutable access to a single [`Entity`], see [`WorldContainer::entity_mut`].
+/// Like with an [`EntityScope`], the changes to the entity's archetype are applied only once,
+/// when the [`EntityMut`] is dropped
+pub struct EntityMut<'world> {
+    world: &'world mut WorldContainer,
+    entity: Entity,
+    changed: bool,
+    old_components: Option<SparseSet<ComponentId, ()>>,
+}
+
+impl<'world> EntityMut<'world> {
+    pub(crate) fn new(world: &'world mut WorldContainer, entity: Entity) -> Option<Self> {
+        world.get_entity_info(entity)?;
+        let old_components = world.components_for_hooks(entity);
+        Some(Self {
+            world,
+            entity,
+            changed: false,
+            old_components,
+        })
+    }
+
+    /// The [`Entity`] being edited
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// Adds a component to the entity: if the entity already had the component, it is overwritten
+    pub fn insert<T: 'static>(&mut self, component: T) -> &mut Self {
+        self.changed |= self.world.insert_component(self.entity, component);
+        self
+    }
+
+    /// Removes a component from the entity, if it has one
+    pub fn remove<T: 'static>(&mut self) -> &mut Self {
+        self.changed |= self.world.take_component::<T>(self.entity);
+        self
+    }
+
+    /// Gets a reference to a component of the entity, if it has one
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.world.get_component(self.entity)
+    }
+
+    /// Gets a mutable reference to a component of the entity, if it has one
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.world.get_component_mut(self.entity)
+    }
+}
+
+impl<'world> Drop for EntityMut<'world> {
+    fn drop(&mut self) {
+        if self.changed {
+            self.world.update_entity_archetype(self.entity);
         }
+        self.world
+            .run_component_hooks(self.entity, self.old_components.take());
     }
 }
 
 impl ComponentId {
-    /// Gets this component's full name
+    /// Gets this component's full name, or `<unknown>` if the id was not created by registering a type
     pub fn name(&self) -> &'static str {
-        self.0 .1
+        if self.0 .1.is_empty() {
+            "<unknown>"
+        } else {
+            self.0 .1
+        }
     }
 }
 
@@ -283,9 +914,18 @@ impl WorldContainer {
             entity_manager: Default::default(),
             registrar: TypeRegistrar::default(),
             archetype_manager: ArchetypeManager::default(),
-            send_resources: Resources::new(),
-            non_send_resources: Resources::new(),
+            send_resources: ResourceStorage::new(),
+            non_send_resources: ResourceStorage::new(),
             resource_sendness: Default::default(),
+            change_tick: AtomicU64::new(1),
+            required_components: Default::default(),
+            empty_query_warning: None,
+            component_hooks: Default::default(),
+            system_handle_ticks: Default::default(),
+            component_cloners: Default::default(),
+            warning_handler: None,
+            #[cfg(feature = "serde")]
+            serializable_components: Default::default(),
             commands,
         }
     }
@@ -313,21 +953,44 @@ impl WorldContainer {
     }
 
     pub(crate) fn remove_entity(&mut self, entity: Entity) {
+        self.detach_hierarchy(entity);
+        let old_components = self.components_for_hooks(entity);
         if let Some(info) = self.entity_manager.entity_info_mut(entity) {
             let components = info.components.iter().map(|(c, _)| c).collect::<Vec<_>>();
             for component in components {
                 Self::remove_component_untyped(entity, info, component, &mut self.storage);
             }
+            if let Some(archetype) = self.archetype_manager.get_archetype_mut(info.archetype_id) {
+                archetype.entities.remove(&entity);
+            }
             // SAFETY: An entity is alive only when it has an associated EntityInfo
             unsafe {
                 self.storage.erase_entity(entity);
             }
             self.entity_manager.destroy_entity(entity);
         }
+        self.run_component_hooks(entity, old_components);
     }
 
     pub(crate) fn add_component<C: 'static>(&mut self, entity: Entity, component: C) {
+        let old_components = self.components_for_hooks(entity);
+        if self.insert_component(entity, component) {
+            self.update_entity_archetype(entity);
+        }
+        self.run_component_hooks(entity, old_components);
+    }
+
+    // Returns true if the component was added to the entity, false if it replaced an existing one.
+    // The caller must update the entity's archetype when the component is added
+    fn insert_component<C: 'static>(&mut self, entity: Entity, component: C) -> bool {
         let component_id = ComponentId(self.registrar.get_registration::<C>());
+        // Check the entity before adding the components it requires
+        assert!(
+            self.contains(entity),
+            "Tried to add component '{}' to destroyed entity {entity:?}",
+            std::any::type_name::<C>()
+        );
+        let required_added = self.insert_required_components(entity, component_id);
         let entity_info = self
             .entity_manager
             .entity_info_mut(entity)
@@ -338,18 +1001,188 @@ impl WorldContainer {
                 self.storage
                     .replace_entity_component(entity, component_id, component);
             };
-            return;
+            self.mark_component_changed(entity, component_id);
+            return required_added;
         } else {
             entity_info.components.insert(component_id, ());
         }
 
-        self.update_entity_archetype(entity);
-
         //# SAFETY: The entity does not have the specified component
         unsafe {
             self.storage
                 .add_entity_component(entity, component_id, component);
         }
+        self.mark_component_added(entity, component_id);
+        true
+    }
+
+    // Adds the components required by `component_id` (and the ones required by them) that the entity does not have yet.
+    // Returns true if any component was added: the caller must update the entity's archetype
+    fn insert_required_components(&mut self, entity: Entity, component_id: ComponentId) -> bool {
+        let mut visited = SparseSet::<ComponentId, ()>::default();
+        visited.insert(component_id, ());
+        let mut to_visit = vec![component_id];
+        let mut added = false;
+
+        while let Some(current) = to_visit.pop() {
+            let Some(required) = self.required_components.get(&current) else {
+                continue;
+            };
+            let entity_info = self
+                .entity_manager
+                .entity_info_mut(entity)
+                .expect("Failed to find entity");
+            let mut missing = vec![];
+            for requirement in required {
+                if visited.contains(&requirement.component_id) {
+                    continue;
+                }
+                visited.insert(requirement.component_id, ());
+                to_visit.push(requirement.component_id);
+                if !entity_info.components.contains(&requirement.component_id) {
+                    entity_info.components.insert(requirement.component_id, ());
+                    missing.push((requirement.component_id, (requirement.constructor)()));
+                }
+            }
+
+            for (required_id, component) in missing {
+                //# SAFETY: The entity does not have the specified component, and the blob was created
+                // by the constructor registered for the component
+                unsafe {
+                    self.storage.add_entity_component_dynamic(
+                        entity,
+                        required_id,
+                        &component.into_data(),
+                    )
+                }
+                self.mark_component_added(entity, required_id);
+                added = true;
+            }
+        }
+        added
+    }
+
+    // Removes the component from the entity without updating its archetype.
+    // Returns true if the entity had the component: the caller must update the entity's archetype
+    fn take_component<C: 'static>(&mut self, entity: Entity) -> bool {
+        let component_id = self.get_or_create_component_id::<C>();
+        let entity_info = self
+            .entity_manager
+            .entity_info_mut(entity)
+            .expect("Failed to find entity");
+        let had_component = entity_info.components.contains(&component_id);
+        Self::remove_component_untyped(entity, entity_info, component_id, &mut self.storage);
+        had_component
+    }
+
+    /// Gets an [`EntityMut`] to edit the entity, if it exists: the entity's archetype is computed
+    /// only once, when the [`EntityMut`] is dropped
+    pub fn entity_mut(&mut self, entity: Entity) -> Option<EntityMut<'_>> {
+        EntityMut::new(self, entity)
+    }
+
+    /// Applies all the edits done to `entity` by `fun` through an [`EntityScope`]:
+    /// the entity's archetype is computed only once, after `fun` returns
+    pub(crate) fn entity_scope(&mut self, entity: Entity, fun: impl FnOnce(&mut EntityScope)) {
+        assert!(
+            self.get_entity_info(entity).is_some(),
+            "Failed to find entity"
+        );
+        let old_components = self.components_for_hooks(entity);
+        let mut scope = EntityScope {
+            world: self,
+            entity,
+            changed: false,
+        };
+        fun(&mut scope);
+        if scope.changed {
+            self.update_entity_archetype(entity);
+        }
+        self.run_component_hooks(entity, old_components);
+    }
+
+    // Returns the components of the entity when any hook is registered: after editing the entity,
+    // pass them to run_component_hooks to run the hooks of the added and removed components
+    fn components_for_hooks(&self, entity: Entity) -> Option<SparseSet<ComponentId, ()>> {
+        if self.component_hooks.is_empty() {
+            return None;
+        }
+        self.get_entity_info(entity)
+            .map(|info| info.components.clone())
+    }
+
+    fn run_component_hooks(
+        &mut self,
+        entity: Entity,
+        old_components: Option<SparseSet<ComponentId, ()>>,
+    ) {
+        let Some(old_components) = old_components else {
+            return;
+        };
+        let new_components = self
+            .get_entity_info(entity)
+            .map(|info| info.components.clone())
+            .unwrap_or_default();
+
+        // Collect the hooks first, since the hooks can register new hooks
+        let mut hooks = vec![];
+        for (component, _) in old_components.iter_sorted() {
+            if let Some(component_hooks) = self.component_hooks.get(&component) {
+                if !new_components.contains(&component) {
+                    hooks.extend(component_hooks.on_remove.iter().cloned());
+                }
+            }
+        }
+        for (component, _) in new_components.iter_sorted() {
+            if let Some(component_hooks) = self.component_hooks.get(&component) {
+                if !old_components.contains(&component) {
+                    hooks.extend(component_hooks.on_add.iter().cloned());
+                }
+            }
+        }
+        for hook in hooks {
+            hook(self, entity);
+        }
+    }
+
+    pub(crate) fn register_system_handle<H: 'static>(&mut self) -> ComponentId {
+        let id = self.get_or_create_component_id::<H>();
+        self.system_handle_ticks.get_or_insert(id, Default::default);
+        id
+    }
+
+    pub(crate) fn set_system_handle_tick(&self, id: ComponentId, tick: u64) {
+        self.system_handle_ticks
+            .get(&id)
+            .expect("The system handle was not registered")
+            .store(tick, Ordering::Relaxed);
+    }
+
+    /// Increments the world's change tick, returning the new tick
+    pub(crate) fn increment_change_tick(&self) -> u64 {
+        self.change_tick.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    // The entity must have the component
+    fn mark_component_changed(&self, entity: Entity, component_id: ComponentId) {
+        let tick = self.increment_change_tick();
+        // SAFETY: The component was just added to the entity
+        unsafe { self.storage.get_changed_tick(entity, component_id) }
+            .store(tick, Ordering::Relaxed);
+    }
+
+    // The entity must have the component, which was just added
+    fn mark_component_added(&self, entity: Entity, component_id: ComponentId) {
+        let tick = self.increment_change_tick();
+        // SAFETY: The component was just added to the entity
+        unsafe {
+            self.storage
+                .get_changed_tick(entity, component_id)
+                .store(tick, Ordering::Relaxed);
+            self.storage
+                .get_added_tick(entity, component_id)
+                .store(tick, Ordering::Relaxed);
+        }
     }
 
     fn update_entity_archetype(&mut self, entity: Entity) {
@@ -361,7 +1194,7 @@ impl WorldContainer {
             .archetype_manager
             .get_archetype_mut(entity_info.archetype_id)
         {
-            old_archetype.entities.remove(entity);
+            old_archetype.entities.remove(&entity);
         }
 
         let new_archetype_id = self.archetype_manager.archetype_of(&entity_info.components);
@@ -371,7 +1204,7 @@ impl WorldContainer {
             .archetype_manager
             .get_archetype_mut(entity_info.archetype_id)
             .unwrap();
-        new_archetype.entities.insert(entity, ());
+        new_archetype.entities.insert(entity);
     }
 
     pub(crate) unsafe fn get_component_unsafe<C: 'static>(
@@ -379,17 +1212,40 @@ impl WorldContainer {
         entity: Entity,
         component_id: ComponentId,
     ) -> UnsafePtr<'_, C> {
-        let entity_info = &self.entity_manager.entity_info(entity).unwrap().components;
+        let entity_info = &self
+            .entity_manager
+            .entity_info(entity)
+            .expect("Tried to access a component of a destroyed entity")
+            .components;
         assert!(entity_info.contains(&component_id));
         //# SAFETY: We asserted that the entity has the component
         unsafe { self.storage.get_component(entity, component_id) }
     }
+    pub(crate) unsafe fn get_changed_tick_unsafe(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> &AtomicU64 {
+        let entity_info = &self
+            .entity_manager
+            .entity_info(entity)
+            .expect("Tried to access a component of a destroyed entity")
+            .components;
+        assert!(entity_info.contains(&component_id));
+        //# SAFETY: We asserted that the entity has the component
+        unsafe { self.storage.get_changed_tick(entity, component_id) }
+    }
+
     pub(crate) unsafe fn get_component_mut_unsafe<C: 'static>(
         &self,
         entity: Entity,
         component_id: ComponentId,
     ) -> UnsafeMutPtr<'_, C> {
-        let entity_info = &self.entity_manager.entity_info(entity).unwrap().components;
+        let entity_info = &self
+            .entity_manager
+            .entity_info(entity)
+            .expect("Tried to access a component of a destroyed entity")
+            .components;
         assert!(entity_info.contains(&component_id));
         //# SAFETY: We asserted that the entity has the component
         unsafe { self.storage.get_component_mut(entity, component_id) }
@@ -397,10 +1253,41 @@ impl WorldContainer {
 
     pub(crate) fn remove_component<C: 'static>(&mut self, entity: Entity) {
         let component_id = ComponentId(self.registrar.get_registration::<C>());
+        let old_components = self.components_for_hooks(entity);
         if let Some(entity_info) = self.entity_manager.entity_info_mut(entity) {
             Self::remove_component_untyped(entity, entity_info, component_id, &mut self.storage);
             self.update_entity_archetype(entity);
         }
+        self.run_component_hooks(entity, old_components);
+    }
+
+    /// Removes the component `T` from all the entities that have it, returning how many entities lost the component
+    pub fn remove_component_from_all<T: 'static>(&mut self) -> usize {
+        let Some(component_id) = self.get_component_id::<T>() else {
+            return 0;
+        };
+        let entities = self
+            .iter_all_entities()
+            .filter(|(_, info)| info.components.contains(&component_id))
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        let mut removed = 0;
+        for entity in entities {
+            let old_components = self.components_for_hooks(entity);
+            // A hook may have already removed the component, or the entity
+            let Some(entity_info) = self
+                .entity_manager
+                .entity_info_mut(entity)
+                .filter(|info| info.components.contains(&component_id))
+            else {
+                continue;
+            };
+            Self::remove_component_untyped(entity, entity_info, component_id, &mut self.storage);
+            self.update_entity_archetype(entity);
+            self.run_component_hooks(entity, old_components);
+            removed += 1;
+        }
+        removed
     }
 
     fn remove_component_untyped(
@@ -411,19 +1298,28 @@ impl WorldContainer {
     ) {
         let entity_components = &mut entity_info.components;
         if entity_components.contains(&component_id) {
+            // The component is removed from the entity before being dropped, so that a panicking drop
+            // doesn't leave the entity with a component that was already dropped
+            entity_components.remove(component_id);
             //# SAFETY: We know for sure that the entity has the specified component
             unsafe { storage.erase_entity_component(entity, component_id) };
-            entity_components.remove(component_id);
         }
     }
 }
 
 impl Drop for WorldContainer {
     fn drop(&mut self) {
+        // The hooks must not run while the world is being torn down
+        self.component_hooks.clear();
         let entities = self.iter_all_entities().map(|(e, _)| e).collect::<Vec<_>>();
         for ent in entities {
             self.remove_entity(ent);
         }
+        for name in self.non_send_resources.iter_leaked_on_drop() {
+            self.warn(format!(
+                "The non-send resource {name} is leaked, since the world is dropped on a thread that doesn't own it"
+            ));
+        }
     }
 }
 
@@ -432,6 +1328,10 @@ impl<'a> UnsafeWorldPtr<'a> {
         self.0.into_mut()
     }
 
+    pub(crate) unsafe fn get(&self) -> &'a WorldContainer {
+        unsafe { self.0 .0.as_ref().unwrap() }
+    }
+
     pub(crate) fn copied(&self) -> Self {
         Self(UnsafeMutPtr(self.0 .0, PhantomData))
     }
@@ -450,7 +1350,696 @@ impl<'a> UnsafeWorldPtr<'a> {
         let component_id = store.get_component_id_assertive::<A>();
         store.get_component_mut_unsafe(entity, component_id)
     }
+
+    pub(crate) unsafe fn get_changed_tick<A: 'static>(&self, entity: Entity) -> &'a AtomicU64 {
+        let store = unsafe { self.0 .0.as_mut().unwrap() };
+        let component_id = store.get_component_id_assertive::<A>();
+        store.get_changed_tick_unsafe(entity, component_id)
+    }
+
+    // The following methods don't check the components of the entity, so the caller must ensure
+    // that the entity has the component identified by `component_id`, which must be of type A
+    pub(crate) unsafe fn get_component_by_id<A: 'static>(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> UnsafePtr<'a, A> {
+        let store = unsafe { self.0 .0.as_ref().unwrap() };
+        store.storage.get_component(entity, component_id)
+    }
+
+    pub(crate) unsafe fn get_component_mut_by_id<A: 'static>(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> UnsafeMutPtr<'a, A> {
+        let store = unsafe { self.0 .0.as_ref().unwrap() };
+        store.storage.get_component_mut(entity, component_id)
+    }
+
+    pub(crate) unsafe fn get_changed_tick_by_id(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> &'a AtomicU64 {
+        let store = unsafe { self.0 .0.as_ref().unwrap() };
+        store.storage.get_changed_tick(entity, component_id)
+    }
+
+    pub(crate) unsafe fn get_added_tick_by_id(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> &'a AtomicU64 {
+        let store = unsafe { self.0 .0.as_ref().unwrap() };
+        store.storage.get_added_tick(entity, component_id)
+    }
 }
 
 unsafe impl<'a> Send for UnsafeWorldPtr<'a> {}
 unsafe impl<'a> Sync for UnsafeWorldPtr<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        any::TypeId,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use crate::{
+        commands::{Commands, TypedBlob},
+        Entity, GraphScheduler, Query, Resource, Scheduler,
+    };
+
+    use super::WorldContainer;
+
+    fn make_world_container() -> WorldContainer {
+        WorldContainer::new(Commands::create().0)
+    }
+
+    fn capture_warnings(world: &mut WorldContainer) -> Arc<Mutex<Vec<String>>> {
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let warnings_2 = warnings.clone();
+        world.set_warning_handler(move |warning| {
+            warnings_2.lock().unwrap().push(warning.to_string())
+        });
+        warnings
+    }
+
+    #[test]
+    fn resource_scope() {
+        struct Counter(u32);
+        impl Resource for Counter {}
+
+        let mut world = make_world_container();
+        world.add_resource(Counter(1));
+
+        let value = world.resource_scope(|world, counter: &mut Counter| {
+            // The resource is removed while in the scope
+            assert!(world.get_resource::<Counter>().is_none());
+            counter.0 += 1;
+            counter.0
+        });
+        assert_eq!(value, 2);
+        assert_eq!(world.get_resource::<Counter>().unwrap().0, 2);
+
+        // The resource is added back even if the scope panics
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.resource_scope(|_, counter: &mut Counter| {
+                counter.0 = 10;
+                panic!("Scope panicked");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(world.get_resource::<Counter>().unwrap().0, 10);
+    }
+
+    #[test]
+    fn assertive_resource_access() {
+        struct Counter(u32);
+        impl Resource for Counter {}
+
+        let mut world = make_world_container();
+        world.add_resource(Counter(1));
+        world.resource_mut::<Counter>().0 += 1;
+        assert_eq!(world.resource::<Counter>().0, 2);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Resource kecs::world_container::tests::missing_assertive_resource::Counter not found"
+    )]
+    fn missing_assertive_resource() {
+        struct Counter;
+        impl Resource for Counter {}
+
+        let world = make_world_container();
+        world.resource::<Counter>();
+    }
+
+    #[test]
+    #[should_panic(expected = "not found")]
+    fn resource_scope_missing_resource() {
+        struct Counter;
+        impl Resource for Counter {}
+
+        let mut world = make_world_container();
+        world.resource_scope(|_, _: &mut Counter| {});
+    }
+
+    #[test]
+    fn entity_scope_updates_archetype_once() {
+        struct A;
+        struct B;
+        struct C;
+
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        world.add_component(entity, C);
+        let archetypes = world.get_archetype_manager().len();
+
+        world.entity_scope(entity, |scope| {
+            scope.add(A).add(B).remove::<C>();
+        });
+
+        // Applying the edits one by one would also create the archetypes (C, A) and (C, A, B)
+        assert_eq!(world.get_archetype_manager().len(), archetypes + 1);
+        assert!(world.entity_has_component::<A>(entity));
+        assert!(world.entity_has_component::<B>(entity));
+        assert!(!world.entity_has_component::<C>(entity));
+
+        let info = world.get_entity_info(entity).unwrap();
+        let archetype = world
+            .get_archetype_manager()
+            .get_archetype(info.archetype_id)
+            .unwrap();
+        assert_eq!(archetype.components.len(), 2);
+        assert!(archetype.entities.contains(&entity));
+
+        // Replacing a component does not change the archetype
+        let archetype_id = info.archetype_id;
+        world.entity_scope(entity, |scope| {
+            scope.add(A);
+        });
+        assert_eq!(
+            world.get_entity_info(entity).unwrap().archetype_id,
+            archetype_id
+        );
+        assert_eq!(world.get_archetype_manager().len(), archetypes + 1);
+    }
+
+    #[test]
+    fn entity_mut_updates_archetype_on_drop() {
+        #[derive(Debug, PartialEq)]
+        struct A(u32);
+        struct B;
+
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        let archetypes = world.get_archetype_manager().len();
+
+        {
+            let mut entity_mut = world.entity_mut(entity).unwrap();
+            entity_mut.insert(A(1)).insert(B);
+            assert_eq!(entity_mut.id(), entity);
+            assert_eq!(entity_mut.get::<A>(), Some(&A(1)));
+            entity_mut.get_mut::<A>().unwrap().0 = 2;
+        }
+        // Inserting the components one by one would also create the archetype (A)
+        assert_eq!(world.get_archetype_manager().len(), archetypes + 1);
+        assert_eq!(world.get_component::<A>(entity), Some(&A(2)));
+        assert!(world.entity_has_component::<B>(entity));
+
+        world.entity_mut(entity).unwrap().remove::<B>();
+        assert_eq!(world.get_archetype_manager().len(), archetypes + 2);
+        assert!(!world.entity_has_component::<B>(entity));
+
+        world.remove_entity(entity);
+        assert!(world.entity_mut(entity).is_none());
+    }
+
+    #[test]
+    fn contains() {
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        assert!(world.contains(entity));
+
+        world.remove_entity(entity);
+        assert!(!world.contains(entity));
+
+        // The id is reused with a new generation: the stale handle must not be contained
+        let new_entity = world.new_entity();
+        assert_eq!(new_entity.0, entity.0);
+        assert!(world.contains(new_entity));
+        assert!(!world.contains(entity));
+    }
+
+    #[test]
+    #[should_panic(expected = "Tried to add component 'u64' with layout")]
+    fn dynamic_add_checks_layout() {
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        world.add_component(entity, 0u32);
+
+        // Simulate a TypeId collision between two different types
+        let mut blob = TypedBlob::new(0u64);
+        blob.blob_ty_id = TypeId::of::<u32>();
+        blob.type_name = Some("u64");
+        unsafe { world.add_component_from_type_id(entity, blob) };
+    }
+
+    #[test]
+    fn required_components() {
+        #[derive(Default, Debug, PartialEq)]
+        struct A;
+        #[derive(Debug, PartialEq)]
+        struct B(u32);
+        #[derive(Debug, PartialEq)]
+        struct C(u32);
+
+        let mut world = make_world_container();
+        world.register_required::<A, _>(|| B(1));
+        world.register_required::<B, _>(|| C(2));
+        // A cycle must not cause infinite loops
+        world.register_required::<C, _>(A::default);
+
+        let entity = world.new_entity();
+        world.add_component(entity, A);
+        assert_eq!(world.get_component::<B>(entity), Some(&B(1)));
+        assert_eq!(world.get_component::<C>(entity), Some(&C(2)));
+        let info = world.get_entity_info(entity).unwrap();
+        let archetype = world
+            .get_archetype_manager()
+            .get_archetype(info.archetype_id)
+            .unwrap();
+        assert_eq!(archetype.components.len(), 3);
+
+        // The components already on the entity are not overwritten
+        let entity = world.new_entity();
+        world.add_component(entity, C(10));
+        world.add_component(entity, B(5));
+        assert_eq!(world.get_component::<C>(entity), Some(&C(10)));
+        assert!(world.get_component::<A>(entity).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "to destroyed entity")]
+    fn required_components_of_destroyed_entity() {
+        struct A;
+        struct B;
+
+        let mut world = make_world_container();
+        world.register_required::<A, _>(|| -> B {
+            panic!("The required component was constructed")
+        });
+
+        let entity = world.new_entity();
+        world.remove_entity(entity);
+        world.add_component(entity, A);
+    }
+
+    #[test]
+    fn empty_query_warning() {
+        struct Misspelled;
+        struct Position;
+        fn move_system(_: Query<(&Misspelled, &Position)>) {}
+
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        world.add_component(entity, Position);
+        world.set_empty_query_warning(Some(3));
+        let warnings = capture_warnings(&mut world);
+
+        let mut scheduler = GraphScheduler::new();
+        scheduler.add_system(&mut world, move_system);
+        scheduler.execute(&mut world);
+        scheduler.execute(&mut world);
+        assert!(warnings.lock().unwrap().is_empty());
+
+        scheduler.execute(&mut world);
+        {
+            let warnings = warnings.lock().unwrap();
+            assert_eq!(warnings.len(), 1);
+            assert!(warnings[0].contains("move_system"));
+            assert!(warnings[0].contains("Misspelled"));
+            assert!(warnings[0].contains("Position"));
+        }
+
+        // The warning is not repeated while the query keeps matching no entities
+        scheduler.execute(&mut world);
+        assert_eq!(warnings.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn init_resource() {
+        #[derive(Default)]
+        struct Config(u32);
+        impl Resource for Config {}
+        #[derive(Default)]
+        struct NonSendState;
+
+        let mut world = make_world_container();
+        world.init_resource::<Config>();
+        world.init_non_send_resource::<NonSendState>();
+        assert_eq!(world.get_resource::<Config>().unwrap().0, 0);
+
+        // Calling init again doesn't overwrite the existing resources
+        world.get_resource_mut::<Config>().unwrap().0 = 42;
+        world.init_resource::<Config>();
+        world.init_non_send_resource::<NonSendState>();
+        assert_eq!(world.get_resource::<Config>().unwrap().0, 42);
+        assert_eq!(
+            world
+                .iter_resource_ids()
+                .filter(|(_, is_send)| !is_send)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn component_count() {
+        struct A;
+        struct B;
+
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        assert_eq!(world.component_count(entity), 0);
+
+        world.add_component(entity, A);
+        world.add_component(entity, B);
+        assert_eq!(world.component_count(entity), 2);
+
+        // Replacing a component doesn't change the count
+        world.add_component(entity, A);
+        assert_eq!(world.component_count(entity), 2);
+
+        world.remove_component::<A>(entity);
+        assert_eq!(world.component_count(entity), 1);
+
+        world.remove_entity(entity);
+        assert_eq!(world.component_count(entity), 0);
+    }
+
+    #[test]
+    fn panicking_drop_on_replace() {
+        struct Bomb {
+            armed: bool,
+            drops: Arc<AtomicU64>,
+        }
+        impl Drop for Bomb {
+            fn drop(&mut self) {
+                self.drops.fetch_add(1, Ordering::Relaxed);
+                if self.armed {
+                    panic!("Bomb exploded");
+                }
+            }
+        }
+
+        let drops = Arc::new(AtomicU64::new(0));
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        world.add_component(
+            entity,
+            Bomb {
+                armed: true,
+                drops: drops.clone(),
+            },
+        );
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.add_component(
+                entity,
+                Bomb {
+                    armed: false,
+                    drops: drops.clone(),
+                },
+            )
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        assert!(!world.get_component::<Bomb>(entity).unwrap().armed);
+
+        // The new component is dropped exactly once, and the old one is never dropped again
+        drop(world);
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn dynamic_component_access() {
+        struct Position(u32);
+        struct Velocity(u32);
+
+        let mut world = make_world_container();
+        let still = world.new_entity();
+        world.add_component(still, Position(1));
+        let moving = world.new_entity();
+        world.add_component(moving, Position(2));
+        world.add_component(moving, Velocity(3));
+
+        // Look the components up by name, as a scripting layer would
+        let id_of = |name: &str| {
+            world
+                .registered_components()
+                .find(|(_, type_name)| type_name.ends_with(name))
+                .unwrap()
+                .0
+        };
+        let position = id_of("Position");
+        let velocity = id_of("Velocity");
+
+        let mut with_position = world.iter_entities_with(&[position]).collect::<Vec<_>>();
+        with_position.sort();
+        assert_eq!(with_position, vec![still, moving]);
+        assert_eq!(
+            world
+                .iter_entities_with(&[position, velocity])
+                .collect::<Vec<_>>(),
+            vec![moving]
+        );
+
+        let ptr = world.get_component_erased(moving, velocity).unwrap();
+        assert_eq!(unsafe { ptr.deref::<Velocity>() }.0, 3);
+        let ptr = world.get_component_erased(still, position).unwrap();
+        assert_eq!(unsafe { ptr.deref::<Position>() }.0, 1);
+        assert!(world.get_component_erased(still, velocity).is_none());
+    }
+
+    #[test]
+    fn clone_entity() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Name(String);
+        #[derive(Clone, Debug, PartialEq)]
+        struct Health(u32);
+        struct Unique;
+
+        let mut world = make_world_container();
+        let warnings = capture_warnings(&mut world);
+        world.register_clonable::<Name>();
+        world.register_clonable::<Health>();
+        let src = world.new_entity();
+        world.add_component(src, Name("Goblin".to_string()));
+        world.add_component(src, Health(10));
+        world.add_component(src, Unique);
+
+        let copy = world.clone_entity(src).unwrap();
+        assert_ne!(copy, src);
+        assert_eq!(
+            world.get_component::<Name>(copy),
+            Some(&Name("Goblin".to_string()))
+        );
+        assert_eq!(world.get_component::<Health>(copy), Some(&Health(10)));
+        assert!(world.get_component::<Unique>(copy).is_none());
+        if cfg!(debug_assertions) {
+            let warnings = warnings.lock().unwrap();
+            assert_eq!(warnings.len(), 1);
+            assert!(warnings[0].contains("Unique"));
+        }
+
+        // The copies are independent from the source
+        world.get_component_mut::<Health>(copy).unwrap().0 = 5;
+        assert_eq!(world.get_component::<Health>(src), Some(&Health(10)));
+
+        world.remove_entity(src);
+        assert!(world.clone_entity(src).is_none());
+    }
+
+    #[test]
+    fn get_component_of_destroyed_entity() {
+        struct A(u32);
+
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        world.add_component(entity, A(1));
+        world.remove_entity(entity);
+
+        assert!(world.get_component::<A>(entity).is_none());
+        assert!(world.get_component_mut::<A>(entity).is_none());
+        assert!(!world.entity_has_component::<A>(entity));
+        assert!(world.get_component_changed_tick::<A>(entity).is_none());
+        assert_eq!(world.component_count(entity), 0);
+        assert!(world.entity_mut(entity).is_none());
+
+        // A new entity reusing the same index is not reachable through the old handle
+        let reused = world.new_entity();
+        world.add_component(reused, A(2));
+        assert_eq!(reused.0, entity.0);
+        assert!(world.get_component::<A>(entity).is_none());
+        assert!(world.get_component_mut::<A>(entity).is_none());
+        assert_eq!(world.get_component::<A>(reused).unwrap().0, 2);
+    }
+
+    #[test]
+    fn get_or_spawn() {
+        struct A;
+
+        let mut world = make_world_container();
+        let warnings = capture_warnings(&mut world);
+        let entity = Entity(5, 0);
+        assert!(world.get_or_spawn(entity));
+        assert!(!world.get_or_spawn(entity));
+        world.add_component(entity, A);
+        assert!(world.get_component::<A>(entity).is_some());
+
+        // The skipped indices can still be allocated, but the reserved one is never reused
+        let spawned = (0..10).map(|_| world.new_entity()).collect::<Vec<_>>();
+        assert!(spawned.iter().all(|spawned| spawned.0 != entity.0));
+        assert!(world.get_component::<A>(entity).is_some());
+
+        // A newer generation replaces the live entity, with a warning
+        assert!(warnings.lock().unwrap().is_empty());
+        let newer = Entity(5, 3);
+        assert!(world.get_or_spawn(newer));
+        assert!(!world.contains(entity));
+        assert!(world.contains(newer));
+        assert!(world.get_component::<A>(newer).is_none());
+        assert_eq!(warnings.lock().unwrap().len(), 1);
+
+        // An older generation is stale, and doesn't replace the live entity
+        world.add_component(newer, A);
+        assert!(!world.get_or_spawn(entity));
+        assert!(!world.contains(entity));
+        assert!(world.get_component::<A>(newer).is_some());
+        assert_eq!(warnings.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn iter_all_entities_sorted() {
+        let mut world = make_world_container();
+        let entities = (0..10).map(|_| world.new_entity()).collect::<Vec<_>>();
+        for entity in [entities[2], entities[7], entities[0]] {
+            world.remove_entity(entity);
+        }
+        let respawned = world.new_entity();
+
+        let mut expected = entities
+            .iter()
+            .copied()
+            .filter(|entity| world.contains(*entity))
+            .chain([respawned])
+            .collect::<Vec<_>>();
+        expected.sort();
+        let sorted = world
+            .iter_all_entities_sorted()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn components_of() {
+        struct Position;
+        struct Velocity;
+
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        world.add_component(entity, Velocity);
+        world.add_component(entity, Position);
+        assert_eq!(
+            world.components_of(entity).unwrap(),
+            vec![
+                (
+                    world.get_component_id_assertive::<Velocity>(),
+                    std::any::type_name::<Velocity>()
+                ),
+                (
+                    world.get_component_id_assertive::<Position>(),
+                    std::any::type_name::<Position>()
+                ),
+            ]
+        );
+
+        world.remove_entity(entity);
+        assert!(world.components_of(entity).is_none());
+    }
+
+    #[test]
+    fn registered_components() {
+        struct Position;
+        struct Config;
+        impl Resource for Config {}
+
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        world.add_component(entity, Position);
+        world.add_resource(Config);
+        // SAFETY: The blob was created from a u64
+        unsafe { world.add_component_from_type_id(entity, TypedBlob::new(0u64)) };
+
+        let components = world.registered_components().collect::<Vec<_>>();
+        assert_eq!(
+            components,
+            vec![
+                (
+                    world.get_component_id_assertive::<Position>(),
+                    std::any::type_name::<Position>()
+                ),
+                (
+                    world.get_component_id_assertive::<Config>(),
+                    std::any::type_name::<Config>()
+                ),
+                (world.get_component_id_assertive::<u64>(), "u64"),
+            ]
+        );
+    }
+
+    #[test]
+    fn iter_resource_ids() {
+        struct SendResource;
+        impl Resource for SendResource {}
+        struct NonSendResource;
+        struct Component;
+
+        let mut world = make_world_container();
+        let entity = world.new_entity();
+        world.add_component(entity, Component);
+        world.add_resource(SendResource);
+        world.add_non_send_resource(NonSendResource);
+
+        let mut resources = world.iter_resource_ids().collect::<Vec<_>>();
+        resources.sort();
+        assert_eq!(
+            resources,
+            vec![
+                (world.get_component_id_assertive::<SendResource>(), true),
+                (world.get_component_id_assertive::<NonSendResource>(), false),
+            ]
+        );
+        assert!(resources
+            .iter()
+            .all(|(id, _)| *id != world.get_component_id_assertive::<Component>()));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn stale_archetype_is_skipped_in_debug() {
+        struct A;
+        struct B;
+
+        let mut world = make_world_container();
+        let mut scheduler = GraphScheduler::new();
+        let iterated = Arc::new(AtomicU64::new(0));
+        let iterated_2 = iterated.clone();
+        scheduler.add_system(&mut world, move |query: Query<(&A, &B)>| {
+            let count = query.iter().count() as u64;
+            iterated_2.store(count, Ordering::Relaxed);
+        });
+
+        let stale = world.new_entity();
+        world.add_component(stale, A);
+        world.add_component(stale, B);
+        let entity = world.new_entity();
+        world.add_component(entity, A);
+        world.add_component(entity, B);
+        scheduler.execute(&mut world);
+        assert_eq!(iterated.load(Ordering::Relaxed), 2);
+
+        // Remove the component without updating the entity's archetype
+        assert!(world.take_component::<B>(stale));
+        scheduler.execute(&mut world);
+        assert_eq!(iterated.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,7 @@
+#[test]
+// trybuild needs to invoke cargo, which is not supported by miri
+#[cfg_attr(miri, ignore)]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
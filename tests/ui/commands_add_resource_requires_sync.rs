@@ -0,0 +1,14 @@
+use std::cell::Cell;
+
+use kecs::{Commands, Resource, World};
+
+// Resources added through commands can be accessed in parallel, so Resource requires Sync
+struct Counter(Cell<u32>);
+impl Resource for Counter {}
+
+fn main() {
+    let mut world = World::new();
+    world.add_system(0, |mut commands: Commands| {
+        commands.add_resource(Counter(Cell::new(0)));
+    });
+}
@@ -0,0 +1,15 @@
+use kecs::{Query, World};
+
+struct Position(f32);
+
+fn main() {
+    let mut world = World::new();
+    // Only one mutable iterator can be alive at a time
+    world.add_system(0, |mut query: Query<&mut Position>| {
+        let first = query.iter_mut();
+        let second = query.iter_mut();
+        for (a, b) in first.zip(second) {
+            a.0 += b.0;
+        }
+    });
+}
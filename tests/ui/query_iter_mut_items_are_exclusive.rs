@@ -0,0 +1,15 @@
+use kecs::{Query, World};
+
+struct Position(f32);
+
+fn main() {
+    let mut world = World::new();
+    // The items borrow the query, so they can't alias the items of another iterator
+    world.add_system(0, |mut query: Query<&mut Position>| {
+        let first: Vec<&mut Position> = query.iter_mut().collect();
+        let second: Vec<&mut Position> = query.iter_mut().collect();
+        for (a, b) in first.into_iter().zip(second) {
+            a.0 += b.0;
+        }
+    });
+}
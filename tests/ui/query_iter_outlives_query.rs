@@ -0,0 +1,17 @@
+use kecs::{Query, World};
+
+struct Position(f32);
+
+fn main() {
+    let mut world = World::new();
+    world.add_system(0, |query: Query<&Position>| {
+        // The iterator must not outlive the query it was created from
+        let iter = {
+            let query = query;
+            query.iter()
+        };
+        for position in iter {
+            println!("{}", position.0);
+        }
+    });
+}
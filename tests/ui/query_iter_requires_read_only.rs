@@ -0,0 +1,13 @@
+use kecs::{Query, World};
+
+struct Position(f32);
+
+fn main() {
+    let mut world = World::new();
+    // Mutable queries must be iterated with iter_mut
+    world.add_system(0, |query: Query<&mut Position>| {
+        for position in query.iter() {
+            position.0 += 1.0;
+        }
+    });
+}
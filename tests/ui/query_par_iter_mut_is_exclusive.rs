@@ -0,0 +1,15 @@
+use kecs::{Query, World};
+
+struct Position(f32);
+
+fn main() {
+    let mut world = World::new();
+    // The query can't be iterated again while a parallel iteration is writing to the components
+    world.add_system(0, |mut query: Query<&mut Position>| {
+        query.par_iter_mut(|a| {
+            query.par_iter_mut(|b| {
+                b.0 += a.0;
+            });
+        });
+    });
+}
@@ -0,0 +1,15 @@
+use kecs::{Query, SystemState, World, WorldContainer};
+
+struct Health(Vec<u32>);
+
+fn main() {
+    let mut world = World::new();
+    // The parameters borrow the world, so it can't be changed while a query item is alive
+    world.add_system(0, |world: &mut WorldContainer| {
+        let mut state = SystemState::<Query<&mut Health>>::new(world);
+        let mut query = state.get(world);
+        let health = query.iter_mut().next().unwrap();
+        world.remove_component_from_all::<Health>();
+        health.0.push(4);
+    });
+}